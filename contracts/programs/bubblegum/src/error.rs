@@ -1,5 +1,11 @@
 use anchor_lang::prelude::*;
 
+/// Errors returned by gummyroll while bubblegum modifies a tree, e.g. `RootNotFound` for a stale
+/// proof, `LeafContentsModified` if the asset changed since the proof was fetched, or `TreeFull`.
+/// A failed CPI aborts the transaction with gummyroll's error code, so these reach clients as is.
+/// Their codes overlap with the codes of `BubblegumError`, check which program failed in the logs.
+pub use gummyroll::error::GummyrollError;
+
 #[error_code]
 pub enum BubblegumError {
     #[msg("Asset Owner Does not match")]
@@ -34,4 +40,40 @@ pub enum BubblegumError {
     MintRequestDiscriminatorMismatch,
     #[msg("Something went wrong closing mint request")]
     CloseMintRequestError,
+    #[msg("Tree cannot be closed while mints are approved")]
+    OutstandingMintApprovals,
+    #[msg("Mint is not an asset of this tree")]
+    AssetNotInTree,
+    #[msg("Token account must hold exactly one token of the asset")]
+    InvalidTokenAccount,
+    #[msg("Metadata does not belong to this collection")]
+    CollectionNotFound,
+    #[msg("Collection can only be verified with mint_to_collection_v1 or verify_collection")]
+    CollectionCannotBeVerifiedInThisInstruction,
+    #[msg("Collection is already verified")]
+    AlreadyVerified,
+    #[msg("Collection is already unverified")]
+    AlreadyUnverified,
+    #[msg("Signer is not the update authority or a delegated authority of the collection")]
+    InvalidCollectionAuthority,
+    #[msg("Collection master edition account is invalid")]
+    CollectionMasterEditionAccountInvalid,
+    #[msg("Verified creators must sign the mint")]
+    CreatorDidNotSign,
+    #[msg("Signer is not a creator of the asset")]
+    CreatorNotFound,
+    #[msg("Creator is already verified")]
+    CreatorAlreadyVerified,
+    #[msg("Creator is already unverified")]
+    CreatorAlreadyUnverified,
+    #[msg("Metadata is not mutable")]
+    MetadataImmutable,
+    #[msg("Primary sale can only be flipped to true")]
+    PrimarySaleCanOnlyBeFlippedToTrue,
+    #[msg("Creators can only be unverified by themselves")]
+    CannotUnverifyAnotherCreator,
+    #[msg("Token metadata does not support Token-2022 mints yet")]
+    UnsupportedTokenProgramVersion,
+    #[msg("Mint authority must sign if it is not the tree authority")]
+    MintAuthorityDidNotSign,
 }
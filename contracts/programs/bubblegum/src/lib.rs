@@ -3,28 +3,30 @@ use {
     crate::state::metaplex_anchor::MplTokenMetadata,
     crate::state::{
         leaf_schema::{LeafSchema, Version},
-        metaplex_adapter::{Creator, MetadataArgs, TokenProgramVersion},
+        metaplex_adapter::{Collection, Creator, MetadataArgs, UpdateArgs},
         metaplex_anchor::{MasterEdition, TokenMetadata},
         request::{MintRequest, MINT_REQUEST_SIZE},
-        NFTDecompressionEvent, NewNFTEvent, TreeConfig, Voucher, ASSET_PREFIX, TREE_AUTHORITY_SIZE,
-        VOUCHER_PREFIX, VOUCHER_SIZE,
+        CollectionVerificationEvent, CreatorVerificationEvent, MetadataUpdateEvent,
+        NFTCompressionEvent, NFTDecompressionEvent, NewNFTEvent, TreeConfig, Voucher, ASSET_PREFIX,
+        TREE_AUTHORITY_SIZE, VOUCHER_PREFIX, VOUCHER_SIZE,
     },
     crate::utils::{
-        append_leaf, assert_metadata_is_mpl_compatible, assert_pubkey_equal, cmp_bytes,
-        cmp_pubkeys, get_asset_id, replace_leaf,
+        append_leaf, assert_derivation, assert_has_collection_authority,
+        assert_metadata_is_mpl_compatible, assert_owned_by, assert_pubkey_equal,
+        assert_token_program_version_supported, cmp_bytes, cmp_pubkeys, get_asset_id,
+        get_metadata_args, replace_leaf,
     },
     anchor_lang::{
         prelude::*,
         solana_program::{
             keccak,
             program::{invoke, invoke_signed},
-            program_error::ProgramError,
             program_pack::Pack,
             system_instruction,
         },
     },
     gummyroll::{program::Gummyroll, state::CandyWrapper, utils::wrap_event, Node},
-    spl_token::state::Mint as SplMint,
+    spl_token::state::{Account as SplAccount, Mint as SplMint},
 };
 
 pub mod error;
@@ -262,17 +264,17 @@ pub struct DecompressV1<'info> {
 #[derive(Accounts)]
 pub struct Compress<'info> {
     #[account(
+        mut,
         seeds = [merkle_slab.key().as_ref()],
         bump,
     )]
-    /// CHECK: This account is neither written to nor read from.
-    pub authority: UncheckedAccount<'info>,
-    /// CHECK: This account is not read
+    pub authority: Account<'info, TreeConfig>,
+    #[account(mut)]
+    /// CHECK: This account is modified in the downstream program
     pub merkle_slab: UncheckedAccount<'info>,
     /// CHECK: This account is checked in the instruction
+    #[account(mut)]
     pub owner: Signer<'info>,
-    /// CHECK: This account is chekced in the instruction
-    pub delegate: UncheckedAccount<'info>,
     /// CHECK: versioning is handled in the instruction
     #[account(mut)]
     pub token_account: AccountInfo<'info>,
@@ -294,6 +296,108 @@ pub struct Compress<'info> {
     pub gummyroll_program: Program<'info, Gummyroll>,
 }
 
+#[derive(Accounts)]
+pub struct MintToCollectionV1<'info> {
+    /// CHECK: This is checked in the instruction. Must be signer if it is not equal to the `authority`
+    pub mint_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [merkle_slab.key().as_ref()],
+        bump,
+    )]
+    pub authority: Account<'info, TreeConfig>,
+    pub candy_wrapper: Program<'info, CandyWrapper>,
+    pub gummyroll_program: Program<'info, Gummyroll>,
+    /// CHECK: This account is neither written to nor read from.
+    pub owner: AccountInfo<'info>,
+    /// CHECK: This account is neither written to nor read from.
+    pub delegate: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds=[merkle_slab.key().as_ref(), mint_authority.key().as_ref()],
+        bump,
+    )]
+    pub mint_authority_request: Account<'info, MintRequest>,
+    #[account(mut)]
+    /// CHECK: unsafe
+    pub merkle_slab: UncheckedAccount<'info>,
+    pub collection_authority: Signer<'info>,
+    /// CHECK: This account is checked in the instruction. Pass the bubblegum program id if the
+    /// `collection_authority` is the update authority of the collection
+    pub collection_authority_record_pda: UncheckedAccount<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub collection_mint: UncheckedAccount<'info>,
+    pub collection_metadata: Box<Account<'info, TokenMetadata>>,
+    pub edition_account: Box<Account<'info, MasterEdition>>,
+}
+
+#[derive(Accounts)]
+pub struct CollectionVerification<'info> {
+    #[account(
+        seeds = [merkle_slab.key().as_ref()],
+        bump,
+    )]
+    pub authority: Account<'info, TreeConfig>,
+    /// CHECK: This account is checked in the instruction
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub delegate: UncheckedAccount<'info>,
+    pub collection_authority: Signer<'info>,
+    /// CHECK: This account is checked in the instruction. Pass the bubblegum program id if the
+    /// `collection_authority` is the update authority of the collection
+    pub collection_authority_record_pda: UncheckedAccount<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub collection_mint: UncheckedAccount<'info>,
+    pub collection_metadata: Box<Account<'info, TokenMetadata>>,
+    pub edition_account: Box<Account<'info, MasterEdition>>,
+    pub candy_wrapper: Program<'info, CandyWrapper>,
+    pub gummyroll_program: Program<'info, Gummyroll>,
+    #[account(mut)]
+    /// CHECK: This account is modified in the downstream program
+    pub merkle_slab: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreatorVerification<'info> {
+    #[account(
+        seeds = [merkle_slab.key().as_ref()],
+        bump,
+    )]
+    pub authority: Account<'info, TreeConfig>,
+    /// CHECK: This account is checked in the instruction
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub delegate: UncheckedAccount<'info>,
+    pub creator: Signer<'info>,
+    pub candy_wrapper: Program<'info, CandyWrapper>,
+    pub gummyroll_program: Program<'info, Gummyroll>,
+    #[account(mut)]
+    /// CHECK: This account is modified in the downstream program
+    pub merkle_slab: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    #[account(
+        seeds = [merkle_slab.key().as_ref()],
+        bump,
+    )]
+    pub authority: Account<'info, TreeConfig>,
+    #[account(
+        constraint= *update_authority.key == authority.creator || *update_authority.key == authority.delegate
+    )]
+    pub update_authority: Signer<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: This account is checked in the instruction
+    pub delegate: UncheckedAccount<'info>,
+    pub candy_wrapper: Program<'info, CandyWrapper>,
+    pub gummyroll_program: Program<'info, Gummyroll>,
+    #[account(mut)]
+    /// CHECK: This account is modified in the downstream program
+    pub merkle_slab: UncheckedAccount<'info>,
+}
+
 #[derive(Accounts)]
 pub struct SetMintRequest<'info> {
     #[account(
@@ -402,6 +506,26 @@ pub struct SetTreeDelegate<'info> {
     pub tree_authority: Account<'info, TreeConfig>,
 }
 
+#[derive(Accounts)]
+pub struct CloseTree<'info> {
+    #[account(
+        mut,
+        close = recipient,
+        seeds = [merkle_slab.key().as_ref()],
+        bump,
+        has_one = creator
+    )]
+    pub authority: Account<'info, TreeConfig>,
+    pub creator: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: this account only receives the lamports of the closed accounts
+    pub recipient: UncheckedAccount<'info>,
+    pub gummyroll_program: Program<'info, Gummyroll>,
+    #[account(mut)]
+    /// CHECK: This account is checked by gummyroll
+    pub merkle_slab: UncheckedAccount<'info>,
+}
+
 pub fn hash_metadata(metadata: &MetadataArgs) -> Result<[u8; 32]> {
     let metadata_args_hash = keccak::hashv(&[metadata.try_to_vec()?.as_slice()]);
     Ok(keccak::hashv(&[
@@ -411,6 +535,21 @@ pub fn hash_metadata(metadata: &MetadataArgs) -> Result<[u8; 32]> {
     .to_bytes())
 }
 
+pub fn hash_creators(creators: &[Creator]) -> [u8; 32] {
+    let creator_data = creators
+        .iter()
+        .map(|c| [c.address.as_ref(), &[c.verified as u8], &[c.share]].concat())
+        .collect::<Vec<_>>();
+    keccak::hashv(
+        creator_data
+            .iter()
+            .map(|c| c.as_slice())
+            .collect::<Vec<&[u8]>>()
+            .as_ref(),
+    )
+    .to_bytes()
+}
+
 pub enum InstructionName {
     Unknown,
     MintV1,
@@ -421,6 +560,12 @@ pub enum InstructionName {
     DecompressV1,
     Compress,
     Burn,
+    MintToCollectionV1,
+    VerifyCollection,
+    UnverifyCollection,
+    VerifyCreator,
+    UnverifyCreator,
+    UpdateMetadata,
 }
 
 pub fn get_instruction_type(full_bytes: &[u8]) -> InstructionName {
@@ -438,10 +583,34 @@ pub fn get_instruction_type(full_bytes: &[u8]) -> InstructionName {
         [54, 85, 76, 70, 228, 250, 164, 81] => InstructionName::DecompressV1,
         [116, 110, 29, 56, 107, 219, 42, 93] => InstructionName::Burn,
         [82, 193, 176, 117, 176, 21, 115, 253] => InstructionName::Compress,
+        [153, 18, 178, 47, 197, 158, 86, 15] => InstructionName::MintToCollectionV1,
+        [56, 113, 101, 253, 79, 55, 122, 169] => InstructionName::VerifyCollection,
+        [250, 251, 42, 106, 41, 137, 186, 168] => InstructionName::UnverifyCollection,
+        [52, 17, 96, 132, 71, 4, 85, 194] => InstructionName::VerifyCreator,
+        [107, 178, 57, 39, 105, 115, 112, 152] => InstructionName::UnverifyCreator,
+        [170, 182, 43, 239, 97, 78, 225, 186] => InstructionName::UpdateMetadata,
         _ => InstructionName::Unknown,
     }
 }
 
+/// Creators can only be minted as verified if they sign, either as the mint authority or in the
+/// remaining accounts. Other creators verify themselves later with `verify_creator`.
+fn assert_verified_creators_signed<'info>(
+    creators: &[Creator],
+    mint_authority: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    for creator in creators.iter().filter(|c| c.verified) {
+        let signed = std::iter::once(mint_authority)
+            .chain(remaining_accounts.iter())
+            .any(|account| account.is_signer && cmp_pubkeys(account.key, &creator.address));
+        if !signed {
+            return Err(BubblegumError::CreatorDidNotSign.into());
+        }
+    }
+    Ok(())
+}
+
 fn assert_enough_mints_to_approve<'info>(
     authority: &Account<'info, TreeConfig>,
     to_approve: u64,
@@ -463,7 +632,6 @@ fn process_mint_v1<'info>(
     gummyroll_program: &AccountInfo<'info>,
 ) -> Result<()> {
     assert_metadata_is_mpl_compatible(&message)?;
-    // TODO -> Pass collection in check collection authority or collection delegate authority signer
     // TODO -> Separate V1 / V1 into seperate instructions
     // @dev: seller_fee_basis points is encoded twice so that it can be passed to marketplace instructions, without passing the entire, un-hashed MetadataArgs struct
     let metadata_args_hash = keccak::hashv(&[message.try_to_vec()?.as_slice()]);
@@ -471,19 +639,7 @@ fn process_mint_v1<'info>(
         &metadata_args_hash.to_bytes(),
         &message.seller_fee_basis_points.to_le_bytes(),
     ]);
-    let creator_data = message
-        .creators
-        .iter()
-        //TODO include verified
-        .map(|c| [c.address.as_ref(), &[c.share]].concat())
-        .collect::<Vec<_>>();
-    let creator_hash = keccak::hashv(
-        creator_data
-            .iter()
-            .map(|c| c.as_slice())
-            .collect::<Vec<&[u8]>>()
-            .as_ref(),
-    );
+    let creator_hash = hash_creators(&message.creators);
     let asset_id = get_asset_id(&merkle_slab.key(), authority.num_minted);
     let leaf = LeafSchema::new_v0(
         asset_id,
@@ -491,7 +647,7 @@ fn process_mint_v1<'info>(
         delegate,
         authority.num_minted,
         data_hash.to_bytes(),
-        creator_hash.to_bytes(),
+        creator_hash,
     );
     let new_nft = NewNFTEvent {
         version: Version::V1,
@@ -516,6 +672,160 @@ fn process_mint_v1<'info>(
     )
 }
 
+/// Sets the verified flag of the collection of a leaf to `verify`, after checking that the signer
+/// is allowed to verify assets of that collection.
+fn process_collection_verification<'info>(
+    ctx: Context<'_, '_, '_, 'info, CollectionVerification<'info>>,
+    root: [u8; 32],
+    creator_hash: [u8; 32],
+    nonce: u64,
+    index: u32,
+    mut message: MetadataArgs,
+    verify: bool,
+) -> Result<()> {
+    let merkle_slab = ctx.accounts.merkle_slab.to_account_info();
+    let collection_mint = ctx.accounts.collection_mint.key();
+    let previous_data_hash = hash_metadata(&message)?;
+
+    let collection = message
+        .collection
+        .as_mut()
+        .ok_or(BubblegumError::CollectionNotFound)?;
+    if !cmp_pubkeys(&collection.key, &collection_mint) {
+        return Err(BubblegumError::CollectionNotFound.into());
+    }
+    if collection.verified == verify {
+        if verify {
+            return Err(BubblegumError::AlreadyVerified.into());
+        }
+        return Err(BubblegumError::AlreadyUnverified.into());
+    }
+    assert_has_collection_authority(
+        &ctx.accounts.collection_authority.key(),
+        &ctx.accounts.collection_authority_record_pda,
+        &collection_mint,
+        &ctx.accounts.collection_metadata,
+        &ctx.accounts.edition_account.to_account_info(),
+    )?;
+    collection.verified = verify;
+    let new_data_hash = hash_metadata(&message)?;
+
+    let asset_id = get_asset_id(&merkle_slab.key(), nonce);
+    let previous_leaf = LeafSchema::new_v0(
+        asset_id,
+        ctx.accounts.owner.key(),
+        ctx.accounts.delegate.key(),
+        nonce,
+        previous_data_hash,
+        creator_hash,
+    );
+    let new_leaf = LeafSchema::new_v0(
+        asset_id,
+        ctx.accounts.owner.key(),
+        ctx.accounts.delegate.key(),
+        nonce,
+        new_data_hash,
+        creator_hash,
+    );
+    let event = CollectionVerificationEvent {
+        version: Version::V1,
+        id: asset_id,
+        tree_id: merkle_slab.key(),
+        nonce,
+        collection: collection_mint,
+        verified: verify,
+    };
+    emit!(event);
+    wrap_event(event.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+    emit!(new_leaf.to_event());
+    replace_leaf(
+        &merkle_slab.key(),
+        *ctx.bumps.get("authority").unwrap(),
+        &ctx.accounts.gummyroll_program.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &merkle_slab,
+        &ctx.accounts.candy_wrapper.to_account_info(),
+        ctx.remaining_accounts,
+        root,
+        previous_leaf.to_node(),
+        new_leaf.to_node(),
+        index,
+    )
+}
+
+/// Sets the verified flag of the signing creator of a leaf to `verify`, which changes both the
+/// data hash and the creator hash of the leaf.
+fn process_creator_verification<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreatorVerification<'info>>,
+    root: [u8; 32],
+    nonce: u64,
+    index: u32,
+    mut message: MetadataArgs,
+    verify: bool,
+) -> Result<()> {
+    let merkle_slab = ctx.accounts.merkle_slab.to_account_info();
+    let creator_key = ctx.accounts.creator.key();
+    let previous_data_hash = hash_metadata(&message)?;
+    let previous_creator_hash = hash_creators(&message.creators);
+
+    let creator = message
+        .creators
+        .iter_mut()
+        .find(|c| cmp_pubkeys(&c.address, &creator_key))
+        .ok_or(BubblegumError::CreatorNotFound)?;
+    if creator.verified == verify {
+        if verify {
+            return Err(BubblegumError::CreatorAlreadyVerified.into());
+        }
+        return Err(BubblegumError::CreatorAlreadyUnverified.into());
+    }
+    creator.verified = verify;
+    let new_data_hash = hash_metadata(&message)?;
+    let new_creator_hash = hash_creators(&message.creators);
+
+    let asset_id = get_asset_id(&merkle_slab.key(), nonce);
+    let previous_leaf = LeafSchema::new_v0(
+        asset_id,
+        ctx.accounts.owner.key(),
+        ctx.accounts.delegate.key(),
+        nonce,
+        previous_data_hash,
+        previous_creator_hash,
+    );
+    let new_leaf = LeafSchema::new_v0(
+        asset_id,
+        ctx.accounts.owner.key(),
+        ctx.accounts.delegate.key(),
+        nonce,
+        new_data_hash,
+        new_creator_hash,
+    );
+    let event = CreatorVerificationEvent {
+        version: Version::V1,
+        id: asset_id,
+        tree_id: merkle_slab.key(),
+        nonce,
+        creator: creator_key,
+        verified: verify,
+    };
+    emit!(event);
+    wrap_event(event.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+    emit!(new_leaf.to_event());
+    replace_leaf(
+        &merkle_slab.key(),
+        *ctx.bumps.get("authority").unwrap(),
+        &ctx.accounts.gummyroll_program.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &merkle_slab,
+        &ctx.accounts.candy_wrapper.to_account_info(),
+        ctx.remaining_accounts,
+        root,
+        previous_leaf.to_node(),
+        new_leaf.to_node(),
+        index,
+    )
+}
+
 #[program]
 pub mod bubblegum {
     use super::*;
@@ -594,9 +904,40 @@ pub mod bubblegum {
         Ok(())
     }
 
+    /// Closes a tree whose leaves were all burned (or that was never minted to), along with
+    /// its `TreeConfig`, and sends the lamports of both accounts to `recipient`.
+    /// Mint requests must be closed first so that no approved mints are left.
+    ///
+    /// Outstanding `Voucher`s are not tracked by the `TreeConfig` and are not checked here.
+    /// They survive the tree and `decompress_v1` still works on them, but `cancel_redeem`
+    /// needs the tree, so redeemed assets must be decompressed before their tree is closed.
+    pub fn close_tree(ctx: Context<CloseTree>) -> Result<()> {
+        if ctx.accounts.authority.num_mints_approved > 0 {
+            return err!(BubblegumError::OutstandingMintApprovals);
+        }
+        let merkle_slab = ctx.accounts.merkle_slab.to_account_info();
+        let seed = merkle_slab.key();
+        let seeds = &[seed.as_ref(), &[*ctx.bumps.get("authority").unwrap()]];
+        let authority_pda_signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.gummyroll_program.to_account_info(),
+            gummyroll::cpi::accounts::CloseTree {
+                merkle_roll: merkle_slab,
+                authority: ctx.accounts.authority.to_account_info(),
+                recipient: ctx.accounts.recipient.to_account_info(),
+            },
+            authority_pda_signer,
+        );
+        gummyroll::cpi::close_empty_tree(cpi_ctx)
+    }
+
+    /// Mints a compressed NFT to the tree.
+    /// Collections cannot be verified here, use `mint_to_collection_v1` instead.
     pub fn mint_v1(ctx: Context<MintV1>, message: MetadataArgs) -> Result<()> {
-        // TODO -> Pass collection in check collection authority or collection delegate authority signer
         // TODO -> Separate V1 / V1 into seperate instructions
+        if let Some(Collection { verified: true, .. }) = message.collection {
+            return Err(BubblegumError::CollectionCannotBeVerifiedInThisInstruction.into());
+        }
         let owner = ctx.accounts.owner.key();
         let delegate = ctx.accounts.delegate.key();
         let mint_authority = &mut ctx.accounts.mint_authority;
@@ -604,8 +945,74 @@ pub mod bubblegum {
 
         // The mint authority must sign if it is not equal to the tree authority
         if mint_authority.key() != ctx.accounts.authority.key() {
-            assert!(mint_authority.is_signer);
+            if !mint_authority.is_signer {
+                return Err(BubblegumError::MintAuthorityDidNotSign.into());
+            }
         }
+        assert_verified_creators_signed(&message.creators, mint_authority, ctx.remaining_accounts)?;
+
+        let authority = &mut ctx.accounts.authority;
+        let request = &mut ctx.accounts.mint_authority_request;
+
+        request.decrement_approvals()?;
+        process_mint_v1(
+            message,
+            owner,
+            delegate,
+            *ctx.bumps.get("authority").unwrap(),
+            authority,
+            merkle_slab,
+            &ctx.accounts.candy_wrapper,
+            &ctx.accounts.gummyroll_program,
+        )?;
+        if request.num_mints_approved == 0 && request.num_mints_requested == 0 {
+            // Transfer lamports
+            let request_info = request.to_account_info();
+            **mint_authority.lamports.borrow_mut() = mint_authority
+                .lamports()
+                .checked_add(request_info.lamports())
+                .ok_or(BubblegumError::CloseMintRequestError)?;
+            **request_info.lamports.borrow_mut() = 0;
+        }
+        Ok(())
+    }
+
+    /// Mints a compressed NFT whose collection is verified. The `collection_authority` must be the
+    /// update authority of the collection or be approved by it with a collection authority record.
+    pub fn mint_to_collection_v1(
+        ctx: Context<MintToCollectionV1>,
+        metadata_args: MetadataArgs,
+    ) -> Result<()> {
+        let mut message = metadata_args;
+        let owner = ctx.accounts.owner.key();
+        let delegate = ctx.accounts.delegate.key();
+        let mint_authority = &mut ctx.accounts.mint_authority;
+        let merkle_slab = &ctx.accounts.merkle_slab;
+
+        // The mint authority must sign if it is not equal to the tree authority
+        if mint_authority.key() != ctx.accounts.authority.key() {
+            if !mint_authority.is_signer {
+                return Err(BubblegumError::MintAuthorityDidNotSign.into());
+            }
+        }
+        assert_verified_creators_signed(&message.creators, mint_authority, ctx.remaining_accounts)?;
+
+        let collection_mint = ctx.accounts.collection_mint.key();
+        let collection = message
+            .collection
+            .as_mut()
+            .ok_or(BubblegumError::CollectionNotFound)?;
+        if !cmp_pubkeys(&collection.key, &collection_mint) {
+            return Err(BubblegumError::CollectionNotFound.into());
+        }
+        assert_has_collection_authority(
+            &ctx.accounts.collection_authority.key(),
+            &ctx.accounts.collection_authority_record_pda,
+            &collection_mint,
+            &ctx.accounts.collection_metadata,
+            &ctx.accounts.edition_account.to_account_info(),
+        )?;
+        collection.verified = true;
 
         let authority = &mut ctx.accounts.authority;
         let request = &mut ctx.accounts.mint_authority_request;
@@ -864,84 +1271,81 @@ pub mod bubblegum {
             _ => Err(BubblegumError::UnsupportedSchemaVersion),
         }?;
         let voucher = &ctx.accounts.voucher;
-        match metadata.token_program_version {
-            TokenProgramVersion::Original => {
-                if ctx.accounts.mint.data_is_empty() {
-                    invoke_signed(
-                        &system_instruction::create_account(
-                            &ctx.accounts.owner.key(),
-                            &ctx.accounts.mint.key(),
-                            Rent::get()?.minimum_balance(SplMint::LEN),
-                            SplMint::LEN as u64,
-                            &spl_token::id(),
-                        ),
-                        &[
-                            ctx.accounts.owner.to_account_info(),
-                            ctx.accounts.mint.to_account_info(),
-                            ctx.accounts.system_program.to_account_info(),
-                        ],
-                        &[&[
-                            ASSET_PREFIX.as_bytes(),
-                            voucher.merkle_slab.key().as_ref(),
-                            voucher.leaf_schema.nonce().to_le_bytes().as_ref(),
-                            &[*ctx.bumps.get("mint").unwrap()],
-                        ]],
-                    )?;
-                    invoke(
-                        &spl_token::instruction::initialize_mint2(
-                            &spl_token::id(),
-                            &ctx.accounts.mint.key(),
-                            &ctx.accounts.mint_authority.key(),
-                            None,
-                            0,
-                        )?,
-                        &[
-                            ctx.accounts.token_program.to_account_info(),
-                            ctx.accounts.mint.to_account_info(),
-                        ],
-                    )?;
-                }
-                if ctx.accounts.token_account.data_is_empty() {
-                    invoke(
-                        &spl_associated_token_account::instruction::create_associated_token_account(
-                            &ctx.accounts.owner.key(),
-                            &ctx.accounts.owner.key(),
-                            &ctx.accounts.mint.key(),
-                        ),
-                        &[
-                            ctx.accounts.owner.to_account_info(),
-                            ctx.accounts.mint.to_account_info(),
-                            ctx.accounts.token_account.to_account_info(),
-                            ctx.accounts.token_program.to_account_info(),
-                            ctx.accounts.associated_token_program.to_account_info(),
-                            ctx.accounts.system_program.to_account_info(),
-                            ctx.accounts.sysvar_rent.to_account_info(),
-                        ],
-                    )?;
-                }
-                invoke_signed(
-                    &spl_token::instruction::mint_to(
-                        &spl_token::id(),
-                        &ctx.accounts.mint.key(),
-                        &ctx.accounts.token_account.key(),
-                        &ctx.accounts.mint_authority.key(),
-                        &[],
-                        1,
-                    )?,
-                    &[
-                        ctx.accounts.mint.to_account_info(),
-                        ctx.accounts.token_account.to_account_info(),
-                        ctx.accounts.mint_authority.to_account_info(),
-                        ctx.accounts.token_program.to_account_info(),
-                    ],
-                    &[&[
-                        ctx.accounts.mint.key().as_ref(),
-                        &[ctx.bumps["mint_authority"]],
-                    ]],
-                )?;
-            }
-            TokenProgramVersion::Token2022 => return Err(ProgramError::InvalidArgument.into()),
+        assert_token_program_version_supported(metadata.token_program_version)?;
+        assert_pubkey_equal(&ctx.accounts.token_program.key(), &spl_token::id(), None)?;
+        if ctx.accounts.mint.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    &ctx.accounts.owner.key(),
+                    &ctx.accounts.mint.key(),
+                    Rent::get()?.minimum_balance(SplMint::LEN),
+                    SplMint::LEN as u64,
+                    &spl_token::id(),
+                ),
+                &[
+                    ctx.accounts.owner.to_account_info(),
+                    ctx.accounts.mint.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[&[
+                    ASSET_PREFIX.as_bytes(),
+                    voucher.merkle_slab.key().as_ref(),
+                    voucher.leaf_schema.nonce().to_le_bytes().as_ref(),
+                    &[*ctx.bumps.get("mint").unwrap()],
+                ]],
+            )?;
+            invoke(
+                &spl_token::instruction::initialize_mint2(
+                    &spl_token::id(),
+                    &ctx.accounts.mint.key(),
+                    &ctx.accounts.mint_authority.key(),
+                    None,
+                    0,
+                )?,
+                &[
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.mint.to_account_info(),
+                ],
+            )?;
+        }
+        if ctx.accounts.token_account.data_is_empty() {
+            invoke(
+                &spl_associated_token_account::instruction::create_associated_token_account(
+                    &ctx.accounts.owner.key(),
+                    &ctx.accounts.owner.key(),
+                    &ctx.accounts.mint.key(),
+                ),
+                &[
+                    ctx.accounts.owner.to_account_info(),
+                    ctx.accounts.mint.to_account_info(),
+                    ctx.accounts.token_account.to_account_info(),
+                    ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.associated_token_program.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    ctx.accounts.sysvar_rent.to_account_info(),
+                ],
+            )?;
         }
+        invoke_signed(
+            &spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &ctx.accounts.mint.key(),
+                &ctx.accounts.token_account.key(),
+                &ctx.accounts.mint_authority.key(),
+                &[],
+                1,
+            )?,
+            &[
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.token_account.to_account_info(),
+                ctx.accounts.mint_authority.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ],
+            &[&[
+                ctx.accounts.mint.key().as_ref(),
+                &[ctx.bumps["mint_authority"]],
+            ]],
+        )?;
 
         let metadata_infos = vec![
             ctx.accounts.metadata.to_account_info(),
@@ -979,7 +1383,15 @@ pub mod bubblegum {
                 metadata.symbol.clone(),
                 metadata.uri.clone(),
                 if metadata.creators.len() > 0 {
-                    let mut amended_metadata_creators = metadata.creators;
+                    // Creators verify themselves in token metadata with sign_metadata
+                    let mut amended_metadata_creators = metadata
+                        .creators
+                        .iter()
+                        .map(|c| Creator {
+                            verified: false,
+                            ..*c
+                        })
+                        .collect::<Vec<_>>();
                     amended_metadata_creators.push(Creator {
                         address: ctx.accounts.mint_authority.key(),
                         verified: true,
@@ -997,8 +1409,12 @@ pub mod bubblegum {
                 metadata.seller_fee_basis_points,
                 true,
                 metadata.is_mutable,
+                // Token metadata only verifies collections in verify_collection
                 match metadata.collection {
-                    Some(c) => Some(c.adapt()),
+                    Some(c) => Some(mpl_token_metadata::state::Collection {
+                        verified: false,
+                        key: c.key,
+                    }),
                     None => None,
                 },
                 match metadata.uses {
@@ -1035,8 +1451,250 @@ pub mod bubblegum {
         Ok(())
     }
 
-    pub fn compress(_ctx: Context<Compress>) -> Result<()> {
-        // TODO
+    /// Burns an asset that was decompressed from this tree and appends it back to the tree.
+    ///
+    /// `nonce` is the nonce of the asset when it was decompressed, its mint must be the asset id.
+    /// The token is burned and its account closed, returning the rent to `owner`.
+    /// The asset is minted to `owner` again with a new nonce and `owner` as delegate, from
+    /// `MetadataArgs` rebuilt from its metadata, so it uses up one mint of the tree capacity.
+    ///
+    /// The metadata and master edition accounts stay open and keep their rent: token metadata
+    /// 1.3.3 has no instruction that closes them (`BurnNft` only exists in later versions), and
+    /// only token metadata can write to them. The mint stays open as well, since the SPL Token
+    /// program cannot close mints. Those accounts only describe a mint with no supply. The asset
+    /// gets a new id from its new nonce, so it can still be decompressed again into a new mint.
+    pub fn compress(ctx: Context<Compress>, nonce: u64) -> Result<()> {
+        let merkle_slab = &ctx.accounts.merkle_slab;
+        let owner = ctx.accounts.owner.key();
+        let mint = ctx.accounts.mint.key();
+        // Only assets of this tree can be compressed back into it
+        if !cmp_pubkeys(&mint, &get_asset_id(&merkle_slab.key(), nonce)) {
+            return Err(BubblegumError::AssetNotInTree.into());
+        }
+        assert_pubkey_equal(&ctx.accounts.metadata.mint, &mint, None)?;
+        assert_derivation(
+            &mpl_token_metadata::id(),
+            &ctx.accounts.master_edition.to_account_info(),
+            &[
+                mpl_token_metadata::state::PREFIX.as_bytes(),
+                mpl_token_metadata::id().as_ref(),
+                mint.as_ref(),
+                mpl_token_metadata::state::EDITION.as_bytes(),
+            ],
+            Some(BubblegumError::PublicKeyMismatch.into()),
+        )?;
+        assert_pubkey_equal(&ctx.accounts.token_program.key(), &spl_token::id(), None)?;
+        assert_owned_by(&ctx.accounts.token_account, &spl_token::id())?;
+        let token_account = SplAccount::unpack(&ctx.accounts.token_account.try_borrow_data()?)?;
+        if !cmp_pubkeys(&token_account.owner, &owner) {
+            return Err(BubblegumError::AssetOwnerMismatch.into());
+        }
+        if !cmp_pubkeys(&token_account.mint, &mint) || token_account.amount != 1 {
+            return Err(BubblegumError::InvalidTokenAccount.into());
+        }
+        assert_enough_mints_to_approve(&ctx.accounts.authority, 1)?;
+
+        invoke(
+            &spl_token::instruction::burn(
+                &spl_token::id(),
+                &ctx.accounts.token_account.key(),
+                &mint,
+                &owner,
+                &[],
+                1,
+            )?,
+            &[
+                ctx.accounts.token_account.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.owner.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ],
+        )?;
+        invoke(
+            &spl_token::instruction::close_account(
+                &spl_token::id(),
+                &ctx.accounts.token_account.key(),
+                &owner,
+                &owner,
+                &[],
+            )?,
+            &[
+                ctx.accounts.token_account.to_account_info(),
+                ctx.accounts.owner.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ],
+        )?;
+
+        let (mint_authority, _) = Pubkey::find_program_address(&[mint.as_ref()], &crate::id());
+        let metadata = get_metadata_args(&ctx.accounts.metadata, &mint_authority);
+        let authority = &mut ctx.accounts.authority;
+        let event = NFTCompressionEvent {
+            version: Version::V1,
+            mint,
+            id: get_asset_id(&merkle_slab.key(), authority.num_minted),
+            tree_id: merkle_slab.key(),
+            nonce: authority.num_minted,
+        };
+        process_mint_v1(
+            metadata,
+            owner,
+            owner,
+            *ctx.bumps.get("authority").unwrap(),
+            authority,
+            merkle_slab,
+            &ctx.accounts.candy_wrapper,
+            &ctx.accounts.gummyroll_program,
+        )?;
+        emit!(event);
         Ok(())
     }
+
+    /// Verifies the collection of a leaf. `message` holds the current metadata of the asset and
+    /// is used to recompute its data hash. The `collection_authority` must be the update
+    /// authority of the collection or be approved by it with a collection authority record.
+    pub fn verify_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, CollectionVerification<'info>>,
+        root: [u8; 32],
+        creator_hash: [u8; 32],
+        nonce: u64,
+        index: u32,
+        message: MetadataArgs,
+    ) -> Result<()> {
+        process_collection_verification(ctx, root, creator_hash, nonce, index, message, true)
+    }
+
+    /// Unverifies the collection of a leaf, see `verify_collection`.
+    pub fn unverify_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, CollectionVerification<'info>>,
+        root: [u8; 32],
+        creator_hash: [u8; 32],
+        nonce: u64,
+        index: u32,
+        message: MetadataArgs,
+    ) -> Result<()> {
+        process_collection_verification(ctx, root, creator_hash, nonce, index, message, false)
+    }
+
+    /// Verifies the signing creator of a leaf. `message` holds the current metadata of the asset
+    /// and is used to recompute its data hash and creator hash.
+    pub fn verify_creator<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatorVerification<'info>>,
+        root: [u8; 32],
+        nonce: u64,
+        index: u32,
+        message: MetadataArgs,
+    ) -> Result<()> {
+        process_creator_verification(ctx, root, nonce, index, message, true)
+    }
+
+    /// Unverifies the signing creator of a leaf, see `verify_creator`.
+    pub fn unverify_creator<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatorVerification<'info>>,
+        root: [u8; 32],
+        nonce: u64,
+        index: u32,
+        message: MetadataArgs,
+    ) -> Result<()> {
+        process_creator_verification(ctx, root, nonce, index, message, false)
+    }
+
+    /// Updates the metadata of a mutable asset. `current_metadata` holds the metadata of the
+    /// asset and is checked against its leaf. Only the tree creator or the tree delegate can
+    /// update metadata, and creators cannot be verified or unverified this way.
+    pub fn update_metadata<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateMetadata<'info>>,
+        root: [u8; 32],
+        nonce: u64,
+        index: u32,
+        current_metadata: MetadataArgs,
+        update_args: UpdateArgs,
+    ) -> Result<()> {
+        if !current_metadata.is_mutable {
+            return Err(BubblegumError::MetadataImmutable.into());
+        }
+        let mut metadata = current_metadata.clone();
+        if let Some(name) = update_args.name {
+            metadata.name = name;
+        }
+        if let Some(symbol) = update_args.symbol {
+            metadata.symbol = symbol;
+        }
+        if let Some(uri) = update_args.uri {
+            metadata.uri = uri;
+        }
+        if let Some(seller_fee_basis_points) = update_args.seller_fee_basis_points {
+            metadata.seller_fee_basis_points = seller_fee_basis_points;
+        }
+        if let Some(primary_sale_happened) = update_args.primary_sale_happened {
+            if !primary_sale_happened && current_metadata.primary_sale_happened {
+                return Err(BubblegumError::PrimarySaleCanOnlyBeFlippedToTrue.into());
+            }
+            metadata.primary_sale_happened = primary_sale_happened;
+        }
+        if let Some(is_mutable) = update_args.is_mutable {
+            metadata.is_mutable = is_mutable;
+        }
+        if let Some(creators) = update_args.creators {
+            // Verified creators keep their verification, see `verify_creator`
+            let was_verified = |address: &Pubkey, creators: &[Creator]| {
+                creators
+                    .iter()
+                    .any(|c| c.verified && cmp_pubkeys(&c.address, address))
+            };
+            for creator in creators.iter() {
+                if creator.verified && !was_verified(&creator.address, &current_metadata.creators) {
+                    return Err(BubblegumError::CreatorDidNotSign.into());
+                }
+            }
+            for creator in current_metadata.creators.iter() {
+                if creator.verified && !was_verified(&creator.address, &creators) {
+                    return Err(BubblegumError::CannotUnverifyAnotherCreator.into());
+                }
+            }
+            metadata.creators = creators;
+        }
+        assert_metadata_is_mpl_compatible(&metadata)?;
+
+        let merkle_slab = ctx.accounts.merkle_slab.to_account_info();
+        let asset_id = get_asset_id(&merkle_slab.key(), nonce);
+        let previous_leaf = LeafSchema::new_v0(
+            asset_id,
+            ctx.accounts.owner.key(),
+            ctx.accounts.delegate.key(),
+            nonce,
+            hash_metadata(&current_metadata)?,
+            hash_creators(&current_metadata.creators),
+        );
+        let new_leaf = LeafSchema::new_v0(
+            asset_id,
+            ctx.accounts.owner.key(),
+            ctx.accounts.delegate.key(),
+            nonce,
+            hash_metadata(&metadata)?,
+            hash_creators(&metadata.creators),
+        );
+        let event = MetadataUpdateEvent {
+            version: Version::V1,
+            id: asset_id,
+            tree_id: merkle_slab.key(),
+            nonce,
+            metadata,
+        };
+        emit!(event);
+        wrap_event(event.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+        emit!(new_leaf.to_event());
+        replace_leaf(
+            &merkle_slab.key(),
+            *ctx.bumps.get("authority").unwrap(),
+            &ctx.accounts.gummyroll_program.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            &merkle_slab,
+            &ctx.accounts.candy_wrapper.to_account_info(),
+            ctx.remaining_accounts,
+            root,
+            previous_leaf.to_node(),
+            new_leaf.to_node(),
+            index,
+        )
+    }
 }
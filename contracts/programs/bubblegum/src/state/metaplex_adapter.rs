@@ -24,6 +24,16 @@ impl Creator {
     }
 }
 
+impl From<&mpl_token_metadata::state::Creator> for Creator {
+    fn from(creator: &mpl_token_metadata::state::Creator) -> Self {
+        Self {
+            address: creator.address,
+            verified: creator.verified,
+            share: creator.share,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Debug, Clone)]
 pub enum TokenStandard {
     NonFungible,        // This is a master edition
@@ -32,6 +42,19 @@ pub enum TokenStandard {
     NonFungibleEdition, // This is a limited edition
 }
 
+impl From<&mpl_token_metadata::state::TokenStandard> for TokenStandard {
+    fn from(token_standard: &mpl_token_metadata::state::TokenStandard) -> Self {
+        match token_standard {
+            mpl_token_metadata::state::TokenStandard::NonFungible => TokenStandard::NonFungible,
+            mpl_token_metadata::state::TokenStandard::FungibleAsset => TokenStandard::FungibleAsset,
+            mpl_token_metadata::state::TokenStandard::Fungible => TokenStandard::Fungible,
+            mpl_token_metadata::state::TokenStandard::NonFungibleEdition => {
+                TokenStandard::NonFungibleEdition
+            }
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Debug, Clone)]
 pub enum UseMethod {
     Burn,
@@ -61,6 +84,20 @@ impl Uses {
     }
 }
 
+impl From<&mpl_token_metadata::state::Uses> for Uses {
+    fn from(uses: &mpl_token_metadata::state::Uses) -> Self {
+        Self {
+            use_method: match uses.use_method {
+                mpl_token_metadata::state::UseMethod::Burn => UseMethod::Burn,
+                mpl_token_metadata::state::UseMethod::Multiple => UseMethod::Multiple,
+                mpl_token_metadata::state::UseMethod::Single => UseMethod::Single,
+            },
+            remaining: uses.remaining,
+            total: uses.total,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Debug, Clone)]
 pub struct Collection {
@@ -77,6 +114,15 @@ impl Collection {
     }
 }
 
+impl From<&mpl_token_metadata::state::Collection> for Collection {
+    fn from(collection: &mpl_token_metadata::state::Collection) -> Self {
+        Self {
+            verified: collection.verified,
+            key: collection.key,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Clone)]
 pub struct MetadataArgs {
     /// The name of the asset
@@ -102,3 +148,17 @@ pub struct MetadataArgs {
     pub token_program_version: TokenProgramVersion,
     pub creators: Vec<Creator>,
 }
+
+/// Fields of `MetadataArgs` that `update_metadata` can change, `None` keeps the current value
+#[derive(AnchorSerialize, AnchorDeserialize, PartialEq, Clone)]
+pub struct UpdateArgs {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub uri: Option<String>,
+    pub creators: Option<Vec<Creator>>,
+    pub seller_fee_basis_points: Option<u16>,
+    /// Can only be flipped from false to true
+    pub primary_sale_happened: Option<bool>,
+    /// Setting it to false prevents any further update
+    pub is_mutable: Option<bool>,
+}
@@ -74,3 +74,47 @@ pub struct NFTDecompressionEvent {
     pub tree_id: Pubkey,
     pub nonce: u64,
 }
+
+#[event]
+pub struct NFTCompressionEvent {
+    pub version: Version,
+    /// Mint of the burned token
+    pub mint: Pubkey,
+    pub id: Pubkey,
+    pub tree_id: Pubkey,
+    pub nonce: u64,
+}
+
+/// Emitted whenever the collection of an asset is verified or unverified in place.
+/// Assets minted with `mint_to_collection_v1` are reported by `NewNFTEvent` instead.
+#[event]
+pub struct CollectionVerificationEvent {
+    pub version: Version,
+    pub id: Pubkey,
+    pub tree_id: Pubkey,
+    pub nonce: u64,
+    /// Mint of the collection
+    pub collection: Pubkey,
+    pub verified: bool,
+}
+
+/// Emitted whenever a creator of an asset is verified or unverified.
+#[event]
+pub struct CreatorVerificationEvent {
+    pub version: Version,
+    pub id: Pubkey,
+    pub tree_id: Pubkey,
+    pub nonce: u64,
+    pub creator: Pubkey,
+    pub verified: bool,
+}
+
+/// Emitted with the full metadata of an asset after `update_metadata`
+#[event]
+pub struct MetadataUpdateEvent {
+    pub version: Version,
+    pub id: Pubkey,
+    pub tree_id: Pubkey,
+    pub nonce: u64,
+    pub metadata: MetadataArgs,
+}
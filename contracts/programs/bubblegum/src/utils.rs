@@ -1,12 +1,15 @@
 use {
     crate::error::BubblegumError,
-    crate::state::metaplex_adapter::MetadataArgs,
+    crate::state::metaplex_adapter::{
+        Collection, Creator, MetadataArgs, TokenProgramVersion, TokenStandard, Uses,
+    },
     crate::ASSET_PREFIX,
     anchor_lang::{
         prelude::*, solana_program::program_memory::sol_memcmp,
         solana_program::pubkey::PUBKEY_BYTES,
     },
     gummyroll::Node,
+    mpl_token_metadata::state::{Key, Metadata, COLLECTION_AUTHORITY, EDITION, PREFIX},
 };
 
 /// Assert that the provided MetadataArgs are compatible with MPL `Data`
@@ -50,6 +53,37 @@ pub fn assert_metadata_is_mpl_compatible(metadata: &MetadataArgs) -> Result<()>
     Ok(())
 }
 
+/// Rebuilds the `MetadataArgs` of a decompressed asset from its token metadata.
+/// `decompression_creator` is the mint authority that `decompress_v1` adds as a creator,
+/// it is left out.
+pub fn get_metadata_args(metadata: &Metadata, decompression_creator: &Pubkey) -> MetadataArgs {
+    // Token metadata pads strings with null bytes up to their max length
+    let unpad = |s: &str| s.trim_end_matches(char::from(0)).to_string();
+    MetadataArgs {
+        name: unpad(&metadata.data.name),
+        symbol: unpad(&metadata.data.symbol),
+        uri: unpad(&metadata.data.uri),
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        primary_sale_happened: metadata.primary_sale_happened,
+        is_mutable: metadata.is_mutable,
+        edition_nonce: metadata.edition_nonce,
+        token_standard: metadata.token_standard.as_ref().map(TokenStandard::from),
+        collection: metadata.collection.as_ref().map(Collection::from),
+        uses: metadata.uses.as_ref().map(Uses::from),
+        token_program_version: TokenProgramVersion::Original,
+        creators: metadata
+            .data
+            .creators
+            .iter()
+            .flatten()
+            .filter(|creator| !cmp_pubkeys(&creator.address, decompression_creator))
+            .map(Creator::from)
+            .collect(),
+    }
+}
+
+/// Replaces `previous_leaf` with `new_leaf` through a CPI into gummyroll.
+/// Fails with the matching `GummyrollError` if the proof is stale or the leaf has changed.
 pub fn replace_leaf<'info>(
     seed: &Pubkey,
     bump: u8,
@@ -78,6 +112,8 @@ pub fn replace_leaf<'info>(
     gummyroll::cpi::replace_leaf(cpi_ctx, root_node, previous_leaf, new_leaf, index)
 }
 
+/// Appends `leaf_node` through a CPI into gummyroll.
+/// Fails with `GummyrollError::TreeFull` if the tree is at capacity.
 pub fn append_leaf<'info>(
     seed: &Pubkey,
     bump: u8,
@@ -101,6 +137,74 @@ pub fn append_leaf<'info>(
     gummyroll::cpi::append(cpi_ctx, leaf_node)
 }
 
+/// Fails for assets that cannot be decompressed yet. Token metadata 1.3.3 only creates metadata
+/// and master editions for mints owned by SPL Token, so `Token2022` stays rejected until the
+/// token metadata dependency is bumped to a version that accepts Token-2022 mints.
+pub fn assert_token_program_version_supported(version: TokenProgramVersion) -> Result<()> {
+    match version {
+        TokenProgramVersion::Original => Ok(()),
+        TokenProgramVersion::Token2022 => {
+            Err(BubblegumError::UnsupportedTokenProgramVersion.into())
+        }
+    }
+}
+
+/// Checks that `collection_authority` can verify assets of the collection minted by
+/// `collection_mint`, as token metadata does for `verify_collection`.
+///
+/// The collection must be a master edition, and the authority must either be the update authority
+/// of the collection metadata or have been approved with a collection authority record.
+/// Pass the bubblegum program id as `collection_authority_record` when there is no record.
+pub fn assert_has_collection_authority(
+    collection_authority: &Pubkey,
+    collection_authority_record: &AccountInfo,
+    collection_mint: &Pubkey,
+    collection_metadata: &Metadata,
+    edition_account: &AccountInfo,
+) -> Result<()> {
+    if !cmp_pubkeys(&collection_metadata.mint, collection_mint) {
+        return Err(BubblegumError::CollectionNotFound.into());
+    }
+    assert_derivation(
+        &mpl_token_metadata::id(),
+        edition_account,
+        &[
+            PREFIX.as_bytes(),
+            mpl_token_metadata::id().as_ref(),
+            collection_mint.as_ref(),
+            EDITION.as_bytes(),
+        ],
+        Some(BubblegumError::CollectionMasterEditionAccountInvalid.into()),
+    )?;
+    if cmp_pubkeys(collection_authority_record.key, &crate::id()) {
+        if !cmp_pubkeys(&collection_metadata.update_authority, collection_authority) {
+            return Err(BubblegumError::InvalidCollectionAuthority.into());
+        }
+        return Ok(());
+    }
+    let bump = assert_derivation(
+        &mpl_token_metadata::id(),
+        collection_authority_record,
+        &[
+            PREFIX.as_bytes(),
+            mpl_token_metadata::id().as_ref(),
+            collection_mint.as_ref(),
+            COLLECTION_AUTHORITY.as_bytes(),
+            collection_authority.as_ref(),
+        ],
+        Some(BubblegumError::InvalidCollectionAuthority.into()),
+    )?;
+    // Revoked records are closed, so only initialized records grant authority
+    if !cmp_pubkeys(collection_authority_record.owner, &mpl_token_metadata::id()) {
+        return Err(BubblegumError::InvalidCollectionAuthority.into());
+    }
+    let record = collection_authority_record.try_borrow_data()?;
+    if record.len() < 2 || record[0] != Key::CollectionAuthorityRecord as u8 || record[1] != bump {
+        return Err(BubblegumError::InvalidCollectionAuthority.into());
+    }
+    Ok(())
+}
+
 pub fn cmp_pubkeys(a: &Pubkey, b: &Pubkey) -> bool {
     sol_memcmp(a.as_ref(), b.as_ref(), PUBKEY_BYTES) == 0
 }
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum GumballMachineError {
+    #[msg("Signer is not the authority of the gumball machine")]
+    InvalidAuthority,
+    #[msg("Gumball machine account does not match the size of its header")]
+    InvalidAccountSize,
+    #[msg("Suspicious transaction, dispense must be the only instruction")]
+    SuspiciousTransaction,
+    #[msg("Gumball machine is not live yet")]
+    DispenseNotLive,
+    #[msg("All indices must be initialized before dispensing")]
+    IndicesNotInitialized,
+    #[msg("Cannot dispense more than the max mint size")]
+    MaxMintSizeExceeded,
+    #[msg("There are no remaining NFTs to dispense")]
+    NoItemsRemaining,
+    #[msg("Max items must fit into the merkle tree")]
+    MaxItemsExceedTreeCapacity,
+    #[msg("Creator keys and creator shares must have the same length")]
+    CreatorKeysSharesMismatch,
+    #[msg("Too many creators")]
+    TooManyCreators,
+    #[msg("If specifying creators, shares must sum to 100")]
+    CreatorSharesMustSumTo100,
+    #[msg("Config lines must be a non-zero multiple of the config line size")]
+    InvalidConfigLineLength,
+    #[msg("Config lines are out of bounds")]
+    ConfigLinesOutOfBounds,
+    #[msg("Receiver does not match the gumball machine receiver")]
+    ReceiverMismatch,
+    #[msg("Payment mint does not match the gumball machine mint")]
+    MintMismatch,
+    #[msg("Unsupported config line encode method")]
+    InvalidEncodeMethod,
+    #[msg("Metadata is not valid UTF-8")]
+    InvalidUtf8,
+    #[msg("Numerical overflow")]
+    NumericalOverflow,
+}
@@ -17,13 +17,16 @@ use gummyroll::{
     state::{CandyWrapper, MerkleRollHeader},
 };
 use spl_token::native_mint;
+use std::convert::TryFrom;
 
+pub mod error;
 pub mod state;
+use crate::error::GumballMachineError;
 use crate::state::{GumballCreatorAdapter, NUM_CREATORS};
 
 pub mod utils;
 
-use crate::state::{EncodeMethod, GumballMachineHeader, ZeroCopy};
+use crate::state::{EncodeMethod, GumballMachineHeader};
 use crate::utils::get_metadata_args;
 
 declare_id!("GBALLoMcmimUutWvtNdFFGH5oguS7ghUUV6toQPppuTW");
@@ -190,8 +193,8 @@ fn assert_valid_single_instruction_transaction<'info>(
     let mut fixed_data = [0u8; 2];
     fixed_data.copy_from_slice(&instruction_sysvar[0..2]);
     let num_instructions = u16::from_le_bytes(fixed_data);
-    if num_instructions > 2 {
-        assert!(false, "Suspicious transaction, failing")
+    let is_valid = if num_instructions > 2 {
+        false
     } else if num_instructions == 2 {
         let compute_budget_instruction =
             load_instruction_at_checked(0, instruction_sysvar_account)?;
@@ -199,18 +202,85 @@ fn assert_valid_single_instruction_transaction<'info>(
         let compute_budget_id: Pubkey =
             Pubkey::new(bs58::decode(&COMPUTE_BUDGET_ADDRESS).into_vec().unwrap()[..32].as_ref());
 
-        assert_eq!(compute_budget_instruction.program_id, compute_budget_id);
         let current_instruction = load_instruction_at_checked(1, instruction_sysvar_account)?;
-        assert_eq!(current_instruction.program_id, id());
+        compute_budget_instruction.program_id == compute_budget_id
+            && current_instruction.program_id == id()
     } else if num_instructions == 1 {
         let only_instruction = load_instruction_at_checked(0, instruction_sysvar_account)?;
-        assert_eq!(only_instruction.program_id, id());
-    }
+        only_instruction.program_id == id()
+    } else {
+        true
+    };
     // We should not be executing dispense... from a CPI
+    if !is_valid {
+        msg!("Suspicious transaction, failing");
+        return err!(GumballMachineError::SuspiciousTransaction);
+    }
 
     return Ok(());
 }
 
+#[inline(always)]
+// Checks that the data after the header holds exactly the indices and the config lines of
+// `max_items` NFTs with `extension_len` byte config lines, and splits it between the two
+fn split_config_data(
+    config_data: &mut [u8],
+    max_items: u32,
+    extension_len: u64,
+) -> Result<(&mut [u8], &mut [u8])> {
+    let size = max_items as usize;
+    let index_array_size = std::mem::size_of::<u32>() * size;
+    let expected_len = (extension_len as usize)
+        .checked_mul(size)
+        .and_then(|config_size| config_size.checked_add(index_array_size))
+        .ok_or(GumballMachineError::NumericalOverflow)?;
+    if config_data.len() != expected_len {
+        msg!(
+            "Gumball machine holds {} bytes of indices and config lines, expected {}",
+            config_data.len(),
+            expected_len
+        );
+        return err!(GumballMachineError::InvalidAccountSize);
+    }
+    Ok(config_data.split_at_mut(index_array_size))
+}
+
+#[inline(always)]
+// Builds the creators array of the header, the shares of a non-empty list of creators
+// must sum to 100
+fn get_creators(
+    creator_keys: &[Pubkey],
+    creator_shares: &[u8],
+) -> Result<[GumballCreatorAdapter; NUM_CREATORS]> {
+    if creator_keys.len() != creator_shares.len() {
+        return err!(GumballMachineError::CreatorKeysSharesMismatch);
+    }
+    if creator_keys.len() >= NUM_CREATORS {
+        msg!("Cannot set more than {} creators", NUM_CREATORS - 1);
+        return err!(GumballMachineError::TooManyCreators);
+    }
+    let total_shares: u16 = creator_shares.iter().map(|share| *share as u16).sum();
+    if creator_shares.len() != 0 && total_shares != 100 {
+        return err!(GumballMachineError::CreatorSharesMustSumTo100);
+    }
+    let mut creators: [GumballCreatorAdapter; NUM_CREATORS] = [
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        Default::default(),
+    ];
+    for i in 0..creator_keys.len() {
+        let creator_to_add = GumballCreatorAdapter {
+            address: creator_keys[i],
+            // TODO: metaplex is working on creator verification
+            verified: (0 as u8),
+            share: creator_shares[i],
+        };
+        creators[i] = creator_to_add;
+    }
+    Ok(creators)
+}
+
 #[inline(always)]
 // Preform a fisher_yates shuffle on the array of indices into the config lines data structure. Then return the
 // metadata args corresponding to the chosen config line
@@ -259,8 +329,8 @@ fn fisher_yates_shuffle_and_fetch_nft_metadata<'info>(
         gumball_header.creators,
         nft_index,
         config_line,
-        EncodeMethod::from(gumball_header.config_line_encode_method),
-    );
+        EncodeMethod::try_from(gumball_header.config_line_encode_method)?,
+    )?;
     return Ok(message);
 }
 
@@ -291,39 +361,39 @@ fn find_and_mint_compressed_nfts<'info>(
 
     // Load all data
     let mut gumball_machine_data = gumball_machine.try_borrow_mut_data()?;
-    let (mut header_bytes, config_data) =
-        gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-    let gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
+    let (gumball_header, config_data) = GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
 
     // Cannot dispense before all indices are initialized
-    assert_eq!(
-        gumball_header.max_items,
-        gumball_header.smallest_uninitialized_index
-    );
+    if gumball_header.max_items != gumball_header.smallest_uninitialized_index {
+        return err!(GumballMachineError::IndicesNotInitialized);
+    }
 
     // Cannot dispense more than the max_mint_size
-    assert!(num_items <= gumball_header.max_mint_size);
+    if num_items > gumball_header.max_mint_size {
+        return err!(GumballMachineError::MaxMintSizeExceeded);
+    }
 
     // Cannot dispense before project is live
     let clock = Clock::get()?;
-    assert!(clock.unix_timestamp > gumball_header.go_live_date);
+    if clock.unix_timestamp <= gumball_header.go_live_date {
+        return err!(GumballMachineError::DispenseNotLive);
+    }
 
-    let size = gumball_header.max_items as usize;
-    let index_array_size = std::mem::size_of::<u32>() * size;
-    let config_size = gumball_header.extension_len as usize * size;
     let line_size = gumball_header.extension_len as usize;
-
-    assert!(config_data.len() == index_array_size + config_size);
-    let (indices_data, config_lines_data) = config_data.split_at_mut(index_array_size);
+    let (indices_data, config_lines_data) = split_config_data(
+        config_data,
+        gumball_header.max_items,
+        gumball_header.extension_len,
+    )?;
 
     // TODO: Validate data
 
     let indices = cast_slice_mut::<u8, u32>(indices_data);
     let num_nfts_to_mint: u32 = (num_items).max(1).min(gumball_header.remaining);
-    assert!(
-        num_nfts_to_mint > 0,
-        "There are no remaining NFTs to dispense!"
-    );
+    if num_nfts_to_mint == 0 {
+        msg!("There are no remaining NFTs to dispense!");
+        return err!(GumballMachineError::NoItemsRemaining);
+    }
     for _ in 0..num_nfts_to_mint {
         let message = fisher_yates_shuffle_and_fetch_nft_metadata(
             recent_blockhashes,
@@ -402,43 +472,16 @@ pub mod gumball_machine {
         creator_shares: Vec<u8>,
     ) -> Result<()> {
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, config_data) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
-
-        assert!(
-            max_items <= 1 << max_depth,
-            "Max items must fit into tree of depth {}",
-            max_depth
-        );
-        let size = max_items as usize;
+        let (gumball_header, config_data) =
+            GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
 
-        // Construct creators array
-        let mut creators: [GumballCreatorAdapter; NUM_CREATORS] = [
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            Default::default(),
-        ];
-        assert_eq!(creator_keys.len(), creator_shares.len());
-        assert!(
-            creator_keys.len() < NUM_CREATORS,
-            "Cannot set more than {} creators",
-            NUM_CREATORS
-        );
-        assert!(
-            creator_shares.len() == 0 || creator_shares.iter().sum::<u8>() == 100,
-            "If specifying creators, shares must sum to 100% of royalty allocation."
-        );
-        for i in 0..creator_keys.len() {
-            let creator_to_add = GumballCreatorAdapter {
-                address: creator_keys[i],
-                // TODO: metaplex is working on creator verification
-                verified: (0 as u8),
-                share: creator_shares[i],
-            };
-            creators[i] = creator_to_add;
+        if max_depth >= 32 || max_items > 1 << max_depth {
+            msg!("Max items must fit into tree of depth {}", max_depth);
+            return err!(GumballMachineError::MaxItemsExceedTreeCapacity);
         }
+
+        // Construct creators array
+        let creators = get_creators(&creator_keys, &creator_shares)?;
         *gumball_header = GumballMachineHeader {
             url_base: url_base,
             name_base: name_base,
@@ -466,15 +509,17 @@ pub mod gumball_machine {
             smallest_uninitialized_index: 0,
             _padding: [0; 7],
         };
-        let index_array_size = std::mem::size_of::<u32>() * size;
-        let config_size = extension_len as usize * size;
-        assert!(config_data.len() == index_array_size + config_size);
+        split_config_data(config_data, max_items, extension_len)?;
         let seed = ctx.accounts.gumball_machine.key();
         let seeds = &[seed.as_ref(), &[*ctx.bumps.get("willy_wonka").unwrap()]];
         let authority_pda_signer = &[&seeds[..]];
 
         let is_new_tree = {
             let merkle_bytes = ctx.accounts.merkle_slab.try_borrow_data()?;
+            if merkle_bytes.len() < std::mem::size_of::<MerkleRollHeader>() {
+                msg!("Merkle slab is too small to hold a merkle roll header");
+                return err!(GumballMachineError::InvalidAccountSize);
+            }
             let (header_bytes, _) = merkle_bytes.split_at(std::mem::size_of::<MerkleRollHeader>());
             let header = Box::new(MerkleRollHeader::try_from_slice(&header_bytes)?);
             header.authority == Pubkey::default() && header.creation_slot == 0
@@ -529,17 +574,18 @@ pub mod gumball_machine {
     pub fn initialize_indices_chunk(ctx: Context<InitIndices>) -> Result<()> {
         // Fetch mutable header data
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, config_data) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let mut gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
+        let (gumball_header, config_data) =
+            GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
 
         // Assert that indices initialization is authorized
-        assert_eq!(gumball_header.authority, ctx.accounts.authority.key());
+        gumball_header.assert_valid_authority(&ctx.accounts.authority.key())?;
 
         // Grab mutable reference to indices bytes
-        let size = gumball_header.max_items as usize;
-        let index_array_size = std::mem::size_of::<u32>() * size;
-        let (indices_data, _) = config_data.split_at_mut(index_array_size);
+        let (indices_data, _) = split_config_data(
+            config_data,
+            gumball_header.max_items,
+            gumball_header.extension_len,
+        )?;
         let indices = cast_slice_mut::<u8, u32>(indices_data);
 
         // Determine the next byte range to initialize
@@ -571,18 +617,23 @@ pub mod gumball_machine {
         new_config_lines_data: Vec<u8>,
     ) -> Result<()> {
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, config_data) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let mut gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
-        let size = gumball_header.max_items as usize;
-        let index_array_size = std::mem::size_of::<u32>() * size;
+        let (gumball_header, config_data) =
+            GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
+        gumball_header.assert_valid_authority(&ctx.accounts.authority.key())?;
         let line_size = gumball_header.extension_len as usize;
+        if line_size == 0 || new_config_lines_data.len() % line_size != 0 {
+            return err!(GumballMachineError::InvalidConfigLineLength);
+        }
         let num_lines = new_config_lines_data.len() / line_size;
         let start_index = gumball_header.total_items_added as usize;
-        assert_eq!(gumball_header.authority, ctx.accounts.authority.key());
-        assert_eq!(new_config_lines_data.len() % line_size, 0);
-        assert!(start_index + num_lines <= gumball_header.max_items as usize);
-        let (_, config_lines_data) = config_data.split_at_mut(index_array_size);
+        if start_index + num_lines > gumball_header.max_items as usize {
+            return err!(GumballMachineError::ConfigLinesOutOfBounds);
+        }
+        let (_, config_lines_data) = split_config_data(
+            config_data,
+            gumball_header.max_items,
+            gumball_header.extension_len,
+        )?;
         config_lines_data[start_index..]
             .iter_mut()
             .take(new_config_lines_data.len())
@@ -600,20 +651,23 @@ pub mod gumball_machine {
         new_config_lines_data: Vec<u8>,
     ) -> Result<()> {
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, config_data) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
-        let size = gumball_header.max_items as usize;
-        let index_array_size = std::mem::size_of::<u32>() * size;
-        let config_size = gumball_header.extension_len as usize * size;
+        let (gumball_header, config_data) =
+            GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
+        gumball_header.assert_valid_authority(&ctx.accounts.authority.key())?;
         let line_size = gumball_header.extension_len as usize;
+        if line_size == 0 || new_config_lines_data.len() % line_size != 0 {
+            return err!(GumballMachineError::InvalidConfigLineLength);
+        }
         let num_lines = new_config_lines_data.len() / line_size;
-        assert_eq!(gumball_header.authority, ctx.accounts.authority.key());
-        assert_eq!(new_config_lines_data.len() % line_size, 0);
-        assert!(config_data.len() == index_array_size + config_size);
-        assert_eq!(new_config_lines_data.len(), num_lines * line_size);
-        assert!(starting_line as usize + num_lines <= gumball_header.total_items_added as usize);
-        let (_, config_lines_data) = config_data.split_at_mut(index_array_size);
+        if starting_line.saturating_add(num_lines as u64) > gumball_header.total_items_added as u64
+        {
+            return err!(GumballMachineError::ConfigLinesOutOfBounds);
+        }
+        let (_, config_lines_data) = split_config_data(
+            config_data,
+            gumball_header.max_items,
+            gumball_header.extension_len,
+        )?;
         config_lines_data[starting_line as usize * line_size..]
             .iter_mut()
             .take(new_config_lines_data.len())
@@ -641,10 +695,8 @@ pub mod gumball_machine {
         creator_shares: Option<Vec<u8>>,
     ) -> Result<()> {
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, _) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let mut gumball_machine = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
-        assert_eq!(gumball_machine.authority, ctx.accounts.authority.key());
+        let (gumball_machine, _) = GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
+        gumball_machine.assert_valid_authority(&ctx.accounts.authority.key())?;
         match url_base {
             Some(ub) => gumball_machine.url_base = ub,
             None => {}
@@ -703,34 +755,10 @@ pub mod gumball_machine {
         }
         match creator_keys {
             Some(cks) => {
-                // If creator_shares is None but creator_keys is specified, input is invalid -> panic
-                let cs = creator_shares.unwrap();
-                assert!(
-                    cs.len() == 0 || cs.iter().sum::<u8>() == 100,
-                    "If specifying creators, shares must sum to 100% of royalty allocation."
-                );
-                assert_eq!(cks.len(), cs.len());
-                assert!(
-                    cks.len() < NUM_CREATORS,
-                    "Cannot set more than {} creators",
-                    NUM_CREATORS
-                );
+                // If creator_shares is None but creator_keys is specified, input is invalid
+                let cs = creator_shares.ok_or(GumballMachineError::CreatorKeysSharesMismatch)?;
                 // Construct creators array
-                let mut creators: [GumballCreatorAdapter; NUM_CREATORS] = [
-                    Default::default(),
-                    Default::default(),
-                    Default::default(),
-                    Default::default(),
-                ];
-                for i in 0..cks.len() {
-                    let creator_to_add = GumballCreatorAdapter {
-                        address: cks[i],
-                        // TODO: metaplex is working on creator verification
-                        verified: (0 as u8),
-                        share: cs[i],
-                    };
-                    creators[i] = creator_to_add;
-                }
+                let creators = get_creators(&cks, &cs)?;
                 // Overwrite existing creators array, note all creators must then be re-verified
                 gumball_machine.creators = creators;
             }
@@ -761,17 +789,25 @@ pub mod gumball_machine {
         )?;
 
         // Process payment for NFT
-        assert_eq!(&gumball_header.receiver.key(), &ctx.accounts.receiver.key());
+        if gumball_header.receiver != ctx.accounts.receiver.key() {
+            return err!(GumballMachineError::ReceiverMismatch);
+        }
 
         // Can only use this instruction for projects seeking SOL
         let wrapped_sol_pubkey: Pubkey = native_mint::ID;
-        assert_eq!(gumball_header.mint, wrapped_sol_pubkey);
+        if gumball_header.mint != wrapped_sol_pubkey {
+            return err!(GumballMachineError::MintMismatch);
+        }
 
+        let total_price = gumball_header
+            .price
+            .checked_mul(num_nfts_minted as u64)
+            .ok_or(GumballMachineError::NumericalOverflow)?;
         invoke(
             &system_instruction::transfer(
                 &ctx.accounts.payer.key(),
                 &ctx.accounts.receiver.key(),
-                gumball_header.price * (num_nfts_minted as u64),
+                total_price,
             ),
             &[
                 ctx.accounts.payer.to_account_info(),
@@ -804,8 +840,16 @@ pub mod gumball_machine {
         )?;
 
         // Process payment for NFT
-        assert_eq!(&gumball_header.receiver.key(), &ctx.accounts.receiver.key());
-        assert_eq!(ctx.accounts.payer_tokens.mint, gumball_header.mint);
+        if gumball_header.receiver != ctx.accounts.receiver.key() {
+            return err!(GumballMachineError::ReceiverMismatch);
+        }
+        if ctx.accounts.payer_tokens.mint != gumball_header.mint {
+            return err!(GumballMachineError::MintMismatch);
+        }
+        let total_price = gumball_header
+            .price
+            .checked_mul(num_nfts_minted as u64)
+            .ok_or(GumballMachineError::NumericalOverflow)?;
         transfer(
             CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
@@ -815,7 +859,7 @@ pub mod gumball_machine {
                     authority: ctx.accounts.payer.to_account_info(),
                 },
             ),
-            gumball_header.price * (num_nfts_minted as u64),
+            total_price,
         )?;
         Ok(())
     }
@@ -823,10 +867,8 @@ pub mod gumball_machine {
     /// Reclaim gumball_machine lamports to authority
     pub fn destroy(ctx: Context<Destroy>) -> Result<()> {
         let mut gumball_machine_data = ctx.accounts.gumball_machine.try_borrow_mut_data()?;
-        let (mut header_bytes, _) =
-            gumball_machine_data.split_at_mut(std::mem::size_of::<GumballMachineHeader>());
-        let gumball_header = GumballMachineHeader::load_mut_bytes(&mut header_bytes)?;
-        assert!(gumball_header.authority == ctx.accounts.authority.key());
+        let (gumball_header, _) = GumballMachineHeader::split_mut(&mut gumball_machine_data)?;
+        gumball_header.assert_valid_authority(&ctx.accounts.authority.key())?;
         let dest_starting_lamports = ctx.accounts.authority.lamports();
         **ctx.accounts.authority.lamports.borrow_mut() = dest_starting_lamports
             .checked_add(ctx.accounts.gumball_machine.lamports())
@@ -1,6 +1,8 @@
+use crate::error::GumballMachineError;
 use crate::utils::error_msg;
 use anchor_lang::prelude::*;
-use bytemuck::{Pod, Zeroable};
+use bytemuck::{Pod, PodCastError, Zeroable};
+use std::convert::TryFrom;
 use std::mem::size_of;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
@@ -9,12 +11,17 @@ pub enum EncodeMethod {
     Base58Encode,
 }
 
-impl From<u8> for EncodeMethod {
-    fn from(v: u8) -> Self {
+impl TryFrom<u8> for EncodeMethod {
+    type Error = anchor_lang::error::Error;
+
+    fn try_from(v: u8) -> Result<Self> {
         match v {
-            0 => Self::UTF8,
-            1 => Self::Base58Encode,
-            _ => panic!("Unsupported value for EncodeMethod"),
+            0 => Ok(Self::UTF8),
+            1 => Ok(Self::Base58Encode),
+            _ => {
+                msg!("Unsupported value for EncodeMethod: {}", v);
+                err!(GumballMachineError::InvalidEncodeMethod)
+            }
         }
     }
 }
@@ -105,14 +112,44 @@ pub struct GumballMachineHeader {
     pub _padding: [u8; 7],
 }
 
+impl GumballMachineHeader {
+    /// Splits gumball machine account data into its header and the indices and config lines after it
+    pub fn split_mut(data: &mut [u8]) -> Result<(&mut Self, &mut [u8])> {
+        if data.len() < size_of::<Self>() {
+            msg!(
+                "Gumball machine account holds {} bytes, expected at least {}",
+                data.len(),
+                size_of::<Self>()
+            );
+            return err!(GumballMachineError::InvalidAccountSize);
+        }
+        let (header_bytes, config_data) = data.split_at_mut(size_of::<Self>());
+        Ok((Self::load_mut_bytes(header_bytes)?, config_data))
+    }
+
+    /// Fails unless `expected_authority` is the authority of the gumball machine
+    pub fn assert_valid_authority(&self, expected_authority: &Pubkey) -> Result<()> {
+        if self.authority != *expected_authority {
+            msg!(
+                "Signer {} is not the gumball machine authority {}",
+                expected_authority,
+                self.authority
+            );
+            return err!(GumballMachineError::InvalidAuthority);
+        }
+        Ok(())
+    }
+}
+
 impl ZeroCopy for GumballMachineHeader {}
 pub trait ZeroCopy: Pod {
     fn load_mut_bytes<'a>(data: &'a mut [u8]) -> Result<&'a mut Self> {
         let size = size_of::<Self>();
         let data_len = data.len();
+        if data_len < size {
+            return Err(error_msg::<Self>(data_len)(PodCastError::SizeMismatch).into());
+        }
 
-        Ok(bytemuck::try_from_bytes_mut(&mut data[..size])
-            .map_err(error_msg::<Self>(data_len))
-            .unwrap())
+        Ok(bytemuck::try_from_bytes_mut(&mut data[..size]).map_err(error_msg::<Self>(data_len))?)
     }
 }
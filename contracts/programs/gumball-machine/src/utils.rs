@@ -1,3 +1,4 @@
+use crate::error::GumballMachineError;
 use crate::state::{EncodeMethod, GumballCreatorAdapter, NUM_CREATORS};
 use anchor_lang::{
     prelude::*,
@@ -23,6 +24,17 @@ pub fn error_msg<T>(data_len: usize) -> impl Fn(PodCastError) -> ProgramError {
     }
 }
 
+/// Reads a UTF-8 string out of a fixed size, zero padded field
+fn str_from_utf8(bytes: &[u8]) -> Result<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Ok(s),
+        Err(err) => {
+            msg!("Invalid UTF-8: {}", err);
+            err!(GumballMachineError::InvalidUtf8)
+        }
+    }
+}
+
 pub fn get_metadata_args(
     url_base: [u8; 64],
     name_base: [u8; 32],
@@ -35,15 +47,15 @@ pub fn get_metadata_args(
     index: usize,
     config_line: Vec<u8>,
     encode_method: EncodeMethod,
-) -> MetadataArgs {
+) -> Result<MetadataArgs> {
     let zero = 0 as char;
-    let name_base = std::str::from_utf8(&name_base).unwrap().trim_matches(zero);
-    let symbol = std::str::from_utf8(&symbol).unwrap().trim_matches(zero);
-    let uri_base = std::str::from_utf8(&url_base).unwrap().trim_matches(zero);
+    let name_base = str_from_utf8(&name_base)?.trim_matches(zero);
+    let symbol = str_from_utf8(&symbol)?.trim_matches(zero);
+    let uri_base = str_from_utf8(&url_base)?.trim_matches(zero);
     let system_program_id = anchor_lang::system_program::ID;
     let config = match encode_method {
         EncodeMethod::Base58Encode => bs58::encode(config_line).into_string(),
-        _ => std::str::from_utf8(&config_line).unwrap().to_string(),
+        _ => str_from_utf8(&config_line)?.to_string(),
     };
     msg!("Config Line: {}", config);
 
@@ -53,7 +65,7 @@ pub fn get_metadata_args(
             creators_vec.push(creator.adapt());
         }
     }
-    MetadataArgs {
+    Ok(MetadataArgs {
         name: name_base.to_owned() + " #" + &index.to_string(),
         symbol: symbol.to_string(),
         uri: uri_base.to_owned() + &config,
@@ -66,13 +78,14 @@ pub fn get_metadata_args(
             // Treat the SystemProgram as a the null case
             None
         } else {
+            // Bubblegum only verifies collections whose authority signs, see `verify_collection`
             Some(Collection {
-                verified: true,
+                verified: false,
                 key: collection,
             })
         },
         uses,
         token_program_version: TokenProgramVersion::Original,
         creators: creators_vec,
-    }
+    })
 }
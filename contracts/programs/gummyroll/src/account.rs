@@ -0,0 +1,451 @@
+//! Layout of Gummyroll tree accounts: deserialization and sizing for off-chain clients
+//!
+use crate::{
+    check_canopy_bytes, deserialize_header, error::GummyrollError, get_cached_path_length,
+    is_recorded_hasher, merkle_roll_get_size, state::MerkleRollHeader,
+};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{system_instruction::MAX_PERMITTED_DATA_LENGTH, sysvar::rent::Rent},
+};
+use borsh::BorshDeserialize;
+use bytemuck::cast_slice;
+use concurrent_merkle_tree::{
+    hasher::Hasher,
+    merkle_roll_view::{
+        merkle_roll_size, ChangeLogRef, MerkleRollRef, PathRef, MAX_SUPPORTED_DEPTH,
+    },
+    state::Node,
+};
+use std::mem::size_of;
+
+/// Returns the number of bytes to allocate for a Gummyroll tree account with the given
+/// dimensions, caching the top `canopy_depth` levels of the tree below the root in the canopy.
+///
+/// The account holds the header, the merkle roll and `2^(canopy_depth + 1) - 2` canopy nodes.
+pub fn merkle_roll_account_size(
+    max_depth: u32,
+    max_buffer_size: u32,
+    canopy_depth: u32,
+) -> Result<usize> {
+    let merkle_roll_size = match merkle_roll_size(max_depth as usize, max_buffer_size as usize) {
+        Ok(size) => size,
+        Err(_) => {
+            msg!(
+                "Failed to get size of max depth {} and max buffer size {}",
+                max_depth,
+                max_buffer_size
+            );
+            return err!(GummyrollError::MerkleRollConstantsError);
+        }
+    };
+    if canopy_depth > max_depth {
+        msg!(
+            "Canopy depth {} cannot exceed max depth {}",
+            canopy_depth,
+            max_depth
+        );
+        return err!(GummyrollError::CanopyLengthMismatch);
+    }
+    let canopy_size = ((1 << (canopy_depth + 1)) - 2) * size_of::<Node>();
+    Ok(size_of::<MerkleRollHeader>() + merkle_roll_size + canopy_size)
+}
+
+/// Dimensions of a Gummyroll tree along with the size of its account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeConfig {
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+    pub canopy_depth: u32,
+    /// Bytes to allocate for the tree account, see [merkle_roll_account_size]
+    pub account_size: usize,
+    /// Number of proof nodes to pass when modifying a leaf, the others are read from the canopy
+    pub proof_length: u32,
+}
+
+impl TreeConfig {
+    pub fn new(max_depth: u32, max_buffer_size: u32, canopy_depth: u32) -> Result<Self> {
+        Ok(Self {
+            max_depth,
+            max_buffer_size,
+            canopy_depth,
+            account_size: merkle_roll_account_size(max_depth, max_buffer_size, canopy_depth)?,
+            proof_length: max_depth - canopy_depth,
+        })
+    }
+
+    /// Lamports needed for the tree account to be rent exempt
+    pub fn rent_exempt_lamports(&self, rent: &Rent) -> u64 {
+        rent.minimum_balance(self.account_size)
+    }
+}
+
+/// Recommends tree configurations that hold at least `num_leaves` leaves and support
+/// `concurrency` updates to the tree within the same slot.
+///
+/// All configurations use the smallest such depth and buffer size, and differ by their canopy
+/// depth, which trades a larger account for shorter proofs. They are ordered by increasing
+/// canopy depth and only include accounts that can be allocated.
+/// Trees have a depth of at least 1, even if they hold fewer than 2 leaves.
+pub fn recommend_tree_configs(num_leaves: u64, concurrency: u32) -> Result<Vec<TreeConfig>> {
+    let max_depth = (u64::BITS - num_leaves.saturating_sub(1).leading_zeros()).max(1);
+    if max_depth as usize > MAX_SUPPORTED_DEPTH {
+        msg!(
+            "Cannot store {} leaves in a tree of depth at most {}",
+            num_leaves,
+            MAX_SUPPORTED_DEPTH
+        );
+        return err!(GummyrollError::MerkleRollConstantsError);
+    }
+    let max_buffer_size = concurrency
+        .max(1)
+        .checked_next_power_of_two()
+        .ok_or(GummyrollError::MerkleRollConstantsError)?;
+    let mut configs = vec![];
+    for canopy_depth in 0..=max_depth {
+        let config = TreeConfig::new(max_depth, max_buffer_size, canopy_depth)?;
+        if config.account_size as u64 > MAX_PERMITTED_DATA_LENGTH {
+            break;
+        }
+        configs.push(config);
+    }
+    Ok(configs)
+}
+
+/// Typed view over the bytes of a Gummyroll tree account.
+///
+/// The account holds a `MerkleRollHeader`, followed by a merkle roll sized from the header
+/// (see `concurrent_merkle_tree::merkle_roll_view::merkle_roll_size`) and by the canopy,
+/// which caches the top levels of the tree as a full binary tree without the root.
+pub struct MerkleRollAccount<'a> {
+    pub header: MerkleRollHeader,
+    /// Only used for reads that do not hash, see [MerkleRollAccount::merkle_roll]
+    merkle_roll: MerkleRollRef<'a>,
+    canopy: &'a [Node],
+    canopy_depth: u32,
+}
+
+impl<'a> MerkleRollAccount<'a> {
+    /// Parses the raw data of a Gummyroll tree account.
+    ///
+    /// Fails if the header uses a legacy layout or describes unsupported dimensions, if the
+    /// account is too small to hold them or if the remaining bytes do not form a valid canopy.
+    pub fn deserialize(data: &'a [u8]) -> Result<Self> {
+        if data.len() < size_of::<MerkleRollHeader>() {
+            msg!(
+                "Merkle roll account holds {} bytes, expected at least {}",
+                data.len(),
+                size_of::<MerkleRollHeader>()
+            );
+            return err!(GummyrollError::InvalidAccountSize);
+        }
+        let (header_bytes, rest) = data.split_at(size_of::<MerkleRollHeader>());
+        let header = *deserialize_header(header_bytes)?;
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        if rest.len() < merkle_roll_size {
+            msg!(
+                "Merkle roll account holds {} bytes after the header, expected at least {}",
+                rest.len(),
+                merkle_roll_size
+            );
+            return err!(GummyrollError::InvalidAccountSize);
+        }
+        let (roll_bytes, canopy_bytes) = rest.split_at(merkle_roll_size);
+        let merkle_roll = match MerkleRollRef::new(
+            roll_bytes,
+            header.max_depth as usize,
+            header.max_buffer_size as usize,
+        ) {
+            Ok(merkle_roll) => merkle_roll,
+            Err(err) => {
+                msg!("Error zero copying merkle roll: {}", err);
+                return err!(GummyrollError::from(&err));
+            }
+        };
+        check_canopy_bytes(canopy_bytes)?;
+        let canopy = cast_slice::<u8, Node>(canopy_bytes);
+        let canopy_depth = get_cached_path_length(canopy, header.max_depth)?;
+        Ok(Self {
+            header,
+            merkle_roll,
+            canopy,
+            canopy_depth,
+        })
+    }
+
+    /// Read-only view of the merkle roll that hashes with `H`.
+    ///
+    /// `H` must be the hasher recorded in the header, e.g. `DomainSeparated<Keccak>` for a
+    /// domain separated `Keccak` tree, otherwise this fails with `HashFunctionMismatch`.
+    pub fn merkle_roll<H: Hasher>(&self) -> Result<MerkleRollRef<'a, H>> {
+        if !is_recorded_hasher::<H>(&self.header)? {
+            msg!(
+                "Hasher does not match hash function {:?} of the merkle roll (domain separated: {})",
+                self.header.hash_function,
+                self.header.domain_separated
+            );
+            return err!(GummyrollError::HashFunctionMismatch);
+        }
+        match MerkleRollRef::<H>::with_hasher(
+            self.merkle_roll.data(),
+            self.header.max_depth as usize,
+            self.header.max_buffer_size as usize,
+        ) {
+            Ok(merkle_roll) => Ok(merkle_roll),
+            Err(err) => {
+                msg!("Error zero copying merkle roll: {}", err);
+                err!(GummyrollError::ZeroCopyError)
+            }
+        }
+    }
+
+    /// Current root of the tree
+    pub fn root(&self) -> Node {
+        *self.merkle_roll.get_change_log().root
+    }
+
+    /// Number of successful operations on the tree
+    pub fn sequence_number(&self) -> u64 {
+        self.merkle_roll.sequence_number()
+    }
+
+    /// Proof of the rightmost leaf, used to append leaves
+    pub fn rightmost_proof(&self) -> PathRef<'a> {
+        self.merkle_roll.rightmost_proof()
+    }
+
+    /// Change logs of the buffer, from oldest to most recent
+    pub fn change_logs(&self) -> impl Iterator<Item = ChangeLogRef<'a>> + 'a {
+        let merkle_roll = self.merkle_roll;
+        (0..merkle_roll.buffer_size())
+            .rev()
+            .map(move |offset| merkle_roll.get_prior_change_log(offset))
+    }
+
+    /// Canopy nodes, ordered by node index starting from the children of the root
+    pub fn canopy(&self) -> &'a [Node] {
+        self.canopy
+    }
+
+    /// Number of tree levels below the root that are stored in the canopy
+    pub fn canopy_depth(&self) -> u32 {
+        self.canopy_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{HashFunction, LEGACY_MERKLE_ROLL_HEADER_SIZE};
+    use borsh::BorshSerialize;
+    use concurrent_merkle_tree::{
+        hasher::{DomainSeparated, Keccak},
+        merkle_roll_view::MerkleRollMut,
+    };
+
+    const MAX_DEPTH: u32 = 5;
+    const MAX_BUFFER_SIZE: u32 = 8;
+    const HEADER_SIZE: usize = size_of::<MerkleRollHeader>();
+
+    /// Builds the data of a tree account holding `leaves`, with an empty canopy of `canopy_depth`
+    fn account_data<H: Hasher>(
+        canopy_depth: u32,
+        domain_separated: bool,
+        leaves: &[Node],
+    ) -> Vec<u8> {
+        let mut data =
+            vec![0; merkle_roll_account_size(MAX_DEPTH, MAX_BUFFER_SIZE, canopy_depth).unwrap()];
+        let (mut header_bytes, rest) = data.split_at_mut(HEADER_SIZE);
+        let mut header = MerkleRollHeader::try_from_slice(header_bytes).unwrap();
+        header
+            .initialize(
+                MAX_DEPTH,
+                MAX_BUFFER_SIZE,
+                &Pubkey::new_unique(),
+                42,
+                HashFunction::Keccak,
+                domain_separated,
+            )
+            .unwrap();
+        header.serialize(&mut header_bytes).unwrap();
+        let roll_size = merkle_roll_size(MAX_DEPTH as usize, MAX_BUFFER_SIZE as usize).unwrap();
+        let mut merkle_roll = MerkleRollMut::<H>::with_hasher(
+            &mut rest[..roll_size],
+            MAX_DEPTH as usize,
+            MAX_BUFFER_SIZE as usize,
+        )
+        .unwrap();
+        merkle_roll.initialize().unwrap();
+        for leaf in leaves {
+            merkle_roll.append(*leaf).unwrap();
+        }
+        data
+    }
+
+    fn assert_error<T>(result: Result<T>, expected: GummyrollError) {
+        match result {
+            Err(Error::AnchorError(err)) => {
+                assert_eq!(err.error_code_number, u32::from(expected))
+            }
+            Err(err) => panic!("Unexpected error {}", err),
+            Ok(_) => panic!("Expected {}", expected),
+        }
+    }
+
+    #[test]
+    fn test_account_size_matches_sdk() {
+        // Sizes returned by `getMerkleRollAccountSize` in the gummyroll SDK
+        for (max_depth, max_buffer_size, canopy_depth, sdk_size) in [
+            (1, 1, 0, 224),
+            (5, 8, 2, 2072),
+            (14, 64, 0, 31800),
+            (20, 256, 10, 240312),
+            (30, 2048, 14, 3097592),
+        ] {
+            let config = TreeConfig::new(max_depth, max_buffer_size, canopy_depth).unwrap();
+            assert_eq!(config.account_size, sdk_size);
+            assert_eq!(config.proof_length, max_depth - canopy_depth);
+
+            let mut header = MerkleRollHeader::try_from_slice(&[0; HEADER_SIZE]).unwrap();
+            header.max_depth = max_depth;
+            header.max_buffer_size = max_buffer_size;
+            let canopy_size = ((1 << (canopy_depth + 1)) - 2) * size_of::<Node>();
+            assert_eq!(
+                HEADER_SIZE + merkle_roll_get_size(&header).unwrap() + canopy_size,
+                sdk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_recommend_tree_configs() {
+        for num_leaves in [0, 1, 2] {
+            let configs = recommend_tree_configs(num_leaves, 0).unwrap();
+            assert_eq!(
+                configs,
+                vec![
+                    TreeConfig::new(1, 1, 0).unwrap(),
+                    TreeConfig::new(1, 1, 1).unwrap()
+                ]
+            );
+        }
+
+        let configs = recommend_tree_configs(1000, 10).unwrap();
+        assert_eq!(configs.len(), 11);
+        for (canopy_depth, config) in configs.iter().enumerate() {
+            assert_eq!(config.max_depth, 10);
+            assert_eq!(config.max_buffer_size, 16);
+            assert_eq!(config.canopy_depth, canopy_depth as u32);
+        }
+
+        assert_error(
+            recommend_tree_configs((1 << MAX_SUPPORTED_DEPTH) + 1, 1),
+            GummyrollError::MerkleRollConstantsError,
+        );
+    }
+
+    #[test]
+    fn test_deserialize_round_trip() {
+        let leaves = [[1; 32], [2; 32], [3; 32]];
+        let data = account_data::<Keccak>(2, false, &leaves);
+        let account = MerkleRollAccount::deserialize(&data).unwrap();
+
+        assert_eq!(account.header.max_depth, MAX_DEPTH);
+        assert_eq!(account.header.max_buffer_size, MAX_BUFFER_SIZE);
+        assert_eq!(account.header.creation_slot, 42);
+        assert_eq!(account.header.hash_function, HashFunction::Keccak);
+        assert!(account.header.is_canopy_complete());
+        assert_eq!(account.canopy_depth(), 2);
+        assert_eq!(account.canopy().len(), 6);
+        assert_eq!(account.sequence_number(), leaves.len() as u64);
+        assert_eq!(account.rightmost_proof().index, leaves.len() as u32);
+        assert_eq!(account.change_logs().count(), leaves.len() + 1);
+
+        let mut tree = vec![Keccak::empty_node(0); 1 << MAX_DEPTH];
+        tree[..leaves.len()].copy_from_slice(&leaves);
+        while tree.len() > 1 {
+            tree = tree
+                .chunks(2)
+                .map(|siblings| Keccak::hash_nodes(&siblings[0], &siblings[1]))
+                .collect();
+        }
+        assert_eq!(account.root(), tree[0]);
+        assert_eq!(
+            *account
+                .merkle_roll::<Keccak>()
+                .unwrap()
+                .get_change_log()
+                .root,
+            tree[0]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rejects_truncated_header() {
+        let data = account_data::<Keccak>(0, false, &[]);
+        assert_error(
+            MerkleRollAccount::deserialize(&data[..HEADER_SIZE - 1]),
+            GummyrollError::InvalidAccountSize,
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rejects_short_merkle_roll() {
+        let data = account_data::<Keccak>(0, false, &[]);
+        assert_error(
+            MerkleRollAccount::deserialize(&data[..data.len() - 1]),
+            GummyrollError::InvalidAccountSize,
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rejects_invalid_canopy() {
+        let mut data = account_data::<Keccak>(0, false, &[]);
+        // 3 nodes is not 2 less than a power of 2
+        data.extend_from_slice(&[0; 3 * size_of::<Node>()]);
+        assert_error(
+            MerkleRollAccount::deserialize(&data),
+            GummyrollError::CanopyLengthMismatch,
+        );
+        // The canopy cannot hold part of a node
+        let data = account_data::<Keccak>(1, false, &[]);
+        assert_error(
+            MerkleRollAccount::deserialize(&data[..data.len() - 1]),
+            GummyrollError::CanopyLengthMismatch,
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rejects_legacy_header() {
+        let data = account_data::<Keccak>(0, false, &[]);
+        // A legacy account holds the same merkle roll after a shorter header
+        let mut legacy_data = data[..LEGACY_MERKLE_ROLL_HEADER_SIZE].to_vec();
+        legacy_data.extend_from_slice(&data[HEADER_SIZE..]);
+        assert_error(
+            MerkleRollAccount::deserialize(&legacy_data),
+            GummyrollError::UnsupportedHeaderVersion,
+        );
+    }
+
+    #[test]
+    fn test_merkle_roll_uses_recorded_hasher() {
+        let leaves = [[1; 32], [2; 32]];
+        let data = account_data::<DomainSeparated<Keccak>>(0, true, &leaves);
+        let account = MerkleRollAccount::deserialize(&data).unwrap();
+        assert!(account.header.domain_separated);
+
+        let merkle_roll = account.merkle_roll::<DomainSeparated<Keccak>>().unwrap();
+        let mut proof = vec![leaves[0]];
+        for level in 1..MAX_DEPTH {
+            proof.push(DomainSeparated::<Keccak>::empty_node(level));
+        }
+        merkle_roll
+            .prove_leaf(account.root(), leaves[1], &proof, 1)
+            .unwrap();
+
+        assert_error(
+            account.merkle_roll::<Keccak>(),
+            GummyrollError::HashFunctionMismatch,
+        );
+    }
+}
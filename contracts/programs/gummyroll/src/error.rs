@@ -2,14 +2,17 @@ use anchor_lang::prelude::*;
 use concurrent_merkle_tree::error::CMTError;
 
 /// Errors related to misconfiguration or misuse of the Merkle tree
+///
+/// Codes are part of the public interface: new variants are appended at the end so that the
+/// code of every existing variant stays the same.
 #[error_code]
 pub enum GummyrollError {
     /// This error is currently not used.
     #[msg("Incorrect leaf length. Expected vec of 32 bytes")]
     IncorrectLeafLength,
 
-    /// A modification to the tree was invalid and a changelog was not emitted.
-    /// The proof may be invalid or out-of-date, or the provided leaf hash was invalid.
+    /// Deprecated: every `CMTError` is now reported with its own error code below, so this error
+    /// is no longer returned. It is kept so that the codes of the following variants do not change.
     #[msg("Concurrent merkle tree error")]
     ConcurrentMerkleTreeError,
 
@@ -24,10 +27,124 @@ pub enum GummyrollError {
     /// When using Canopy, the stored byte length should a multiple of the node's byte length (32 bytes)
     #[msg("Expected a different byte length for the merkle roll canopy")]
     CanopyLengthMismatch,
+
+    /// A changelog is emitted for every leaf in a batch, so a batch cannot contain more leaves
+    /// than the tree's `max_buffer_size`.
+    #[msg("Batch contains more leaves than the merkle roll buffer can hold")]
+    BatchSizeExceedsBufferSize,
+
+    /// Only trees whose root is the root of an empty tree can be closed.
+    #[msg("Tree still holds data and cannot be closed")]
+    TreeNotEmpty,
+
+    /// The hash function recorded in the tree header was not enabled when building gummyroll.
+    /// See [HashFunction](/gummyroll/state/enum.HashFunction.html).
+    #[msg("Hash function of the merkle roll is not supported by this program")]
+    UnsupportedHashFunction,
+
+    /// The leaf index is larger than the rightmost index of the tree.
+    #[msg("Received an index larger than the rightmost index")]
+    LeafIndexOutOfBounds,
+
+    /// The proof does not hash to any root in the changelog buffer.
+    /// The proof must be fetched again before retrying.
+    #[msg("Invalid root recomputed from proof")]
+    InvalidProof,
+
+    /// Appended leaves cannot be empty nodes.
+    #[msg("Cannot append an empty node")]
+    CannotAppendEmptyNode,
+
+    /// The tree is at capacity.
+    #[msg("Tree is full, cannot append")]
+    TreeFull,
+
+    /// The tree has already been initialized.
+    #[msg("Tree already initialized")]
+    TreeAlreadyInitialized,
+
+    /// A node was not 32 bytes long.
+    #[msg("Invalid number of bytes passed for node (expected 32 bytes)")]
+    InvalidNodeByteLength,
+
+    /// The root of the proof is older than every root in the changelog buffer.
+    /// The proof must be fetched again before retrying.
+    #[msg("Root not found in changelog buffer")]
+    RootNotFound,
+
+    /// The proof was valid, but the leaf was modified after the proof was issued.
+    /// Retrying with a new proof only helps if the new leaf value is still expected.
+    #[msg("Valid proof was passed to a leaf, but it's value has changed since the proof was issued")]
+    LeafContentsModified,
+
+    /// The max depth is 0 or larger than 30, or the max buffer size is not a power of 2.
+    #[msg("Unsupported max depth or max buffer size")]
+    UnsupportedMerkleRollConstants,
+
+    /// The account data does not match the size of a merkle roll with the header dimensions.
+    #[msg("Invalid number of bytes passed for merkle roll")]
+    InvalidMerkleRollByteLength,
+
+    /// The compressed proof has more nodes than the tree depth,
+    /// or its bitmap marks levels outside of the proof as empty.
+    #[msg("Invalid compressed proof")]
+    InvalidCompressedProof,
+
+    /// The leaf indices are not strictly increasing,
+    /// or the multiproof does not hold exactly the nodes needed to prove them.
+    #[msg("Invalid multiproof")]
+    InvalidMultiProof,
+
+    /// The signer is not the authority recorded in the merkle roll header.
+    #[msg("Signer is not the authority of the merkle roll")]
+    InvalidAuthority,
+
+    /// The account data is too small to hold the merkle roll described by its header.
+    #[msg("Merkle roll account is too small")]
+    InvalidAccountSize,
+
+    /// The canopy of a tree initialized with a root has not been uploaded with `prefill_canopy` yet,
+    /// so proofs must include every node up to the root.
+    #[msg("Canopy must be prefilled before it can be used to shorten proofs")]
+    CanopyNotComplete,
+
+    /// Canopy nodes must be uploaded from left to right, in chunks that start at the first pending
+    /// node and do not extend past the end of the canopy.
+    #[msg("Invalid chunk of canopy nodes")]
+    InvalidCanopyChunk,
+
+    /// The tree header was not written with the current layout.
+    /// Trees created with the legacy 48 byte header must be upgraded with `migrate_header`.
+    #[msg("Merkle roll header uses an unsupported layout version")]
+    UnsupportedHeaderVersion,
+
+    /// Only trees created with the legacy 48 byte header can be upgraded with `migrate_header`.
+    #[msg("Merkle roll account does not hold a tree with a legacy header")]
+    InvalidLegacyHeader,
+
+    /// A view of the merkle roll was requested with another hasher than the one recorded in
+    /// the tree header, see [HashFunction](/gummyroll/state/enum.HashFunction.html).
+    #[msg("Hasher does not match the hash function of the merkle roll")]
+    HashFunctionMismatch,
 }
 
 impl From<&CMTError> for GummyrollError {
-    fn from(_error: &CMTError) -> Self {
-        GummyrollError::ConcurrentMerkleTreeError
+    fn from(error: &CMTError) -> Self {
+        match error {
+            CMTError::LeafIndexOutOfBounds => GummyrollError::LeafIndexOutOfBounds,
+            CMTError::InvalidProof => GummyrollError::InvalidProof,
+            CMTError::CannotAppendEmptyNode => GummyrollError::CannotAppendEmptyNode,
+            CMTError::TreeFull => GummyrollError::TreeFull,
+            CMTError::TreeAlreadyInitialized => GummyrollError::TreeAlreadyInitialized,
+            CMTError::InvalidNodeByteLength => GummyrollError::InvalidNodeByteLength,
+            CMTError::RootNotFound => GummyrollError::RootNotFound,
+            CMTError::LeafContentsModified => GummyrollError::LeafContentsModified,
+            CMTError::UnsupportedMerkleRollConstants => {
+                GummyrollError::UnsupportedMerkleRollConstants
+            }
+            CMTError::InvalidMerkleRollByteLength => GummyrollError::InvalidMerkleRollByteLength,
+            CMTError::InvalidCompressedProof => GummyrollError::InvalidCompressedProof,
+            CMTError::InvalidMultiProof => GummyrollError::InvalidMultiProof,
+        }
     }
 }
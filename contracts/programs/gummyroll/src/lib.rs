@@ -20,19 +20,35 @@ use anchor_lang::{
     emit,
     prelude::*,
     solana_program::sysvar::{clock::Clock, rent::Rent},
+    system_program,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use bytemuck::cast_slice_mut;
-use concurrent_merkle_tree::{state::EMPTY, utils::empty_node_cached};
-use std::mem::size_of;
+use bytemuck::{cast_slice, cast_slice_mut};
+#[cfg(feature = "poseidon")]
+use concurrent_merkle_tree::hasher::Poseidon;
+#[cfg(feature = "sha256")]
+use concurrent_merkle_tree::hasher::Sha256;
+use concurrent_merkle_tree::{
+    hasher::{DomainSeparated, Hasher, Keccak},
+    merkle_roll_view::{merkle_roll_size, MerkleRollMut, MerkleRollRef},
+    multiproof::{multiproof_node_indices, MultiProof},
+    state::EMPTY,
+    utils::decompress_proof,
+};
+use std::{any::TypeId, mem::size_of};
 
+pub mod account;
 pub mod error;
 pub mod state;
 pub mod utils;
 
+use crate::account::{merkle_roll_account_size, MerkleRollAccount};
 use crate::error::GummyrollError;
-use crate::state::{CandyWrapper, ChangeLogEvent, MerkleRollHeader};
-use crate::utils::{wrap_event, ZeroCopy};
+use crate::state::{
+    CandyWrapper, ChangeLogEvent, HashFunction, LeafReplacement, LegacyMerkleRollHeader,
+    MerkleRollHeader, LEGACY_MERKLE_ROLL_HEADER_SIZE,
+};
+use crate::utils::wrap_event;
 pub use concurrent_merkle_tree::{error::CMTError, merkle_roll::MerkleRoll, state::Node};
 
 declare_id!("GRoLLzvxpxxu2PGNJMMeZPyMxjAUH9pKqxGXV9DGiceU");
@@ -73,7 +89,10 @@ pub struct Modify<'info> {
 /// Throws an error if provided proof is invalid.
 #[derive(Accounts)]
 pub struct VerifyLeaf<'info> {
-    /// CHECK: This account is validated in the instruction
+    /// CHECK: This account is validated in the instruction.
+    /// It is never written, so it can be passed as a read-only account. It must be owned by
+    /// gummyroll, otherwise a caller could pass an account holding a tree of their choice.
+    #[account(owner = crate::id())]
     pub merkle_roll: UncheckedAccount<'info>,
 }
 
@@ -89,8 +108,72 @@ pub struct TransferAuthority<'info> {
     pub authority: Signer<'info>,
 }
 
+/// Context for closing a tree that holds no data
+#[derive(Accounts)]
+pub struct CloseTree<'info> {
+    #[account(mut)]
+    /// CHECK: This account is validated in the instruction
+    pub merkle_roll: UncheckedAccount<'info>,
+
+    /// Authority that validates the content of the trees.
+    /// Typically a program, e.g., the Bubblegum contract validates that leaves are valid NFTs.
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    /// CHECK: This account only receives the lamports of the closed tree
+    pub recipient: UncheckedAccount<'info>,
+}
+
+/// Context for growing the canopy of a tree
+#[derive(Accounts)]
+pub struct GrowCanopy<'info> {
+    #[account(mut)]
+    /// CHECK: This account is validated in the instruction
+    pub merkle_roll: UncheckedAccount<'info>,
+
+    /// Authority that validates the content of the trees.
+    /// Typically a program, e.g., the Bubblegum contract validates that leaves are valid NFTs.
+    pub authority: Signer<'info>,
+
+    /// Pays for the rent of the larger tree account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Context for uploading the canopy of a tree initialized with a root
+#[derive(Accounts)]
+pub struct PrefillCanopy<'info> {
+    #[account(mut)]
+    /// CHECK: This account is validated in the instruction
+    pub merkle_roll: UncheckedAccount<'info>,
+
+    /// Authority that validates the content of the trees.
+    /// Typically a program, e.g., the Bubblegum contract validates that leaves are valid NFTs.
+    pub authority: Signer<'info>,
+}
+
+/// Context for upgrading a tree with a legacy header to the current header layout
+#[derive(Accounts)]
+pub struct MigrateHeader<'info> {
+    #[account(mut)]
+    /// CHECK: This account is validated in the instruction
+    pub merkle_roll: UncheckedAccount<'info>,
+
+    /// Authority that validates the content of the trees.
+    /// Typically a program, e.g., the Bubblegum contract validates that leaves are valid NFTs.
+    pub authority: Signer<'info>,
+
+    /// Pays for the rent of the larger tree account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[inline(always)]
-fn check_canopy_bytes(canopy_bytes: &mut [u8]) -> Result<()> {
+fn check_canopy_bytes(canopy_bytes: &[u8]) -> Result<()> {
     if canopy_bytes.len() % size_of::<Node>() != 0 {
         msg!(
             "Canopy byte length {} is not a multiple of {}",
@@ -104,7 +187,7 @@ fn check_canopy_bytes(canopy_bytes: &mut [u8]) -> Result<()> {
 }
 
 #[inline(always)]
-fn get_cached_path_length(canopy: &mut [Node], max_depth: u32) -> Result<u32> {
+fn get_cached_path_length(canopy: &[Node], max_depth: u32) -> Result<u32> {
     // The offset of 2 is applied because the canopy is a full binary tree without the root node
     // Size: (2^n - 2) -> Size + 2 must be a power of 2
     let closest_power_of_2 = (canopy.len() + 2) as u32;
@@ -150,16 +233,31 @@ fn update_canopy(
     Ok(())
 }
 
-fn fill_in_proof_from_canopy(
-    canopy_bytes: &mut [u8],
+/// Appends the proof nodes for the leaf at `index` that are stored in the canopy to `proof`.
+/// Empty canopy slots are read as empty nodes, the canopy itself is never written.
+fn fill_in_proof_from_canopy<H: Hasher>(
+    canopy_bytes: &[u8],
     max_depth: u32,
     index: u32,
     proof: &mut Vec<Node>,
+    canopy_complete: bool,
 ) -> Result<()> {
+    if !canopy_complete {
+        // Empty canopy slots cannot be assumed to be empty nodes until the canopy is prefilled
+        if proof.len() < max_depth as usize {
+            msg!(
+                "Expected a proof of {} nodes while the canopy is not prefilled, found {}",
+                max_depth,
+                proof.len()
+            );
+            return err!(GummyrollError::CanopyNotComplete);
+        }
+        return Ok(());
+    }
     // 26 is hard coded as it is the current max depth that Gummyroll supports
     let mut empty_node_cache = Box::new([EMPTY; 30]);
     check_canopy_bytes(canopy_bytes)?;
-    let canopy = cast_slice_mut::<u8, Node>(canopy_bytes);
+    let canopy = cast_slice::<u8, Node>(canopy_bytes);
     let path_len = get_cached_path_length(canopy, max_depth)?;
     // We want to compute the node index (w.r.t. the canopy) where the current path
     // intersects the leaves of the canopy
@@ -175,8 +273,7 @@ fn fill_in_proof_from_canopy(
         };
         if canopy[cached_idx] == EMPTY {
             let level = max_depth - (31 - node_idx.leading_zeros());
-            let empty_node = empty_node_cached::<30>(level, &mut empty_node_cache);
-            canopy[cached_idx] = empty_node;
+            let empty_node = H::empty_node_cached::<30>(level, &mut empty_node_cache);
             inferred_nodes.push(empty_node);
         } else {
             inferred_nodes.push(canopy[cached_idx]);
@@ -191,19 +288,233 @@ fn fill_in_proof_from_canopy(
     Ok(())
 }
 
-/// This macro applies functions on a merkle roll and emits leaf information
+/// Builds a canopy from the nodes of its deepest level, ordered from left to right,
+/// and returns it along with the root that these nodes hash to
+fn build_canopy<H: Hasher>(mut level: Vec<Node>) -> (Vec<Node>, Node) {
+    let mut levels = vec![];
+    while level.len() > 1 {
+        let parents = level
+            .chunks(2)
+            .map(|siblings| H::hash_nodes(&siblings[0], &siblings[1]))
+            .collect();
+        levels.push(level);
+        level = parents;
+    }
+    // The canopy is ordered by node index, starting from the children of the root
+    let canopy = levels.into_iter().rev().flatten().collect();
+    (canopy, level[0])
+}
+
+/// Writes `chunk`, the nodes of the deepest canopy level starting at `start`, to the canopy along
+/// with the siblings from their multiproof `proof` and every ancestor derived from them.
+/// Nothing is written unless the nodes hash up to `root`.
+fn prefill_canopy_chunk<H: Hasher>(
+    canopy: &mut [Node],
+    canopy_depth: u32,
+    start: u32,
+    chunk: &[Node],
+    proof: Vec<Node>,
+    root: Node,
+) -> Result<()> {
+    let leaf_indices = (start..start + chunk.len() as u32).collect();
+    let multiproof = MultiProof::new(leaf_indices, proof);
+    let proofs = match multiproof.expand::<H>(chunk, canopy_depth as usize) {
+        Ok(proofs) => proofs,
+        Err(err) => {
+            msg!("Error expanding canopy multiproof: {}", err);
+            return err!(GummyrollError::from(&err));
+        }
+    };
+    let mut verified_nodes = vec![];
+    for ((offset, node), node_proof) in chunk.iter().enumerate().zip(proofs) {
+        let mut node = *node;
+        let mut node_idx = (1 << canopy_depth) + start + offset as u32;
+        for sibling in node_proof {
+            verified_nodes.push((node_idx, node));
+            verified_nodes.push((node_idx ^ 1, sibling));
+            H::hash_to_parent(&mut node, &sibling, node_idx & 1 == 0);
+            node_idx >>= 1;
+        }
+        if node != root {
+            msg!("Canopy nodes do not hash to the current root");
+            return err!(GummyrollError::InvalidProof);
+        }
+    }
+    for (node_idx, node) in verified_nodes {
+        // node_idx - 2 maps to the canopy index
+        canopy[node_idx as usize - 2] = node;
+    }
+    Ok(())
+}
+
+/// Appends the multiproof nodes for `leaf_indices` that are stored in the canopy to `proof`.
+/// The multiproof supplied by the caller only needs to include the nodes below the canopy.
+fn fill_in_multiproof_from_canopy<H: Hasher>(
+    canopy_bytes: &[u8],
+    max_depth: u32,
+    leaf_indices: &[u32],
+    proof: &mut Vec<Node>,
+    canopy_complete: bool,
+) -> Result<()> {
+    let mut empty_node_cache = Box::new([EMPTY; 30]);
+    check_canopy_bytes(canopy_bytes)?;
+    let canopy = cast_slice::<u8, Node>(canopy_bytes);
+    let path_len = get_cached_path_length(canopy, max_depth)?;
+    let node_indices = match multiproof_node_indices(leaf_indices, max_depth as usize) {
+        Ok(node_indices) => node_indices,
+        Err(err) => {
+            msg!("Error building multiproof: {}", err);
+            return err!(GummyrollError::from(&err));
+        }
+    };
+    for node_idx in node_indices.into_iter().skip(proof.len()) {
+        // Nodes above the canopy leaves are the last ones in the multiproof
+        if node_idx >= 1 << (path_len + 1) {
+            break;
+        }
+        if !canopy_complete {
+            msg!("Expected a multiproof up to the root while the canopy is not prefilled");
+            return err!(GummyrollError::CanopyNotComplete);
+        }
+        // node_idx - 2 maps to the canopy index
+        let cached_idx = node_idx as usize - 2;
+        if canopy[cached_idx] == EMPTY {
+            let level = max_depth - (31 - node_idx.leading_zeros());
+            proof.push(H::empty_node_cached::<30>(level, &mut empty_node_cache));
+        } else {
+            proof.push(canopy[cached_idx]);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the size of the merkle roll described by `header`,
+/// failing if its dimensions are not supported
+#[inline(always)]
+fn merkle_roll_get_size(header: &MerkleRollHeader) -> Result<usize> {
+    match merkle_roll_size(header.max_depth as usize, header.max_buffer_size as usize) {
+        Ok(size) => Ok(size),
+        Err(_) => {
+            msg!(
+                "Failed to get size of max depth {} and max buffer size {}",
+                header.max_depth,
+                header.max_buffer_size
+            );
+            err!(GummyrollError::MerkleRollConstantsError)
+        }
+    }
+}
+
+/// Splits the account data at `mid`, failing if the account is too small
+#[inline(always)]
+fn split_account_data(data: &mut [u8], mid: usize) -> Result<(&mut [u8], &mut [u8])> {
+    if data.len() < mid {
+        msg!(
+            "Merkle roll account holds {} bytes, expected at least {}",
+            data.len(),
+            mid
+        );
+        return err!(GummyrollError::InvalidAccountSize);
+    }
+    Ok(data.split_at_mut(mid))
+}
+
+/// Splits the account data into the merkle roll header and the rest of the account
+#[inline(always)]
+fn split_header_bytes(data: &mut [u8]) -> Result<(&mut [u8], &mut [u8])> {
+    split_account_data(data, size_of::<MerkleRollHeader>())
+}
+
+/// Deserializes the header of an initialized tree, failing unless it was written with the
+/// current layout. Trees with a legacy header must be upgraded with `migrate_header` first.
+#[inline(always)]
+fn deserialize_header(header_bytes: &[u8]) -> Result<Box<MerkleRollHeader>> {
+    // Legacy headers are followed by the merkle roll, which is not a valid header tail
+    let header = match MerkleRollHeader::try_from_slice(header_bytes) {
+        Ok(header) => Box::new(header),
+        Err(_) => {
+            msg!("Merkle roll header does not use the current layout, it must be upgraded with migrate_header");
+            return err!(GummyrollError::UnsupportedHeaderVersion);
+        }
+    };
+    header.assert_current_version()?;
+    Ok(header)
+}
+
+/// Evaluates `$body` with `$hasher` bound to the hasher recorded in `$header`,
+/// failing if that hasher is not compiled into this program
+macro_rules! with_hasher {
+    ($header:ident, $hasher:ident => $body:expr) => {
+        match ($header.hash_function, $header.domain_separated) {
+            (HashFunction::Keccak, false) => {
+                type $hasher = Keccak;
+                $body
+            }
+            (HashFunction::Keccak, true) => {
+                type $hasher = DomainSeparated<Keccak>;
+                $body
+            }
+            #[cfg(feature = "sha256")]
+            (HashFunction::Sha256, false) => {
+                type $hasher = Sha256;
+                $body
+            }
+            #[cfg(feature = "sha256")]
+            (HashFunction::Sha256, true) => {
+                type $hasher = DomainSeparated<Sha256>;
+                $body
+            }
+            #[cfg(feature = "poseidon")]
+            (HashFunction::Poseidon, false) => {
+                type $hasher = Poseidon;
+                $body
+            }
+            #[cfg(feature = "poseidon")]
+            (HashFunction::Poseidon, true) => {
+                type $hasher = DomainSeparated<Poseidon>;
+                $body
+            }
+            #[allow(unreachable_patterns)]
+            (hash_function, _) => {
+                msg!("Hash function {:?} is not enabled in this program", hash_function);
+                err!(GummyrollError::UnsupportedHashFunction)
+            }
+        }
+    };
+}
+
+/// Whether `H` is the hasher recorded in `header`,
+/// failing if the recorded hasher is not compiled into this program
+fn is_recorded_hasher<H: Hasher>(header: &MerkleRollHeader) -> Result<bool> {
+    with_hasher!(header, R => Ok(TypeId::of::<R>() == TypeId::of::<H>()))
+}
+
+/// This applies a given function on a merkle roll whose size is
+/// read from the header information stored on-chain, using the hasher recorded in the header,
+/// and emits leaf information
 /// needed to sync the merkle tree state with off-chain indexers.
-macro_rules! merkle_roll_depth_size_apply_fn {
-    ($max_depth:literal, $max_size:literal, $id:ident, $bytes:ident, $func:ident, $($arg:tt)*) => {
-        match MerkleRoll::<$max_depth, $max_size>::load_mut_bytes($bytes) {
-            Ok(merkle_roll) => {
+///
+/// Prefixing the arguments with `batch <num_logs>,` returns a `Vec` of the
+/// last `num_logs` changelogs instead of only the most recent one
+macro_rules! merkle_roll_apply_fn {
+    ($header:ident, batch $num_logs:expr, $id:ident, $bytes:ident, $func:ident, $($arg:tt)*) => {
+        with_hasher!($header, H => match MerkleRollMut::<H>::with_hasher($bytes, $header.max_depth as usize, $header.max_buffer_size as usize) {
+            Ok(mut merkle_roll) => {
                 match merkle_roll.$func($($arg)*) {
                     Ok(_) => {
-                        Ok(Box::<ChangeLogEvent>::from((merkle_roll.get_change_log(), $id, merkle_roll.sequence_number)))
+                        // Change logs are returned oldest first, one per modified leaf
+                        let num_logs = $num_logs as u64;
+                        Ok((0..num_logs).rev().map(|offset| {
+                            Box::<ChangeLogEvent>::from((
+                                merkle_roll.get_prior_change_log(offset),
+                                $id,
+                                merkle_roll.sequence_number() - offset,
+                            ))
+                        }).collect::<Vec<Box<ChangeLogEvent>>>())
                     }
                     Err(err) => {
                         msg!("Error using concurrent merkle tree: {}", err);
-                        err!(GummyrollError::ConcurrentMerkleTreeError)
+                        err!(GummyrollError::from(&err))
                     }
                 }
             }
@@ -211,84 +522,194 @@ macro_rules! merkle_roll_depth_size_apply_fn {
                 msg!("Error zero copying merkle roll: {}", err);
                 err!(GummyrollError::ZeroCopyError)
             }
-        }
-    }
+        })
+    };
+    ($header:ident, $id:ident, $bytes:ident, $func:ident, $($arg:tt)*) => {
+        with_hasher!($header, H => match MerkleRollMut::<H>::with_hasher($bytes, $header.max_depth as usize, $header.max_buffer_size as usize) {
+            Ok(mut merkle_roll) => {
+                match merkle_roll.$func($($arg)*) {
+                    Ok(_) => {
+                        Ok(Box::<ChangeLogEvent>::from((merkle_roll.get_change_log(), $id, merkle_roll.sequence_number())))
+                    }
+                    Err(err) => {
+                        msg!("Error using concurrent merkle tree: {}", err);
+                        err!(GummyrollError::from(&err))
+                    }
+                }
+            }
+            Err(err) => {
+                msg!("Error zero copying merkle roll: {}", err);
+                err!(GummyrollError::ZeroCopyError)
+            }
+        })
+    };
 }
 
-/// This applies a given function on a merkle roll by
-/// allowing the compiler to infer the size of the tree based
-/// upon the header information stored on-chain
-macro_rules! merkle_roll_get_size {
-    ($header:ident) => {
-        // Note: max_buffer_size MUST be a power of 2
-        match ($header.max_depth, $header.max_buffer_size) {
-            (3, 8) => Ok(size_of::<MerkleRoll<3, 8>>()),
-            (5, 8) => Ok(size_of::<MerkleRoll<5, 8>>()),
-            (14, 64) => Ok(size_of::<MerkleRoll<14, 64>>()),
-            (14, 256) => Ok(size_of::<MerkleRoll<14, 256>>()),
-            (14, 1024) => Ok(size_of::<MerkleRoll<14, 1024>>()),
-            (14, 2048) => Ok(size_of::<MerkleRoll<14, 2048>>()),
-            (20, 64) => Ok(size_of::<MerkleRoll<20, 64>>()),
-            (20, 256) => Ok(size_of::<MerkleRoll<20, 256>>()),
-            (20, 1024) => Ok(size_of::<MerkleRoll<20, 1024>>()),
-            (20, 2048) => Ok(size_of::<MerkleRoll<20, 2048>>()),
-            (24, 64) => Ok(size_of::<MerkleRoll<24, 64>>()),
-            (24, 256) => Ok(size_of::<MerkleRoll<24, 256>>()),
-            (24, 512) => Ok(size_of::<MerkleRoll<24, 512>>()),
-            (24, 1024) => Ok(size_of::<MerkleRoll<24, 1024>>()),
-            (24, 2048) => Ok(size_of::<MerkleRoll<24, 2048>>()),
-            (26, 512) => Ok(size_of::<MerkleRoll<26, 512>>()),
-            (26, 1024) => Ok(size_of::<MerkleRoll<26, 1024>>()),
-            (26, 2048) => Ok(size_of::<MerkleRoll<26, 2048>>()),
-            (30, 512) => Ok(size_of::<MerkleRoll<30, 512>>()),
-            (30, 1024) => Ok(size_of::<MerkleRoll<30, 1024>>()),
-            (30, 2048) => Ok(size_of::<MerkleRoll<30, 2048>>()),
-            _ => {
-                msg!(
-                    "Failed to get size of max depth {} and max buffer size {}",
-                    $header.max_depth,
-                    $header.max_buffer_size
-                );
-                err!(GummyrollError::MerkleRollConstantsError)
-            }
+/// Builds the proof for the leaf at `index` from the nodes passed in the remaining accounts.
+/// Levels marked in the `empty_levels` bitmap are filled in with empty nodes, and
+/// the top of the proof is filled in from the canopy.
+fn get_proof<H: Hasher>(
+    remaining_accounts: &[AccountInfo],
+    canopy_bytes: &[u8],
+    max_depth: u32,
+    index: u32,
+    empty_levels: u32,
+    canopy_complete: bool,
+) -> Result<Vec<Node>> {
+    let nodes: Vec<Node> = remaining_accounts
+        .iter()
+        .map(|node| node.key().to_bytes())
+        .collect();
+    let mut proof = match decompress_proof::<H>(empty_levels, &nodes, max_depth as usize) {
+        Ok(proof) => proof,
+        Err(err) => {
+            msg!("Error decompressing proof: {}", err);
+            return err!(GummyrollError::from(&err));
         }
     };
+    fill_in_proof_from_canopy::<H>(canopy_bytes, max_depth, index, &mut proof, canopy_complete)?;
+    Ok(proof)
 }
 
-/// This applies a given function on a merkle roll by
-/// allowing the compiler to infer the size of the tree based
-/// upon the header information stored on-chain
-macro_rules! merkle_roll_apply_fn {
-    ($header:ident, $id:ident, $bytes:ident, $func:ident, $($arg:tt)*) => {
-        // Note: max_buffer_size MUST be a power of 2
-        match ($header.max_depth, $header.max_buffer_size) {
-            (3, 8) => merkle_roll_depth_size_apply_fn!(3, 8, $id, $bytes, $func, $($arg)*),
-            (5, 8) => merkle_roll_depth_size_apply_fn!(5, 8, $id, $bytes, $func, $($arg)*),
-            (14, 64) => merkle_roll_depth_size_apply_fn!(14, 64, $id, $bytes, $func, $($arg)*),
-            (14, 256) => merkle_roll_depth_size_apply_fn!(14, 256, $id, $bytes, $func, $($arg)*),
-            (14, 1024) => merkle_roll_depth_size_apply_fn!(14, 1024, $id, $bytes, $func, $($arg)*),
-            (14, 2048) => merkle_roll_depth_size_apply_fn!(14, 2048, $id, $bytes, $func, $($arg)*),
-            (20, 64) => merkle_roll_depth_size_apply_fn!(20, 64, $id, $bytes, $func, $($arg)*),
-            (20, 256) => merkle_roll_depth_size_apply_fn!(20, 256, $id, $bytes, $func, $($arg)*),
-            (20, 1024) => merkle_roll_depth_size_apply_fn!(20, 1024, $id, $bytes, $func, $($arg)*),
-            (20, 2048) => merkle_roll_depth_size_apply_fn!(20, 2048, $id, $bytes, $func, $($arg)*),
-            (24, 64) => merkle_roll_depth_size_apply_fn!(24, 64, $id, $bytes, $func, $($arg)*),
-            (24, 256) => merkle_roll_depth_size_apply_fn!(24, 256, $id, $bytes, $func, $($arg)*),
-            (24, 512) => merkle_roll_depth_size_apply_fn!(24, 512, $id, $bytes, $func, $($arg)*),
-            (24, 1024) => merkle_roll_depth_size_apply_fn!(24, 1024, $id, $bytes, $func, $($arg)*),
-            (24, 2048) => merkle_roll_depth_size_apply_fn!(24, 2048, $id, $bytes, $func, $($arg)*),
-            (26, 512) => merkle_roll_depth_size_apply_fn!(26, 512, $id, $bytes, $func, $($arg)*),
-            (26, 1024) => merkle_roll_depth_size_apply_fn!(26, 1024, $id, $bytes, $func, $($arg)*),
-            (26, 2048) => merkle_roll_depth_size_apply_fn!(26, 2048, $id, $bytes, $func, $($arg)*),
-            (30, 512) => merkle_roll_depth_size_apply_fn!(30, 512, $id, $bytes, $func, $($arg)*),
-            (30, 1024) => merkle_roll_depth_size_apply_fn!(30, 1024, $id, $bytes, $func, $($arg)*),
-            (30, 2048) => merkle_roll_depth_size_apply_fn!(30, 2048, $id, $bytes, $func, $($arg)*),
-            _ => {
-                msg!("Failed to apply {} on merkle roll with max depth {} and max buffer size {}", stringify!($func), $header.max_depth, $header.max_buffer_size);
-                err!(GummyrollError::MerkleRollConstantsError)
-            }
+fn process_replace_leaf(
+    ctx: Context<Modify>,
+    root: [u8; 32],
+    previous_leaf: [u8; 32],
+    new_leaf: [u8; 32],
+    index: u32,
+    empty_levels: u32,
+) -> Result<()> {
+    let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+    let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+
+    let header = deserialize_header(header_bytes)?;
+    header.assert_valid_authority(&ctx.accounts.authority.key())?;
+    let merkle_roll_size = merkle_roll_get_size(&header)?;
+    let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+
+    let proof = with_hasher!(header, H => get_proof::<H>(
+        ctx.remaining_accounts,
+        canopy_bytes,
+        header.max_depth,
+        index,
+        empty_levels,
+        header.is_canopy_complete(),
+    ))?;
+    let id = ctx.accounts.merkle_roll.key();
+    // A call is made to MerkleRollMut::set_leaf(root, previous_leaf, new_leaf, proof, index)
+    let change_log = merkle_roll_apply_fn!(
+        header,
+        id,
+        roll_bytes,
+        set_leaf,
+        root,
+        previous_leaf,
+        new_leaf,
+        &proof,
+        index,
+    )?;
+    wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+    emit!(*change_log);
+    update_canopy(canopy_bytes, header.max_depth, Some(change_log))
+}
+
+fn process_verify_leaf(
+    ctx: Context<VerifyLeaf>,
+    root: [u8; 32],
+    leaf: [u8; 32],
+    index: u32,
+    empty_levels: u32,
+) -> Result<()> {
+    // The tree is only read, so the merkle roll does not need to be writable
+    let merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_data()?;
+    let account = MerkleRollAccount::deserialize(&merkle_roll_bytes)?;
+    let header = &account.header;
+
+    with_hasher!(header, H => {
+        let proof = get_proof::<H>(
+            ctx.remaining_accounts,
+            cast_slice(account.canopy()),
+            header.max_depth,
+            index,
+            empty_levels,
+            header.is_canopy_complete(),
+        )?;
+        let merkle_roll = account.merkle_roll::<H>()?;
+        if let Err(err) = merkle_roll.prove_leaf(root, leaf, &proof, index) {
+            msg!("Error using concurrent merkle tree: {}", err);
+            return err!(GummyrollError::from(&err));
         }
-    };
+        Ok(())
+    })
+}
+
+fn process_insert_or_append(
+    ctx: Context<Modify>,
+    root: [u8; 32],
+    leaf: [u8; 32],
+    index: u32,
+    empty_levels: u32,
+) -> Result<()> {
+    let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+    let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+    let header = deserialize_header(header_bytes)?;
+    header.assert_valid_authority(&ctx.accounts.authority.key())?;
+    let merkle_roll_size = merkle_roll_get_size(&header)?;
+    let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+
+    let proof = with_hasher!(header, H => get_proof::<H>(
+        ctx.remaining_accounts,
+        canopy_bytes,
+        header.max_depth,
+        index,
+        empty_levels,
+        header.is_canopy_complete(),
+    ))?;
+    // A call is made to MerkleRollMut::fill_empty_or_append
+    let id = ctx.accounts.merkle_roll.key();
+    let change_log = merkle_roll_apply_fn!(
+        header,
+        id,
+        roll_bytes,
+        fill_empty_or_append,
+        root,
+        leaf,
+        &proof,
+        index,
+    )?;
+    wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+    emit!(*change_log);
+    update_canopy(canopy_bytes, header.max_depth, Some(change_log))
+}
+
+fn process_init_empty_gummyroll(
+    ctx: Context<Initialize>,
+    max_depth: u32,
+    max_buffer_size: u32,
+    hash_function: HashFunction,
+    domain_separated: bool,
+) -> Result<()> {
+    let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+
+    let (mut header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+
+    let mut header = Box::new(MerkleRollHeader::try_from_slice(&header_bytes)?);
+    header.initialize(
+        max_depth,
+        max_buffer_size,
+        &ctx.accounts.authority.key(),
+        Clock::get()?.slot,
+        hash_function,
+        domain_separated,
+    )?;
+    header.serialize(&mut header_bytes)?;
+    let merkle_roll_size = merkle_roll_get_size(&header)?;
+    let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+    let id = ctx.accounts.merkle_roll.key();
+    let change_log = merkle_roll_apply_fn!(header, id, roll_bytes, initialize,)?;
+    wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+    emit!(*change_log);
+    update_canopy(canopy_bytes, header.max_depth, None)
 }
 
 #[program]
@@ -310,26 +731,37 @@ pub mod gummyroll {
         max_depth: u32,
         max_buffer_size: u32,
     ) -> Result<()> {
-        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-
-        let (mut header_bytes, rest) =
-            merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
+        process_init_empty_gummyroll(
+            ctx,
+            max_depth,
+            max_buffer_size,
+            HashFunction::Keccak,
+            false,
+        )
+    }
 
-        let mut header = Box::new(MerkleRollHeader::try_from_slice(&header_bytes)?);
-        header.initialize(
+    /// Same as `init_empty_gummyroll`, for a tree whose nodes are computed with `hash_function`.
+    ///
+    /// If `domain_separated` is set, internal nodes are hashed with a prefix that leaves never
+    /// use, so an internal node cannot be passed off as a leaf. Leaves of such trees must be
+    /// hashed with `Hasher::hash_leaf` by the program that builds them.
+    ///
+    /// Both settings are recorded in the tree header and used by every later instruction.
+    /// Fails if `hash_function` is not enabled in this build of gummyroll.
+    pub fn init_empty_gummyroll_with_hasher(
+        ctx: Context<Initialize>,
+        max_depth: u32,
+        max_buffer_size: u32,
+        hash_function: HashFunction,
+        domain_separated: bool,
+    ) -> Result<()> {
+        process_init_empty_gummyroll(
+            ctx,
             max_depth,
             max_buffer_size,
-            &ctx.accounts.authority.key(),
-            Clock::get()?.slot,
-        );
-        header.serialize(&mut header_bytes)?;
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
-        let id = ctx.accounts.merkle_roll.key();
-        let change_log = merkle_roll_apply_fn!(header, id, roll_bytes, initialize,)?;
-        wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
-        emit!(*change_log);
-        update_canopy(canopy_bytes, header.max_depth, None)
+            hash_function,
+            domain_separated,
+        )
     }
 
     /// Note:
@@ -350,8 +782,7 @@ pub mod gummyroll {
     ) -> Result<()> {
         let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
 
-        let (mut header_bytes, rest) =
-            merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
+        let (mut header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
 
         let mut header = Box::new(MerkleRollHeader::try_from_slice(&header_bytes)?);
         header.initialize(
@@ -359,21 +790,42 @@ pub mod gummyroll {
             max_buffer_size,
             &ctx.accounts.authority.key(),
             Clock::get()?.slot,
-        );
+            HashFunction::Keccak,
+            false,
+        )?;
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+        // The canopy only holds the rightmost path until it is uploaded with `prefill_canopy`
+        check_canopy_bytes(canopy_bytes)?;
+        let canopy_depth = get_cached_path_length(cast_slice::<u8, Node>(canopy_bytes), max_depth)?;
+        if canopy_depth > 0 {
+            header.pending_canopy_nodes = 1 << canopy_depth;
+        }
         header.serialize(&mut header_bytes)?;
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
 
         // Get rightmost proof from accounts
         let mut proof = vec![];
         for node in ctx.remaining_accounts.iter() {
             proof.push(node.key().to_bytes());
         }
-        fill_in_proof_from_canopy(canopy_bytes, header.max_depth, index, &mut proof)?;
-        assert_eq!(proof.len(), max_depth as usize);
+        fill_in_proof_from_canopy::<Keccak>(
+            canopy_bytes,
+            header.max_depth,
+            index,
+            &mut proof,
+            header.is_canopy_complete(),
+        )?;
+        if proof.len() != max_depth as usize {
+            msg!(
+                "Expected a proof of {} nodes, found {}",
+                max_depth,
+                proof.len()
+            );
+            return err!(GummyrollError::InvalidProof);
+        }
 
         let id = ctx.accounts.merkle_roll.key();
-        // A call is made to MerkleRoll::initialize_with_root(root, leaf, proof, index)
+        // A call is made to MerkleRollMut::initialize_with_root(root, leaf, proof, index)
         let change_log = merkle_roll_apply_fn!(
             header,
             id,
@@ -398,36 +850,84 @@ pub mod gummyroll {
         previous_leaf: [u8; 32],
         new_leaf: [u8; 32],
         index: u32,
+    ) -> Result<()> {
+        process_replace_leaf(ctx, root, previous_leaf, new_leaf, index, 0)
+    }
+
+    /// Same as `replace_leaf`, but the proof passed in the remaining accounts omits empty siblings.
+    /// Bit `i` of `empty_levels` is set if the sibling at level `i` is an empty node.
+    pub fn replace_leaf_compressed(
+        ctx: Context<Modify>,
+        root: [u8; 32],
+        previous_leaf: [u8; 32],
+        new_leaf: [u8; 32],
+        index: u32,
+        empty_levels: u32,
+    ) -> Result<()> {
+        process_replace_leaf(ctx, root, previous_leaf, new_leaf, index, empty_levels)
+    }
+
+    /// Replaces several leaves at once, emitting one changelog per leaf.
+    ///
+    /// `replacements` must be sorted by strictly increasing index. Instead of one proof per leaf,
+    /// the remaining accounts hold the multiproof of the previous leaves against `root`, without
+    /// the nodes that are stored in the canopy.
+    /// Either every leaf is replaced or the instruction fails.
+    pub fn replace_leaves(
+        ctx: Context<Modify>,
+        root: [u8; 32],
+        replacements: Vec<LeafReplacement>,
     ) -> Result<()> {
         let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-        let (header_bytes, rest) = merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
+        let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
 
-        let header = Box::new(MerkleRollHeader::try_from_slice(header_bytes)?);
-        assert_eq!(header.authority, ctx.accounts.authority.key());
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
+        let header = deserialize_header(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        if replacements.len() > header.max_buffer_size as usize {
+            msg!(
+                "Cannot replace {} leaves in a merkle roll with max buffer size {}",
+                replacements.len(),
+                header.max_buffer_size
+            );
+            return err!(GummyrollError::BatchSizeExceedsBufferSize);
+        }
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
 
+        let leaf_indices: Vec<u32> = replacements.iter().map(|r| r.index).collect();
         let mut proof = vec![];
         for node in ctx.remaining_accounts.iter() {
             proof.push(node.key().to_bytes());
         }
-        fill_in_proof_from_canopy(canopy_bytes, header.max_depth, index, &mut proof)?;
+        with_hasher!(header, H => fill_in_multiproof_from_canopy::<H>(
+            canopy_bytes,
+            header.max_depth,
+            &leaf_indices,
+            &mut proof,
+            header.is_canopy_complete(),
+        ))?;
+        let leaves: Vec<(Node, Node, u32)> = replacements
+            .iter()
+            .map(|r| (r.previous_leaf, r.new_leaf, r.index))
+            .collect();
         let id = ctx.accounts.merkle_roll.key();
-        // A call is made to MerkleRoll::set_leaf(root, previous_leaf, new_leaf, proof, index)
-        let change_log = merkle_roll_apply_fn!(
+        // A call is made to MerkleRollMut::set_leaves(root, leaves, proof)
+        let change_logs = merkle_roll_apply_fn!(
             header,
+            batch leaves.len(),
             id,
             roll_bytes,
-            set_leaf,
+            set_leaves,
             root,
-            previous_leaf,
-            new_leaf,
-            &proof,
-            index,
+            &leaves,
+            &proof
         )?;
-        wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
-        emit!(*change_log);
-        update_canopy(canopy_bytes, header.max_depth, Some(change_log))
+        for change_log in change_logs {
+            wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+            emit!(*change_log);
+            update_canopy(canopy_bytes, header.max_depth, Some(change_log))?;
+        }
+        Ok(())
     }
 
     /// Transfers `authority`
@@ -437,10 +937,10 @@ pub mod gummyroll {
         new_authority: Pubkey,
     ) -> Result<()> {
         let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-        let (mut header_bytes, _) = merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
+        let (mut header_bytes, _) = split_header_bytes(&mut merkle_roll_bytes)?;
 
-        let mut header = Box::new(MerkleRollHeader::try_from_slice(header_bytes)?);
-        assert_eq!(header.authority, ctx.accounts.authority.key());
+        let mut header = deserialize_header(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
 
         header.authority = new_authority;
         msg!("Authority transferred to: {:?}", header.authority);
@@ -451,27 +951,28 @@ pub mod gummyroll {
 
     /// Verifies a provided proof and leaf.
     /// If invalid, throws an error.
+    ///
+    /// Neither the tree nor any other account is modified, so programs can invoke this instruction
+    /// to check that a leaf is in a tree, see `utils::verify_leaf_cpi`.
     pub fn verify_leaf(
         ctx: Context<VerifyLeaf>,
         root: [u8; 32],
         leaf: [u8; 32],
         index: u32,
     ) -> Result<()> {
-        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-        let (header_bytes, rest) = merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
-        let header = Box::new(MerkleRollHeader::try_from_slice(header_bytes)?);
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
-
-        let mut proof = vec![];
-        for node in ctx.remaining_accounts.iter() {
-            proof.push(node.key().to_bytes());
-        }
-        fill_in_proof_from_canopy(canopy_bytes, header.max_depth, index, &mut proof)?;
-        let id = ctx.accounts.merkle_roll.key();
+        process_verify_leaf(ctx, root, leaf, index, 0)
+    }
 
-        merkle_roll_apply_fn!(header, id, roll_bytes, prove_leaf, root, leaf, &proof, index)?;
-        Ok(())
+    /// Same as `verify_leaf`, but the proof passed in the remaining accounts omits empty siblings.
+    /// Bit `i` of `empty_levels` is set if the sibling at level `i` is an empty node.
+    pub fn verify_leaf_compressed(
+        ctx: Context<VerifyLeaf>,
+        root: [u8; 32],
+        leaf: [u8; 32],
+        index: u32,
+        empty_levels: u32,
+    ) -> Result<()> {
+        process_verify_leaf(ctx, root, leaf, index, empty_levels)
     }
 
     /// This instruction allows the tree's `authority` to append a new leaf to the tree
@@ -481,20 +982,303 @@ pub mod gummyroll {
     /// valid proof, and then updating the rightmost_proof for the next leaf if possible.
     pub fn append(ctx: Context<Modify>, leaf: [u8; 32]) -> Result<()> {
         let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-        let (header_bytes, rest) = merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
+        let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
 
-        let header = Box::new(MerkleRollHeader::try_from_slice(header_bytes)?);
-        assert_eq!(header.authority, ctx.accounts.authority.key());
+        let header = deserialize_header(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
 
         let id = ctx.accounts.merkle_roll.key();
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
         let change_log = merkle_roll_apply_fn!(header, id, roll_bytes, append, leaf)?;
         wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
         emit!(*change_log);
         update_canopy(canopy_bytes, header.max_depth, Some(change_log))
     }
 
+    /// This instruction allows the tree's `authority` to append a batch of leaves to the tree
+    /// without having to supply a valid proof.
+    ///
+    /// The result is identical to invoking `append` once per leaf, including emitting one changelog
+    /// per leaf, so the hashing cost is the same. Batching saves the per instruction overhead and
+    /// appends either every leaf or none of them.
+    /// A batch cannot contain more leaves than the tree's `max_buffer_size`.
+    pub fn append_batch(ctx: Context<Modify>, leaves: Vec<[u8; 32]>) -> Result<()> {
+        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+        let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+
+        let header = deserialize_header(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        if leaves.len() > header.max_buffer_size as usize {
+            msg!(
+                "Cannot append {} leaves to a merkle roll with max buffer size {}",
+                leaves.len(),
+                header.max_buffer_size
+            );
+            return err!(GummyrollError::BatchSizeExceedsBufferSize);
+        }
+
+        let id = ctx.accounts.merkle_roll.key();
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (roll_bytes, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+        // A call is made to MerkleRollMut::append_batch(leaves)
+        let change_logs = merkle_roll_apply_fn!(
+            header,
+            batch leaves.len(),
+            id,
+            roll_bytes,
+            append_batch,
+            &leaves
+        )?;
+        for change_log in change_logs {
+            wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
+            emit!(*change_log);
+            update_canopy(canopy_bytes, header.max_depth, Some(change_log))?;
+        }
+        Ok(())
+    }
+
+    /// Closes a tree that holds no data and returns its lamports to `recipient`.
+    ///
+    /// A tree holds no data if its current root is the root of an empty tree of the same depth,
+    /// meaning that nothing was ever appended or that every leaf was replaced with an empty node.
+    /// The account data is zeroed out so the tree cannot be used again.
+    pub fn close_empty_tree(ctx: Context<CloseTree>) -> Result<()> {
+        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+        let (header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+
+        let header = deserialize_header(header_bytes)?;
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (roll_bytes, _) = split_account_data(rest, merkle_roll_size)?;
+        with_hasher!(header, H => {
+            let merkle_roll = match MerkleRollRef::<H>::with_hasher(
+                roll_bytes,
+                header.max_depth as usize,
+                header.max_buffer_size as usize,
+            ) {
+                Ok(merkle_roll) => merkle_roll,
+                Err(err) => {
+                    msg!("Error zero copying merkle roll: {}", err);
+                    return err!(GummyrollError::ZeroCopyError);
+                }
+            };
+            if *merkle_roll.get_change_log().root != H::empty_node(header.max_depth) {
+                msg!("Cannot close a tree that still holds data");
+                return err!(GummyrollError::TreeNotEmpty);
+            }
+            Ok(())
+        })?;
+        merkle_roll_bytes.fill(0);
+
+        let merkle_roll_info = ctx.accounts.merkle_roll.to_account_info();
+        let recipient = ctx.accounts.recipient.to_account_info();
+        **recipient.lamports.borrow_mut() = recipient
+            .lamports()
+            .checked_add(merkle_roll_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **merkle_roll_info.lamports.borrow_mut() = 0;
+        Ok(())
+    }
+
+    /// Reallocates the tree account to cache the top `canopy_depth` levels of the tree
+    /// in the canopy, so that shorter proofs can be passed to modify the tree.
+    ///
+    /// The remaining accounts hold the `2^canopy_depth` nodes of the deepest canopy level,
+    /// ordered from left to right, which must hash up to the current root.
+    /// All of them must fit in a single transaction, so only small canopies can be grown at once.
+    /// `payer` funds the rent of the larger account.
+    pub fn grow_canopy(ctx: Context<GrowCanopy>, canopy_depth: u32) -> Result<()> {
+        let (mut header, root, current_canopy_depth) = {
+            let merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_data()?;
+            let account = MerkleRollAccount::deserialize(&merkle_roll_bytes)?;
+            let root = account.root();
+            let current_canopy_depth = account.canopy_depth();
+            (account.header, root, current_canopy_depth)
+        };
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        if canopy_depth <= current_canopy_depth || canopy_depth > header.max_depth {
+            msg!(
+                "Cannot grow a canopy of depth {} to depth {} in a tree of depth {}",
+                current_canopy_depth,
+                canopy_depth,
+                header.max_depth
+            );
+            return err!(GummyrollError::CanopyLengthMismatch);
+        }
+        let nodes: Vec<Node> = ctx
+            .remaining_accounts
+            .iter()
+            .map(|node| node.key().to_bytes())
+            .collect();
+        if nodes.len() != 1 << canopy_depth {
+            msg!(
+                "Expected {} canopy nodes, found {}",
+                1 << canopy_depth,
+                nodes.len()
+            );
+            return err!(GummyrollError::CanopyLengthMismatch);
+        }
+        let (canopy, canopy_root) = with_hasher!(header, H => Ok(build_canopy::<H>(nodes)))?;
+        if canopy_root != root {
+            msg!("Canopy nodes do not hash to the current root");
+            return err!(GummyrollError::InvalidProof);
+        }
+
+        let merkle_roll_info = ctx.accounts.merkle_roll.to_account_info();
+        let account_size =
+            merkle_roll_account_size(header.max_depth, header.max_buffer_size, canopy_depth)?;
+        let rent_due = Rent::get()?
+            .minimum_balance(account_size)
+            .saturating_sub(merkle_roll_info.lamports());
+        if rent_due > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: merkle_roll_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        merkle_roll_info.realloc(account_size, false)?;
+
+        let mut merkle_roll_bytes = merkle_roll_info.try_borrow_mut_data()?;
+        let (mut header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (_, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+        canopy_bytes.copy_from_slice(cast_slice(&canopy));
+        // Every canopy node was verified against the root, so the canopy is complete
+        header.pending_canopy_nodes = 0;
+        header.serialize(&mut header_bytes)?;
+        Ok(())
+    }
+
+    /// Uploads the canopy of a tree initialized with `init_gummyroll_with_root`, which only
+    /// holds the rightmost path until then, so that it can be used to shorten proofs.
+    ///
+    /// `nodes` are nodes of the deepest canopy level, ordered from left to right and starting at
+    /// `start_index`. Chunks must be uploaded in order, the first one starting at index 0.
+    /// The remaining accounts hold the multiproof of `nodes` against the current root, in the tree
+    /// formed by the top `canopy_depth` levels. The nodes, their multiproof and every node derived
+    /// from them are written to the canopy. Once the last chunk is uploaded the canopy is complete,
+    /// and proofs passed to modify the tree no longer need to include the canopy nodes.
+    pub fn prefill_canopy(
+        ctx: Context<PrefillCanopy>,
+        start_index: u32,
+        nodes: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let (mut header, root, canopy_depth) = {
+            let merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_data()?;
+            let account = MerkleRollAccount::deserialize(&merkle_roll_bytes)?;
+            let root = account.root();
+            let canopy_depth = account.canopy_depth();
+            (account.header, root, canopy_depth)
+        };
+        header.assert_valid_authority(&ctx.accounts.authority.key())?;
+        let pending_start = (1 << canopy_depth) - header.pending_canopy_nodes;
+        if start_index != pending_start
+            || nodes.is_empty()
+            || nodes.len() > header.pending_canopy_nodes as usize
+        {
+            msg!(
+                "Expected at most {} canopy nodes starting at index {}, found {} at index {}",
+                header.pending_canopy_nodes,
+                pending_start,
+                nodes.len(),
+                start_index
+            );
+            return err!(GummyrollError::InvalidCanopyChunk);
+        }
+        let proof: Vec<Node> = ctx
+            .remaining_accounts
+            .iter()
+            .map(|node| node.key().to_bytes())
+            .collect();
+
+        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
+        let (mut header_bytes, rest) = split_header_bytes(&mut merkle_roll_bytes)?;
+        let merkle_roll_size = merkle_roll_get_size(&header)?;
+        let (_, canopy_bytes) = split_account_data(rest, merkle_roll_size)?;
+        let canopy = cast_slice_mut::<u8, Node>(canopy_bytes);
+        with_hasher!(header, H => prefill_canopy_chunk::<H>(
+            canopy,
+            canopy_depth,
+            start_index,
+            &nodes,
+            proof,
+            root,
+        ))?;
+        header.pending_canopy_nodes -= nodes.len() as u32;
+        header.serialize(&mut header_bytes)?;
+        Ok(())
+    }
+
+    /// Upgrades a tree created with the legacy 48 byte header to the current header layout,
+    /// which every other instruction requires.
+    ///
+    /// The account is reallocated to hold the larger header and the merkle roll and canopy are
+    /// moved after it, unchanged. The upgraded tree is hashed with `Keccak`, without domain
+    /// separation, like every legacy tree. `payer` funds the rent of the larger account.
+    pub fn migrate_header(ctx: Context<MigrateHeader>) -> Result<()> {
+        let merkle_roll_info = ctx.accounts.merkle_roll.to_account_info();
+        let (header, legacy_len) = {
+            let merkle_roll_bytes = merkle_roll_info.try_borrow_data()?;
+            if merkle_roll_bytes.len() < LEGACY_MERKLE_ROLL_HEADER_SIZE {
+                msg!(
+                    "Merkle roll account holds {} bytes, expected at least {}",
+                    merkle_roll_bytes.len(),
+                    LEGACY_MERKLE_ROLL_HEADER_SIZE
+                );
+                return err!(GummyrollError::InvalidAccountSize);
+            }
+            let (legacy_header_bytes, rest) =
+                merkle_roll_bytes.split_at(LEGACY_MERKLE_ROLL_HEADER_SIZE);
+            let legacy_header = LegacyMerkleRollHeader::try_from_slice(legacy_header_bytes)?;
+            let header = Box::new(MerkleRollHeader::from_legacy(&legacy_header));
+            header.assert_valid_authority(&ctx.accounts.authority.key())?;
+            // Both layouts start with the tree dimensions. Since the current header is 8 bytes
+            // longer, only one of them leaves a canopy of 2^n - 2 nodes after the merkle roll.
+            let merkle_roll_size = merkle_roll_get_size(&header)?;
+            let is_legacy = rest.len() >= merkle_roll_size && {
+                let canopy_bytes = &rest[merkle_roll_size..];
+                canopy_bytes.len() % size_of::<Node>() == 0
+                    && get_cached_path_length(cast_slice(canopy_bytes), header.max_depth).is_ok()
+            };
+            if !is_legacy {
+                msg!("Merkle roll account does not hold a tree with a legacy header");
+                return err!(GummyrollError::InvalidLegacyHeader);
+            }
+            (header, merkle_roll_bytes.len())
+        };
+
+        let account_size =
+            legacy_len + size_of::<MerkleRollHeader>() - LEGACY_MERKLE_ROLL_HEADER_SIZE;
+        let rent_due = Rent::get()?
+            .minimum_balance(account_size)
+            .saturating_sub(merkle_roll_info.lamports());
+        if rent_due > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: merkle_roll_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        merkle_roll_info.realloc(account_size, false)?;
+
+        let mut merkle_roll_bytes = merkle_roll_info.try_borrow_mut_data()?;
+        merkle_roll_bytes.copy_within(
+            LEGACY_MERKLE_ROLL_HEADER_SIZE..legacy_len,
+            size_of::<MerkleRollHeader>(),
+        );
+        let (mut header_bytes, _) = split_header_bytes(&mut merkle_roll_bytes)?;
+        header.serialize(&mut header_bytes)?;
+        Ok(())
+    }
+
     /// This instruction takes a proof, and will attempt to write the given leaf
     /// to the specified index in the tree. If the insert operation fails, the leaf will be `append`-ed
     /// to the tree.
@@ -505,32 +1289,18 @@ pub mod gummyroll {
         leaf: [u8; 32],
         index: u32,
     ) -> Result<()> {
-        let mut merkle_roll_bytes = ctx.accounts.merkle_roll.try_borrow_mut_data()?;
-        let (header_bytes, rest) = merkle_roll_bytes.split_at_mut(size_of::<MerkleRollHeader>());
-        let header = Box::new(MerkleRollHeader::try_from_slice(header_bytes)?);
-        assert_eq!(header.authority, ctx.accounts.authority.key());
-        let merkle_roll_size = merkle_roll_get_size!(header)?;
-        let (roll_bytes, canopy_bytes) = rest.split_at_mut(merkle_roll_size);
+        process_insert_or_append(ctx, root, leaf, index, 0)
+    }
 
-        let mut proof = vec![];
-        for node in ctx.remaining_accounts.iter() {
-            proof.push(node.key().to_bytes());
-        }
-        fill_in_proof_from_canopy(canopy_bytes, header.max_depth, index, &mut proof)?;
-        // A call is made to MerkleRoll::fill_empty_or_append
-        let id = ctx.accounts.merkle_roll.key();
-        let change_log = merkle_roll_apply_fn!(
-            header,
-            id,
-            roll_bytes,
-            fill_empty_or_append,
-            root,
-            leaf,
-            &proof,
-            index,
-        )?;
-        wrap_event(change_log.try_to_vec()?, &ctx.accounts.candy_wrapper)?;
-        emit!(*change_log);
-        update_canopy(canopy_bytes, header.max_depth, Some(change_log))
+    /// Same as `insert_or_append`, but the proof passed in the remaining accounts omits empty siblings.
+    /// Bit `i` of `empty_levels` is set if the sibling at level `i` is an empty node.
+    pub fn insert_or_append_compressed(
+        ctx: Context<Modify>,
+        root: [u8; 32],
+        leaf: [u8; 32],
+        index: u32,
+        empty_levels: u32,
+    ) -> Result<()> {
+        process_insert_or_append(ctx, root, leaf, index, empty_levels)
     }
 }
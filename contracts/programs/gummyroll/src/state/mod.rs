@@ -1,8 +1,13 @@
 //! State related to storing a buffer of Merkle tree roots on-chain.
 //!
+use crate::error::GummyrollError;
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
-use concurrent_merkle_tree::state::{ChangeLog, Node};
+use concurrent_merkle_tree::{
+    merkle_roll_view::ChangeLogRef,
+    replay::{self, ChangeLogRecord},
+    state::{ChangeLog, Node},
+};
 
 #[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug)]
 pub struct PathNode {
@@ -19,6 +24,14 @@ impl PathNode {
     }
 }
 
+/// Single leaf replacement of a `replace_leaves` instruction
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug)]
+pub struct LeafReplacement {
+    pub previous_leaf: [u8; 32],
+    pub new_leaf: [u8; 32],
+    pub index: u32,
+}
+
 #[event]
 pub struct NewLeafEvent {
     /// Public key of the merkle roll
@@ -41,56 +54,122 @@ pub struct ChangeLogEvent {
     /// Bitmap of node parity (used when hashing)
     pub index: u32,
 }
+impl ChangeLogEvent {
+    pub fn new(id: Pubkey, root: Node, changelog_path: &[Node], seq: u64, index: u32) -> Self {
+        let path_len = changelog_path.len() as u32;
+        let mut path: Vec<PathNode> = changelog_path
+            .iter()
+            .enumerate()
+            .map(|(lvl, n)| PathNode::new(*n, (1 << (path_len - lvl as u32)) + (index >> lvl)))
+            .collect();
+        path.push(PathNode::new(root, 1));
+        Self {
+            id,
+            path,
+            seq,
+            index,
+        }
+    }
+}
+
 //  ChangeLog<MAX_DEPTH>
 impl<const MAX_DEPTH: usize> From<(Box<ChangeLog<MAX_DEPTH>>, Pubkey, u64)>
     for Box<ChangeLogEvent>
 {
     fn from(log_info: (Box<ChangeLog<MAX_DEPTH>>, Pubkey, u64)) -> Self {
         let (changelog, tree_id, seq) = log_info;
-        let path_len = changelog.path.len() as u32;
-        let mut path: Vec<PathNode> = changelog
-            .path
-            .iter()
-            .enumerate()
-            .map(|(lvl, n)| {
-                PathNode::new(
-                    *n,
-                    (1 << (path_len - lvl as u32)) + (changelog.index >> lvl),
-                )
-            })
-            .collect();
-        path.push(PathNode::new(changelog.root, 1));
-        Box::new(ChangeLogEvent {
-            id: tree_id,
-            path,
+        Box::new(ChangeLogEvent::new(
+            tree_id,
+            changelog.root,
+            &changelog.path,
             seq,
-            index: changelog.index,
-        })
+            changelog.index,
+        ))
     }
 }
 
-/// Initialization parameters for a Gummyroll Merkle tree.
+//  ChangeLogRef read from a runtime-sized merkle roll
+impl<'a> From<(ChangeLogRef<'a>, Pubkey, u64)> for Box<ChangeLogEvent> {
+    fn from(log_info: (ChangeLogRef<'a>, Pubkey, u64)) -> Self {
+        let (changelog, tree_id, seq) = log_info;
+        Box::new(ChangeLogEvent::new(
+            tree_id,
+            *changelog.root,
+            changelog.path,
+            seq,
+            changelog.index,
+        ))
+    }
+}
+
+// ChangeLogRecord consumed by `concurrent_merkle_tree::replay::TreeReplayer`
+impl From<&ChangeLogEvent> for ChangeLogRecord {
+    fn from(event: &ChangeLogEvent) -> Self {
+        ChangeLogRecord {
+            path: event
+                .path
+                .iter()
+                .map(|path_node| replay::PathNode {
+                    node: path_node.node,
+                    index: path_node.index,
+                })
+                .collect(),
+            seq: event.seq,
+            index: event.index,
+        }
+    }
+}
+
+/// Hash function used to compute the nodes of a Gummyroll Merkle tree.
 ///
-/// Only the following permutations are valid:
+/// `Sha256` and `Poseidon` trees can only be used if gummyroll is built with
+/// the `sha256` and `poseidon` features respectively.
+#[derive(AnchorDeserialize, AnchorSerialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HashFunction {
+    Keccak,
+    Sha256,
+    Poseidon,
+}
+
+/// Version of the `MerkleRollHeader` layout written by this program.
 ///
-/// | max_depth | max_buffer_size       |
-/// | --------- | --------------------- |
-/// | 14        | (64, 256, 1024, 2048) |           
-/// | 20        | (64, 256, 1024, 2048) |           
-/// | 24        | (64, 256, 512, 1024, 2048) |           
-/// | 26        | (64, 256, 512, 1024, 2048) |           
-/// | 30        | (512, 1024, 2048) |           
+/// Trees created before the header held a layout version use a 48 byte header without the
+/// hash function, domain separation and canopy fields. They must be upgraded with
+/// `migrate_header` before any other instruction can be used on them.
+pub const MERKLE_ROLL_HEADER_VERSION: u8 = 1;
+
+/// Byte length of the header of trees created before the header held a layout version,
+/// see [LegacyMerkleRollHeader]
+pub const LEGACY_MERKLE_ROLL_HEADER_SIZE: usize = 48;
+
+/// Header of trees created before the header held a layout version.
+/// Such trees are always hashed with `Keccak`, without domain separation.
+#[derive(BorshDeserialize, BorshSerialize)]
+#[repr(C)]
+pub struct LegacyMerkleRollHeader {
+    pub max_buffer_size: u32,
+    pub max_depth: u32,
+    pub authority: Pubkey,
+    pub creation_slot: u64,
+}
+
+/// Initialization parameters for a Gummyroll Merkle tree.
+///
+/// `max_depth` can be at most 30 and `max_buffer_size` must be a power of 2.
+/// The merkle roll stored after the header is sized at runtime from these values,
+/// see `concurrent_merkle_tree::merkle_roll_view::merkle_roll_size`.
 ///
 #[derive(BorshDeserialize, BorshSerialize)]
 #[repr(C)]
 pub struct MerkleRollHeader {
     /// Buffer of changelogs stored on-chain.
-    /// Must be a power of 2; see above table for valid combinations.
+    /// Must be a power of 2.
     pub max_buffer_size: u32,
 
     /// Depth of the Merkle tree to store.
     /// Tree capacity can be calculated as power(2, max_depth).
-    /// See above table for valid options.
+    /// Can be at most 30.
     pub max_depth: u32,
 
     /// Authority that validates the content of the trees.
@@ -100,6 +179,27 @@ pub struct MerkleRollHeader {
     /// Slot corresponding to when the Merkle tree was created.
     /// Provides a lower-bound on what slot to start (re-)building a tree from.
     pub creation_slot: u64,
+
+    /// Hash function used to compute the nodes of the tree.
+    /// Zeroed accounts default to `Keccak`.
+    pub hash_function: HashFunction,
+
+    /// Whether leaves and internal nodes are hashed with distinct prefixes.
+    /// Leaves of such trees must be built with `Hasher::hash_leaf`,
+    /// see `concurrent_merkle_tree::hasher::DomainSeparated`.
+    pub domain_separated: bool,
+
+    /// Version of the header layout, see [MERKLE_ROLL_HEADER_VERSION].
+    /// Zeroed accounts hold version 0 until they are initialized.
+    pub layout_version: u8,
+
+    /// Keeps the in-memory size of the header equal to its serialized size
+    _padding: [u8; 1],
+
+    /// Number of nodes of the deepest canopy level that remain to be uploaded with
+    /// `prefill_canopy`. Proofs are only filled in from the canopy once this is 0,
+    /// which is always the case for trees that were initialized empty.
+    pub pending_canopy_nodes: u32,
 }
 
 impl MerkleRollHeader {
@@ -109,14 +209,69 @@ impl MerkleRollHeader {
         max_buffer_size: u32,
         authority: &Pubkey,
         creation_slot: u64,
-    ) {
+        hash_function: HashFunction,
+        domain_separated: bool,
+    ) -> Result<()> {
         // Check header is empty
-        assert_eq!(self.max_buffer_size, 0);
-        assert_eq!(self.max_depth, 0);
+        if self.max_buffer_size != 0 || self.max_depth != 0 {
+            msg!("Merkle roll header is already initialized");
+            return err!(GummyrollError::TreeAlreadyInitialized);
+        }
         self.max_buffer_size = max_buffer_size;
         self.max_depth = max_depth;
         self.authority = *authority;
         self.creation_slot = creation_slot;
+        self.hash_function = hash_function;
+        self.domain_separated = domain_separated;
+        self.layout_version = MERKLE_ROLL_HEADER_VERSION;
+        Ok(())
+    }
+
+    /// Builds the header of a tree created with the legacy 48 byte header
+    pub fn from_legacy(legacy: &LegacyMerkleRollHeader) -> Self {
+        Self {
+            max_buffer_size: legacy.max_buffer_size,
+            max_depth: legacy.max_depth,
+            authority: legacy.authority,
+            creation_slot: legacy.creation_slot,
+            hash_function: HashFunction::Keccak,
+            domain_separated: false,
+            layout_version: MERKLE_ROLL_HEADER_VERSION,
+            _padding: [0; 1],
+            // Legacy trees never skipped empty canopy slots, their canopy is always usable
+            pending_canopy_nodes: 0,
+        }
+    }
+
+    /// Fails unless the header was written with the current layout
+    pub fn assert_current_version(&self) -> Result<()> {
+        if self.layout_version != MERKLE_ROLL_HEADER_VERSION {
+            msg!(
+                "Merkle roll header has layout version {}, expected {}. Legacy trees must be upgraded with migrate_header",
+                self.layout_version,
+                MERKLE_ROLL_HEADER_VERSION
+            );
+            return err!(GummyrollError::UnsupportedHeaderVersion);
+        }
+        Ok(())
+    }
+
+    /// Whether every canopy node holds the value of its node in the tree
+    pub fn is_canopy_complete(&self) -> bool {
+        self.pending_canopy_nodes == 0
+    }
+
+    /// Fails unless `expected_authority` is the authority of the merkle roll
+    pub fn assert_valid_authority(&self, expected_authority: &Pubkey) -> Result<()> {
+        if self.authority != *expected_authority {
+            msg!(
+                "Signer {} is not the merkle roll authority {}",
+                expected_authority,
+                self.authority
+            );
+            return err!(GummyrollError::InvalidAuthority);
+        }
+        Ok(())
     }
 }
 
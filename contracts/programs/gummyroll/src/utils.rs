@@ -6,7 +6,7 @@ use anchor_lang::{
     solana_program::{msg, program::invoke, program_error::ProgramError},
 };
 use bytemuck::{Pod, PodCastError};
-use concurrent_merkle_tree::merkle_roll::MerkleRoll;
+use concurrent_merkle_tree::{merkle_roll::MerkleRoll, state::Node};
 use std::any::type_name;
 use std::mem::size_of;
 
@@ -21,14 +21,43 @@ pub fn wrap_event<'info>(
     Ok(())
 }
 
+/// Verifies that `leaf` is at `index` in the tree with root `root` through a CPI into gummyroll.
+/// `proof` holds the proof nodes that are not stored in the canopy, ordered from the leaf up.
+///
+/// No account is written, so `merkle_roll` and the proof accounts can be passed as read-only.
+/// Fails if `gummyroll_program` is not gummyroll or `merkle_roll` is not owned by gummyroll,
+/// and with the matching `GummyrollError` if the proof is invalid.
+#[cfg(feature = "cpi")]
+pub fn verify_leaf_cpi<'info>(
+    gummyroll_program: &AccountInfo<'info>,
+    merkle_roll: &AccountInfo<'info>,
+    proof: &[AccountInfo<'info>],
+    root: Node,
+    leaf: Node,
+    index: u32,
+) -> Result<()> {
+    if gummyroll_program.key() != crate::id() {
+        return Err(ProgramError::IncorrectProgramId.into());
+    }
+    let cpi_ctx = CpiContext::new(
+        gummyroll_program.clone(),
+        crate::cpi::accounts::VerifyLeaf {
+            merkle_roll: merkle_roll.clone(),
+        },
+    )
+    .with_remaining_accounts(proof.to_vec());
+    crate::cpi::verify_leaf(cpi_ctx, root, leaf, index)
+}
+
 pub trait ZeroCopy: Pod {
     fn load_mut_bytes<'a>(data: &'a mut [u8]) -> Result<&'a mut Self> {
         let size = size_of::<Self>();
         let data_len = data.len();
+        if data_len < size {
+            return Err(error_msg::<Self>(data_len)(PodCastError::SizeMismatch).into());
+        }
 
-        Ok(bytemuck::try_from_bytes_mut(&mut data[..size])
-            .map_err(error_msg::<Self>(data_len))
-            .unwrap())
+        Ok(bytemuck::try_from_bytes_mut(&mut data[..size]).map_err(error_msg::<Self>(data_len))?)
     }
 }
 
@@ -0,0 +1,9 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum SugarShackError {
+    #[msg("Every creator share must have a matching verified flag")]
+    CreatorSharesVerifiedMismatch,
+    #[msg("Not enough remaining accounts for the creators of the asset")]
+    MissingCreatorAccounts,
+}
@@ -13,7 +13,9 @@ use bubblegum::program::Bubblegum;
 use gummyroll::program::Gummyroll;
 use gummyroll::state::CandyWrapper;
 use solana_safe_math::SafeMath;
+pub mod error;
 pub mod state;
+use crate::error::SugarShackError;
 use crate::state::{MarketplaceProperties, MARKETPLACE_PROPERTIES_SIZE};
 
 declare_id!("9T5Xv2cJRydUBqvdK7rLGuNGqhkA8sU8Yq1rGN7hExNK");
@@ -306,6 +308,7 @@ pub mod sugar_shack {
         index: u32,
         root: [u8; 32],
         creator_shares: Vec<u8>,
+        creator_verified: Vec<bool>,
         seller_fee_basis_points: u16,
     ) -> Result<()> {
         // The fees for the marketplace plus the seller_fee_basis points cannot exceed 100% of the price
@@ -336,16 +339,22 @@ pub mod sugar_shack {
         let mut total_remaining_price_allocation = price.safe_sub(amount_to_pay_marketplace)?;
 
         // Second, payout each "creator". Creators are an immutable set of secondary marketplace sale royalty recipients.
-        // Simultaneously, collect <address, share> pairs to prepare to compute creator_hash
+        // Simultaneously, collect <address, verified, share> tuples to prepare to compute creator_hash
         let total_creator_allocation = (price as u128)
             .safe_mul(seller_fee_basis_points as u128)?
             .safe_div(10000)? as u64;
         let mut amount_paid_out_to_creators = 0;
         let mut creator_data: Vec<Vec<u8>> = Vec::new();
+        if creator_shares.len() != creator_verified.len() {
+            return Err(SugarShackError::CreatorSharesVerifiedMismatch.into());
+        }
+        if ctx.remaining_accounts.len() < creator_shares.len() {
+            return Err(SugarShackError::MissingCreatorAccounts.into());
+        }
         let (creator_accounts, proof_accounts) =
             ctx.remaining_accounts.split_at(creator_shares.len());
         let creator_accounts_iter = &mut creator_accounts.iter();
-        for share in creator_shares.into_iter() {
+        for (share, verified) in creator_shares.into_iter().zip(creator_verified) {
             let current_creator_info = next_account_info(creator_accounts_iter)?;
             let amount_to_pay_creator = (total_creator_allocation as u128)
                 .safe_mul(share as u128)?
@@ -364,7 +373,14 @@ pub mod sugar_shack {
             )?;
             amount_paid_out_to_creators =
                 amount_paid_out_to_creators.safe_add(amount_to_pay_creator)?;
-            creator_data.push([current_creator_info.key().as_ref(), &[share]].concat());
+            creator_data.push(
+                [
+                    current_creator_info.key().as_ref(),
+                    &[verified as u8],
+                    &[share],
+                ]
+                .concat(),
+            );
         }
         total_remaining_price_allocation =
             total_remaining_price_allocation.safe_sub(amount_paid_out_to_creators)?;
@@ -383,7 +399,7 @@ pub mod sugar_shack {
             ],
         )?;
 
-        // Compute the creator hash using <address, share> pairs
+        // Compute the creator hash using <address, verified, share> tuples
         let creator_hash = hashv(
             creator_data
                 .iter()
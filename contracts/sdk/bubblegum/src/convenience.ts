@@ -240,6 +240,7 @@ export function computeCreatorHash(creators: Creator[]) {
     bufferOfCreatorData = Buffer.concat([
       bufferOfCreatorData,
       creator.address.toBuffer(),
+      Buffer.from([creator.verified ? 1 : 0]),
       Buffer.from([creator.share]),
     ]);
     bufferOfCreatorShares = Buffer.concat([
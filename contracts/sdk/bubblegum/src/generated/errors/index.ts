@@ -388,6 +388,438 @@ createErrorFromNameLookup.set(
   () => new CloseMintRequestErrorError()
 )
 
+/**
+ * OutstandingMintApprovals: 'Tree cannot be closed while mints are approved'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class OutstandingMintApprovalsError extends Error {
+  readonly code: number = 0x1780
+  readonly name: string = 'OutstandingMintApprovals'
+  constructor() {
+    super('Tree cannot be closed while mints are approved')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, OutstandingMintApprovalsError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1780, () => new OutstandingMintApprovalsError())
+createErrorFromNameLookup.set(
+  'OutstandingMintApprovals',
+  () => new OutstandingMintApprovalsError()
+)
+
+/**
+ * AssetNotInTree: 'Mint is not an asset of this tree'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class AssetNotInTreeError extends Error {
+  readonly code: number = 0x1781
+  readonly name: string = 'AssetNotInTree'
+  constructor() {
+    super('Mint is not an asset of this tree')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, AssetNotInTreeError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1781, () => new AssetNotInTreeError())
+createErrorFromNameLookup.set('AssetNotInTree', () => new AssetNotInTreeError())
+
+/**
+ * InvalidTokenAccount: 'Token account must hold exactly one token of the asset'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class InvalidTokenAccountError extends Error {
+  readonly code: number = 0x1782
+  readonly name: string = 'InvalidTokenAccount'
+  constructor() {
+    super('Token account must hold exactly one token of the asset')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, InvalidTokenAccountError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1782, () => new InvalidTokenAccountError())
+createErrorFromNameLookup.set(
+  'InvalidTokenAccount',
+  () => new InvalidTokenAccountError()
+)
+
+/**
+ * CollectionNotFound: 'Metadata does not belong to this collection'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CollectionNotFoundError extends Error {
+  readonly code: number = 0x1783
+  readonly name: string = 'CollectionNotFound'
+  constructor() {
+    super('Metadata does not belong to this collection')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CollectionNotFoundError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1783, () => new CollectionNotFoundError())
+createErrorFromNameLookup.set(
+  'CollectionNotFound',
+  () => new CollectionNotFoundError()
+)
+
+/**
+ * CollectionCannotBeVerifiedInThisInstruction: 'Collection can only be verified with mint_to_collection_v1 or verify_collection'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CollectionCannotBeVerifiedInThisInstructionError extends Error {
+  readonly code: number = 0x1784
+  readonly name: string = 'CollectionCannotBeVerifiedInThisInstruction'
+  constructor() {
+    super('Collection can only be verified with mint_to_collection_v1 or verify_collection')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(
+        this,
+        CollectionCannotBeVerifiedInThisInstructionError
+      )
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x1784,
+  () => new CollectionCannotBeVerifiedInThisInstructionError()
+)
+createErrorFromNameLookup.set(
+  'CollectionCannotBeVerifiedInThisInstruction',
+  () => new CollectionCannotBeVerifiedInThisInstructionError()
+)
+
+/**
+ * AlreadyVerified: 'Collection is already verified'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class AlreadyVerifiedError extends Error {
+  readonly code: number = 0x1785
+  readonly name: string = 'AlreadyVerified'
+  constructor() {
+    super('Collection is already verified')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, AlreadyVerifiedError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1785, () => new AlreadyVerifiedError())
+createErrorFromNameLookup.set(
+  'AlreadyVerified',
+  () => new AlreadyVerifiedError()
+)
+
+/**
+ * AlreadyUnverified: 'Collection is already unverified'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class AlreadyUnverifiedError extends Error {
+  readonly code: number = 0x1786
+  readonly name: string = 'AlreadyUnverified'
+  constructor() {
+    super('Collection is already unverified')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, AlreadyUnverifiedError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1786, () => new AlreadyUnverifiedError())
+createErrorFromNameLookup.set(
+  'AlreadyUnverified',
+  () => new AlreadyUnverifiedError()
+)
+
+/**
+ * InvalidCollectionAuthority: 'Signer is not the update authority or a delegated authority of the collection'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class InvalidCollectionAuthorityError extends Error {
+  readonly code: number = 0x1787
+  readonly name: string = 'InvalidCollectionAuthority'
+  constructor() {
+    super('Signer is not the update authority or a delegated authority of the collection')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, InvalidCollectionAuthorityError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x1787,
+  () => new InvalidCollectionAuthorityError()
+)
+createErrorFromNameLookup.set(
+  'InvalidCollectionAuthority',
+  () => new InvalidCollectionAuthorityError()
+)
+
+/**
+ * CollectionMasterEditionAccountInvalid: 'Collection master edition account is invalid'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CollectionMasterEditionAccountInvalidError extends Error {
+  readonly code: number = 0x1788
+  readonly name: string = 'CollectionMasterEditionAccountInvalid'
+  constructor() {
+    super('Collection master edition account is invalid')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CollectionMasterEditionAccountInvalidError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x1788,
+  () => new CollectionMasterEditionAccountInvalidError()
+)
+createErrorFromNameLookup.set(
+  'CollectionMasterEditionAccountInvalid',
+  () => new CollectionMasterEditionAccountInvalidError()
+)
+
+/**
+ * CreatorDidNotSign: 'Verified creators must sign the mint'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CreatorDidNotSignError extends Error {
+  readonly code: number = 0x1789
+  readonly name: string = 'CreatorDidNotSign'
+  constructor() {
+    super('Verified creators must sign the mint')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CreatorDidNotSignError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1789, () => new CreatorDidNotSignError())
+createErrorFromNameLookup.set(
+  'CreatorDidNotSign',
+  () => new CreatorDidNotSignError()
+)
+
+/**
+ * CreatorNotFound: 'Signer is not a creator of the asset'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CreatorNotFoundError extends Error {
+  readonly code: number = 0x178a
+  readonly name: string = 'CreatorNotFound'
+  constructor() {
+    super('Signer is not a creator of the asset')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CreatorNotFoundError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x178a, () => new CreatorNotFoundError())
+createErrorFromNameLookup.set(
+  'CreatorNotFound',
+  () => new CreatorNotFoundError()
+)
+
+/**
+ * CreatorAlreadyVerified: 'Creator is already verified'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CreatorAlreadyVerifiedError extends Error {
+  readonly code: number = 0x178b
+  readonly name: string = 'CreatorAlreadyVerified'
+  constructor() {
+    super('Creator is already verified')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CreatorAlreadyVerifiedError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x178b, () => new CreatorAlreadyVerifiedError())
+createErrorFromNameLookup.set(
+  'CreatorAlreadyVerified',
+  () => new CreatorAlreadyVerifiedError()
+)
+
+/**
+ * CreatorAlreadyUnverified: 'Creator is already unverified'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CreatorAlreadyUnverifiedError extends Error {
+  readonly code: number = 0x178c
+  readonly name: string = 'CreatorAlreadyUnverified'
+  constructor() {
+    super('Creator is already unverified')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CreatorAlreadyUnverifiedError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x178c, () => new CreatorAlreadyUnverifiedError())
+createErrorFromNameLookup.set(
+  'CreatorAlreadyUnverified',
+  () => new CreatorAlreadyUnverifiedError()
+)
+
+/**
+ * MetadataImmutable: 'Metadata is not mutable'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class MetadataImmutableError extends Error {
+  readonly code: number = 0x178d
+  readonly name: string = 'MetadataImmutable'
+  constructor() {
+    super('Metadata is not mutable')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, MetadataImmutableError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x178d, () => new MetadataImmutableError())
+createErrorFromNameLookup.set(
+  'MetadataImmutable',
+  () => new MetadataImmutableError()
+)
+
+/**
+ * PrimarySaleCanOnlyBeFlippedToTrue: 'Primary sale can only be flipped to true'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class PrimarySaleCanOnlyBeFlippedToTrueError extends Error {
+  readonly code: number = 0x178e
+  readonly name: string = 'PrimarySaleCanOnlyBeFlippedToTrue'
+  constructor() {
+    super('Primary sale can only be flipped to true')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, PrimarySaleCanOnlyBeFlippedToTrueError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x178e,
+  () => new PrimarySaleCanOnlyBeFlippedToTrueError()
+)
+createErrorFromNameLookup.set(
+  'PrimarySaleCanOnlyBeFlippedToTrue',
+  () => new PrimarySaleCanOnlyBeFlippedToTrueError()
+)
+
+/**
+ * CannotUnverifyAnotherCreator: 'Creators can only be unverified by themselves'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class CannotUnverifyAnotherCreatorError extends Error {
+  readonly code: number = 0x178f
+  readonly name: string = 'CannotUnverifyAnotherCreator'
+  constructor() {
+    super('Creators can only be unverified by themselves')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, CannotUnverifyAnotherCreatorError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x178f,
+  () => new CannotUnverifyAnotherCreatorError()
+)
+createErrorFromNameLookup.set(
+  'CannotUnverifyAnotherCreator',
+  () => new CannotUnverifyAnotherCreatorError()
+)
+
+/**
+ * UnsupportedTokenProgramVersion: 'Token metadata does not support Token-2022 mints yet'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class UnsupportedTokenProgramVersionError extends Error {
+  readonly code: number = 0x1790
+  readonly name: string = 'UnsupportedTokenProgramVersion'
+  constructor() {
+    super('Token metadata does not support Token-2022 mints yet')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, UnsupportedTokenProgramVersionError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(
+  0x1790,
+  () => new UnsupportedTokenProgramVersionError()
+)
+createErrorFromNameLookup.set(
+  'UnsupportedTokenProgramVersion',
+  () => new UnsupportedTokenProgramVersionError()
+)
+
+/**
+ * MintAuthorityDidNotSign: 'Mint authority must sign if it is not the tree authority'
+ *
+ * @category Errors
+ * @category generated
+ */
+export class MintAuthorityDidNotSignError extends Error {
+  readonly code: number = 0x1791
+  readonly name: string = 'MintAuthorityDidNotSign'
+  constructor() {
+    super('Mint authority must sign if it is not the tree authority')
+    if (typeof Error.captureStackTrace === 'function') {
+      Error.captureStackTrace(this, MintAuthorityDidNotSignError)
+    }
+  }
+}
+
+createErrorFromCodeLookup.set(0x1791, () => new MintAuthorityDidNotSignError())
+createErrorFromNameLookup.set(
+  'MintAuthorityDidNotSign',
+  () => new MintAuthorityDidNotSignError()
+)
+
 /**
  * Attempts to resolve a custom program error from the provided error code.
  * @category Errors
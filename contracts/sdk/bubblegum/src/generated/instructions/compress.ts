@@ -14,19 +14,31 @@ import * as web3 from '@solana/web3.js'
  * @category Compress
  * @category generated
  */
-export const compressStruct = new beet.BeetArgsStruct<{
-  instructionDiscriminator: number[] /* size: 8 */
-}>(
-  [['instructionDiscriminator', beet.uniformFixedSizeArray(beet.u8, 8)]],
+export type CompressInstructionArgs = {
+  nonce: beet.bignum
+}
+/**
+ * @category Instructions
+ * @category Compress
+ * @category generated
+ */
+export const compressStruct = new beet.BeetArgsStruct<
+  CompressInstructionArgs & {
+    instructionDiscriminator: number[] /* size: 8 */
+  }
+>(
+  [
+    ['instructionDiscriminator', beet.uniformFixedSizeArray(beet.u8, 8)],
+    ['nonce', beet.u64],
+  ],
   'CompressInstructionArgs'
 )
 /**
  * Accounts required by the _compress_ instruction
  *
- * @property [] authority
- * @property [] merkleSlab
- * @property [**signer**] owner
- * @property [] delegate
+ * @property [_writable_] authority
+ * @property [_writable_] merkleSlab
+ * @property [_writable_, **signer**] owner
  * @property [_writable_] tokenAccount
  * @property [_writable_] mint
  * @property [_writable_] metadata
@@ -43,7 +55,6 @@ export type CompressInstructionAccounts = {
   authority: web3.PublicKey
   merkleSlab: web3.PublicKey
   owner: web3.PublicKey
-  delegate: web3.PublicKey
   tokenAccount: web3.PublicKey
   mint: web3.PublicKey
   metadata: web3.PublicKey
@@ -64,38 +75,37 @@ export const compressInstructionDiscriminator = [
  * Creates a _Compress_ instruction.
  *
  * @param accounts that will be accessed while the instruction is processed
+ * @param args to provide as instruction data to the program
+ *
  * @category Instructions
  * @category Compress
  * @category generated
  */
 export function createCompressInstruction(
   accounts: CompressInstructionAccounts,
+  args: CompressInstructionArgs,
   programId = new web3.PublicKey('BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY')
 ) {
   const [data] = compressStruct.serialize({
     instructionDiscriminator: compressInstructionDiscriminator,
+    ...args,
   })
   const keys: web3.AccountMeta[] = [
     {
       pubkey: accounts.authority,
-      isWritable: false,
+      isWritable: true,
       isSigner: false,
     },
     {
       pubkey: accounts.merkleSlab,
-      isWritable: false,
+      isWritable: true,
       isSigner: false,
     },
     {
       pubkey: accounts.owner,
-      isWritable: false,
+      isWritable: true,
       isSigner: true,
     },
-    {
-      pubkey: accounts.delegate,
-      isWritable: false,
-      isSigner: false,
-    },
     {
       pubkey: accounts.tokenAccount,
       isWritable: true,
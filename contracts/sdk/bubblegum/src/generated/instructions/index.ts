@@ -2,13 +2,20 @@ export * from './approveMintAuthorityRequest'
 export * from './burn'
 export * from './cancelRedeem'
 export * from './closeMintRequest'
+export * from './closeTree'
 export * from './compress'
 export * from './createDefaultMintRequest'
 export * from './createTree'
 export * from './decompressV1'
 export * from './delegate'
+export * from './mintToCollectionV1'
 export * from './mintV1'
 export * from './redeem'
 export * from './requestMintAuthority'
 export * from './setTreeDelegate'
 export * from './transfer'
+export * from './unverifyCollection'
+export * from './unverifyCreator'
+export * from './updateMetadata'
+export * from './verifyCollection'
+export * from './verifyCreator'
@@ -20,6 +20,12 @@ export enum InstructionName {
   DecompressV1,
   Compress,
   Burn,
+  MintToCollectionV1,
+  VerifyCollection,
+  UnverifyCollection,
+  VerifyCreator,
+  UnverifyCreator,
+  UpdateMetadata,
 }
 
 /**
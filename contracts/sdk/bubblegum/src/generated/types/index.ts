@@ -5,6 +5,7 @@ export * from './LeafSchema'
 export * from './MetadataArgs'
 export * from './TokenProgramVersion'
 export * from './TokenStandard'
+export * from './UpdateArgs'
 export * from './UseMethod'
 export * from './Uses'
 export * from './Version'
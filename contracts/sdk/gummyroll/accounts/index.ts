@@ -39,6 +39,26 @@ export class OnChainMerkleRoll {
     }
     return pathNodeList;
   }
+
+  /**
+   * Returns the sequence number and new value of every change to the leaf at `leafIndex`
+   * that is still in the changelog buffer, from oldest to most recent.
+   * A proof of the leaf older than one of these changes fails with `LeafContentsModified`.
+   */
+  getChangesForLeaf(leafIndex: number): LeafChange[] {
+    const mask = this.header.maxBufferSize - 1;
+    let changes = [];
+    for (let j = this.roll.bufferSize - 1; j >= 0; j--) {
+      let changeLog = this.roll.changeLogs[(this.roll.activeIndex - j) & mask];
+      if (changeLog.index === leafIndex) {
+        changes.push({
+          seq: this.roll.sequenceNumber.subn(j),
+          leaf: changeLog.pathNodes[0],
+        });
+      }
+    }
+    return changes;
+  }
 }
 
 type MerkleRollHeader = {
@@ -46,8 +66,23 @@ type MerkleRollHeader = {
   maxBufferSize: number; // u32
   authority: PublicKey;
   creationSlot: BN;
+  hashFunction: HashFunction;
+  domainSeparated: boolean;
+  layoutVersion: number; // u8
+  pendingCanopyNodes: number; // u32
 };
 
+/**
+ * Version of the merkle roll header layout written by gummyroll
+ */
+export const MERKLE_ROLL_HEADER_VERSION = 1;
+
+export enum HashFunction {
+  Keccak = 0,
+  Sha256 = 1,
+  Poseidon = 2,
+}
+
 type MerkleRoll = {
   sequenceNumber: BN; // u64
   activeIndex: number; // u64
@@ -61,6 +96,11 @@ export type PathNode = {
   index: number;
 };
 
+export type LeafChange = {
+  seq: BN;
+  leaf: PublicKey;
+};
+
 type ChangeLog = {
   root: PublicKey;
   pathNodes: PublicKey[];
@@ -83,7 +123,19 @@ export function decodeMerkleRoll(buffer: Buffer): OnChainMerkleRoll {
     maxDepth: reader.readU32(),
     authority: readPublicKey(reader),
     creationSlot: reader.readU64(),
+    hashFunction: reader.readU8(),
+    domainSeparated: reader.readU8() !== 0,
+    layoutVersion: reader.readU8(),
+    pendingCanopyNodes: 0,
   };
+  if (header.layoutVersion !== MERKLE_ROLL_HEADER_VERSION) {
+    throw new Error(
+      `Unsupported merkle roll header version ${header.layoutVersion}, legacy trees must be upgraded with migrateHeader`
+    );
+  }
+  // Skip header padding
+  reader.offset += 1;
+  header.pendingCanopyNodes = reader.readU32();
 
   // Decode MerkleRoll
   let sequenceNumber = reader.readU64();
@@ -144,10 +196,11 @@ export function getMerkleRollAccountSize(
   maxBufferSize: number,
   canopyDepth?: number
 ): number {
-  let headerSize = 8 + 32;
+  // Must match `merkle_roll_account_size` in the gummyroll program
+  let headerSize = 8 + 32 + 8 + 8;
   let changeLogSize = (maxDepth * 32 + 32 + 4 + 4) * maxBufferSize;
   let rightMostPathSize = maxDepth * 32 + 32 + 4 + 4;
-  let merkleRollSize = 8 + 8 + 16 + changeLogSize + rightMostPathSize;
+  let merkleRollSize = 8 + 8 + 8 + changeLogSize + rightMostPathSize;
   let canopySize = 0;
   if (canopyDepth) {
     canopySize = ((1 << canopyDepth + 1) - 2) * 32
@@ -376,6 +376,116 @@ export type Gummyroll = {
       "code": 6004,
       "name": "CanopyLengthMismatch",
       "msg": "Expected a different byte length for the merkle roll canopy"
+    },
+    {
+      "code": 6005,
+      "name": "BatchSizeExceedsBufferSize",
+      "msg": "Batch contains more leaves than the merkle roll buffer can hold"
+    },
+    {
+      "code": 6006,
+      "name": "TreeNotEmpty",
+      "msg": "Tree still holds data and cannot be closed"
+    },
+    {
+      "code": 6007,
+      "name": "UnsupportedHashFunction",
+      "msg": "Hash function of the merkle roll is not supported by this program"
+    },
+    {
+      "code": 6008,
+      "name": "LeafIndexOutOfBounds",
+      "msg": "Received an index larger than the rightmost index"
+    },
+    {
+      "code": 6009,
+      "name": "InvalidProof",
+      "msg": "Invalid root recomputed from proof"
+    },
+    {
+      "code": 6010,
+      "name": "CannotAppendEmptyNode",
+      "msg": "Cannot append an empty node"
+    },
+    {
+      "code": 6011,
+      "name": "TreeFull",
+      "msg": "Tree is full, cannot append"
+    },
+    {
+      "code": 6012,
+      "name": "TreeAlreadyInitialized",
+      "msg": "Tree already initialized"
+    },
+    {
+      "code": 6013,
+      "name": "InvalidNodeByteLength",
+      "msg": "Invalid number of bytes passed for node (expected 32 bytes)"
+    },
+    {
+      "code": 6014,
+      "name": "RootNotFound",
+      "msg": "Root not found in changelog buffer"
+    },
+    {
+      "code": 6015,
+      "name": "LeafContentsModified",
+      "msg": "Valid proof was passed to a leaf, but it's value has changed since the proof was issued"
+    },
+    {
+      "code": 6016,
+      "name": "UnsupportedMerkleRollConstants",
+      "msg": "Unsupported max depth or max buffer size"
+    },
+    {
+      "code": 6017,
+      "name": "InvalidMerkleRollByteLength",
+      "msg": "Invalid number of bytes passed for merkle roll"
+    },
+    {
+      "code": 6018,
+      "name": "InvalidCompressedProof",
+      "msg": "Invalid compressed proof"
+    },
+    {
+      "code": 6019,
+      "name": "InvalidMultiProof",
+      "msg": "Invalid multiproof"
+    },
+    {
+      "code": 6020,
+      "name": "InvalidAuthority",
+      "msg": "Signer is not the authority of the merkle roll"
+    },
+    {
+      "code": 6021,
+      "name": "InvalidAccountSize",
+      "msg": "Merkle roll account is too small"
+    },
+    {
+      "code": 6022,
+      "name": "CanopyNotComplete",
+      "msg": "Canopy must be prefilled before it can be used to shorten proofs"
+    },
+    {
+      "code": 6023,
+      "name": "InvalidCanopyChunk",
+      "msg": "Invalid chunk of canopy nodes"
+    },
+    {
+      "code": 6024,
+      "name": "UnsupportedHeaderVersion",
+      "msg": "Merkle roll header uses an unsupported layout version"
+    },
+    {
+      "code": 6025,
+      "name": "InvalidLegacyHeader",
+      "msg": "Merkle roll account does not hold a tree with a legacy header"
+    },
+    {
+      "code": 6026,
+      "name": "HashFunctionMismatch",
+      "msg": "Hasher does not match the hash function of the merkle roll"
     }
   ]
 };
@@ -757,6 +867,116 @@ export const IDL: Gummyroll = {
       "code": 6004,
       "name": "CanopyLengthMismatch",
       "msg": "Expected a different byte length for the merkle roll canopy"
+    },
+    {
+      "code": 6005,
+      "name": "BatchSizeExceedsBufferSize",
+      "msg": "Batch contains more leaves than the merkle roll buffer can hold"
+    },
+    {
+      "code": 6006,
+      "name": "TreeNotEmpty",
+      "msg": "Tree still holds data and cannot be closed"
+    },
+    {
+      "code": 6007,
+      "name": "UnsupportedHashFunction",
+      "msg": "Hash function of the merkle roll is not supported by this program"
+    },
+    {
+      "code": 6008,
+      "name": "LeafIndexOutOfBounds",
+      "msg": "Received an index larger than the rightmost index"
+    },
+    {
+      "code": 6009,
+      "name": "InvalidProof",
+      "msg": "Invalid root recomputed from proof"
+    },
+    {
+      "code": 6010,
+      "name": "CannotAppendEmptyNode",
+      "msg": "Cannot append an empty node"
+    },
+    {
+      "code": 6011,
+      "name": "TreeFull",
+      "msg": "Tree is full, cannot append"
+    },
+    {
+      "code": 6012,
+      "name": "TreeAlreadyInitialized",
+      "msg": "Tree already initialized"
+    },
+    {
+      "code": 6013,
+      "name": "InvalidNodeByteLength",
+      "msg": "Invalid number of bytes passed for node (expected 32 bytes)"
+    },
+    {
+      "code": 6014,
+      "name": "RootNotFound",
+      "msg": "Root not found in changelog buffer"
+    },
+    {
+      "code": 6015,
+      "name": "LeafContentsModified",
+      "msg": "Valid proof was passed to a leaf, but it's value has changed since the proof was issued"
+    },
+    {
+      "code": 6016,
+      "name": "UnsupportedMerkleRollConstants",
+      "msg": "Unsupported max depth or max buffer size"
+    },
+    {
+      "code": 6017,
+      "name": "InvalidMerkleRollByteLength",
+      "msg": "Invalid number of bytes passed for merkle roll"
+    },
+    {
+      "code": 6018,
+      "name": "InvalidCompressedProof",
+      "msg": "Invalid compressed proof"
+    },
+    {
+      "code": 6019,
+      "name": "InvalidMultiProof",
+      "msg": "Invalid multiproof"
+    },
+    {
+      "code": 6020,
+      "name": "InvalidAuthority",
+      "msg": "Signer is not the authority of the merkle roll"
+    },
+    {
+      "code": 6021,
+      "name": "InvalidAccountSize",
+      "msg": "Merkle roll account is too small"
+    },
+    {
+      "code": 6022,
+      "name": "CanopyNotComplete",
+      "msg": "Canopy must be prefilled before it can be used to shorten proofs"
+    },
+    {
+      "code": 6023,
+      "name": "InvalidCanopyChunk",
+      "msg": "Invalid chunk of canopy nodes"
+    },
+    {
+      "code": 6024,
+      "name": "UnsupportedHeaderVersion",
+      "msg": "Merkle roll header uses an unsupported layout version"
+    },
+    {
+      "code": 6025,
+      "name": "InvalidLegacyHeader",
+      "msg": "Merkle roll account does not hold a tree with a legacy header"
+    },
+    {
+      "code": 6026,
+      "name": "HashFunctionMismatch",
+      "msg": "Hasher does not match the hash function of the merkle roll"
     }
   ]
 };
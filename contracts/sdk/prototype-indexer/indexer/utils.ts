@@ -12,6 +12,7 @@ import { Layout } from "buffer-layout";
 import { bs58 } from "@project-serum/anchor/dist/cjs/utils/bytes";
 import {
   Creator,
+  creatorBeet,
   MetadataArgs,
   metadataArgsBeet,
   TokenProgramVersion,
@@ -20,8 +21,6 @@ import {
 import { NewLeafEvent, LeafSchemaEvent } from "./ingester";
 import { keccak_256 } from "js-sha3";
 import { getLeafAssetId } from "../../bubblegum/src/convenience";
-import * as beetSolana from '@metaplex-foundation/beet-solana'
-import * as beet from '@metaplex-foundation/beet'
 
 import { PROGRAM_ID as GUMMYROLL_PROGRAM_ID } from "../../gummyroll";
 import { PROGRAM_ID as BUBBLEGUM_PROGRAM_ID } from "../../bubblegum/src/generated";
@@ -236,27 +235,11 @@ export function hashMetadata(message: MetadataArgs) {
   return digest(Buffer.concat([initBuffer, bps]));
 }
 
-type UnverifiedCreator = {
-  address: PublicKey,
-  share: number
-};
-
-export const unverifiedCreatorBeet = new beet.BeetArgsStruct<UnverifiedCreator>(
-  [
-    ['address', beetSolana.publicKey],
-    ['share', beet.u8],
-  ],
-  'UnverifiedCreator'
-)
-
 export function hashCreators(creators: Creator[]) {
   const bytes = [];
   for (const creator of creators) {
-    const unverifiedCreator = {
-      address: creator.address,
-      share: creator.share
-    }
-    const [buffer, _byteSize] = unverifiedCreatorBeet.serialize(unverifiedCreator);
+    // <address, verified, share>, same layout as the Creator struct
+    const [buffer, _byteSize] = creatorBeet.serialize(creator);
     bytes.push(buffer);
   }
   return digest(Buffer.concat(bytes));
@@ -20,6 +20,7 @@ export type PurchaseInstructionArgs = {
   index: number
   root: number[] /* size: 32 */
   creatorShares: Uint8Array
+  creatorVerified: boolean[]
   sellerFeeBasisPoints: number
 }
 /**
@@ -40,6 +41,7 @@ export const purchaseStruct = new beet.FixableBeetArgsStruct<
     ['index', beet.u32],
     ['root', beet.uniformFixedSizeArray(beet.u8, 32)],
     ['creatorShares', beet.bytes],
+    ['creatorVerified', beet.array(beet.bool)],
     ['sellerFeeBasisPoints', beet.u16],
   ],
   'PurchaseInstructionArgs'
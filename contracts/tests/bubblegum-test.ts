@@ -9,6 +9,10 @@ import {
   Metadata,
   Data,
   TokenStandard,
+  createCreateMetadataAccountV2Instruction,
+  createCreateMasterEditionV3Instruction,
+  createApproveCollectionAuthorityInstruction,
+  createRevokeCollectionAuthorityInstruction,
 } from "@metaplex-foundation/mpl-token-metadata";
 import {
   PublicKey,
@@ -33,6 +37,7 @@ import {
   createApproveMintAuthorityRequestInstruction,
   createCloseMintRequestInstruction,
   MintRequest,
+  metadataArgsBeet,
   TreeConfig,
   createSetTreeDelegateInstruction,
   createCreateDefaultMintRequestInstruction,
@@ -49,6 +54,7 @@ import {
   Token,
   TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
+
 import {
   TokenProgramVersion,
   Version,
@@ -207,6 +213,163 @@ describe("bubblegum", function () {
     )[0];
   };
 
+  const getCollectionAuthorityRecord = async (
+    mint: PublicKey,
+    collectionAuthority: PublicKey
+  ): Promise<PublicKey> => {
+    return (
+      await PublicKey.findProgramAddress(
+        [
+          Buffer.from("metadata"),
+          TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+          mint.toBuffer(),
+          Buffer.from("collection_authority"),
+          collectionAuthority.toBuffer(),
+        ],
+        TOKEN_METADATA_PROGRAM_ID
+      )
+    )[0];
+  };
+
+  /// Creates a collection NFT whose update authority is `updateAuthority`
+  const createCollection = async (
+    updateAuthority: Keypair
+  ): Promise<PublicKey> => {
+    const collectionMint = await Token.createMint(
+      connection,
+      updateAuthority,
+      updateAuthority.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const tokenAccount = await collectionMint.getOrCreateAssociatedAccountInfo(
+      updateAuthority.publicKey
+    );
+    await collectionMint.mintTo(tokenAccount.address, updateAuthority, [], 1);
+
+    const mint = collectionMint.publicKey;
+    const metadata = await getMetadata(mint);
+    const createMetadataIx = createCreateMetadataAccountV2Instruction(
+      {
+        metadata,
+        mint,
+        mintAuthority: updateAuthority.publicKey,
+        payer: updateAuthority.publicKey,
+        updateAuthority: updateAuthority.publicKey,
+      },
+      {
+        createMetadataAccountArgsV2: {
+          data: {
+            name: "collection",
+            symbol: "col",
+            uri: "www.solana.com/collection",
+            sellerFeeBasisPoints: 0,
+            creators: null,
+            collection: null,
+            uses: null,
+          },
+          isMutable: true,
+        },
+      }
+    );
+    const createMasterEditionIx = createCreateMasterEditionV3Instruction(
+      {
+        edition: await getMasterEdition(mint),
+        mint,
+        updateAuthority: updateAuthority.publicKey,
+        mintAuthority: updateAuthority.publicKey,
+        payer: updateAuthority.publicKey,
+        metadata,
+      },
+      { createMasterEditionArgs: { maxSupply: 0 } }
+    );
+    await execute(
+      Bubblegum.provider,
+      [createMetadataIx, createMasterEditionIx],
+      [updateAuthority]
+    );
+    return mint;
+  };
+
+  /// Pass the bubblegum program id as `collectionAuthorityRecord` when the
+  /// `collectionAuthority` is the update authority of the collection
+  const getMintToCollectionIx = async (
+    metadata: MetadataArgs,
+    collectionMint: PublicKey,
+    collectionAuthority: PublicKey,
+    collectionAuthorityRecord: PublicKey
+  ) => {
+    return await Bubblegum.methods
+      .mintToCollectionV1({
+        ...metadata,
+        // Anchor encodes enums as objects
+        tokenProgramVersion: { original: {} },
+      })
+      .accounts({
+        mintAuthority: treeAuthority,
+        authority: treeAuthority,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        mintAuthorityRequest: mintRequest,
+        merkleSlab: merkleRollKeypair.publicKey,
+        collectionAuthority,
+        collectionAuthorityRecordPda: collectionAuthorityRecord,
+        collectionMint,
+        collectionMetadata: await getMetadata(collectionMint),
+        editionAccount: await getMasterEdition(collectionMint),
+      })
+      .instruction();
+  };
+
+  /// `metadata` is the current metadata of the leaf at index 0
+  const getCollectionVerificationIx = async (
+    verify: boolean,
+    metadata: MetadataArgs,
+    nonce: BN,
+    collectionMint: PublicKey,
+    collectionAuthority: PublicKey,
+    collectionAuthorityRecord: PublicKey
+  ) => {
+    const onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const root = bufferToArray(onChainRoot);
+    const creatorHash = computeCreatorHash(metadata.creators);
+    const message = {
+      ...metadata,
+      // Anchor encodes enums as objects
+      tokenProgramVersion: { original: {} },
+    };
+    const builder = verify
+      ? Bubblegum.methods.verifyCollection(root, creatorHash, nonce, 0, message)
+      : Bubblegum.methods.unverifyCollection(
+          root,
+          creatorHash,
+          nonce,
+          0,
+          message
+        );
+    return await builder
+      .accounts({
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        collectionAuthority,
+        collectionAuthorityRecordPda: collectionAuthorityRecord,
+        collectionMint,
+        collectionMetadata: await getMetadata(collectionMint),
+        editionAccount: await getMasterEdition(collectionMint),
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      })
+      .instruction();
+  };
+
   const assertMetadataMatch = (
     onChainMetadata: Metadata,
     mintMetadataArgs: MetadataArgs,
@@ -295,6 +458,19 @@ describe("bubblegum", function () {
     assert(onChainMetadata.uses === mintMetadataArgs.uses, "uses mismatch");
   };
 
+  async function assertFailsWith(tx: Promise<any>, errorName: string) {
+    try {
+      await tx;
+    } catch (e) {
+      assert(
+        e.logs?.some((log: string) => log.includes(`Error Code: ${errorName}`)),
+        `Expected the transaction to fail with ${errorName}`
+      );
+      return;
+    }
+    assert(false, `Transaction should have failed with ${errorName}`);
+  }
+
   beforeEach(async function () {
     payer = Keypair.generate();
     destination = Keypair.generate();
@@ -694,6 +870,687 @@ describe("bubblegum", function () {
     )[0];
     assertMetadataMatch(metadataForDecompressedNFT, metadata, mintAuthority);
   });
+  it("Rejects decompressing Token-2022 assets", async function () {
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Token2022,
+      collection: null,
+      uses: null,
+      creators: [],
+    };
+
+    console.log(" - Minting to tree");
+
+    const mintIx = createMintV1Instruction(
+      {
+        mintAuthority: treeAuthority,
+        mintAuthorityRequest: mintRequest,
+        authority: treeAuthority,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      { message: metadata }
+    );
+    await execute(Bubblegum.provider, [mintIx], [], true);
+
+    const dataHash = computeDataHash(metadata.sellerFeeBasisPoints, mintIx);
+    const creatorHash = computeCreatorHash(metadata.creators);
+
+    console.log(" - Attempting to decompress a Token-2022 leaf");
+
+    const onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const voucher = await getVoucherPDA(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey,
+      0
+    );
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    const leafNonce = nonceCount.sub(new BN(1));
+
+    const redeemIx = createRedeemInstruction(
+      {
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+        voucher: voucher,
+      },
+      {
+        root: bufferToArray(onChainRoot),
+        dataHash,
+        creatorHash,
+        nonce: leafNonce,
+        index: 0,
+      }
+    );
+    await execute(Bubblegum.provider, [redeemIx], [payer], true);
+
+    const [asset] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("asset"),
+        merkleRollKeypair.publicKey.toBuffer(),
+        leafNonce.toBuffer("le", 8),
+      ],
+      Bubblegum.programId
+    );
+    const [mintAuthority] = await PublicKey.findProgramAddress(
+      [asset.toBuffer()],
+      Bubblegum.programId
+    );
+    const [tokenAccount] = await PublicKey.findProgramAddress(
+      [
+        payer.publicKey.toBuffer(),
+        TOKEN_PROGRAM_ID.toBuffer(),
+        asset.toBuffer(),
+      ],
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+
+    const decompressIx = createDecompressV1Instruction(
+      {
+        voucher: voucher,
+        owner: payer.publicKey,
+        tokenAccount,
+        mint: asset,
+        mintAuthority: mintAuthority,
+        metadata: await getMetadata(asset),
+        masterEdition: await getMasterEdition(asset),
+        sysvarRent: SYSVAR_RENT_PUBKEY,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      },
+      {
+        metadata,
+      }
+    );
+    // Token metadata only creates metadata for SPL Token mints
+    await assertFailsWith(
+      execute(Bubblegum.provider, [decompressIx], [payer]),
+      "UnsupportedTokenProgramVersion"
+    );
+    assert(
+      (await connection.getAccountInfo(asset, "confirmed")) === null,
+      "Mint should not be created"
+    );
+  });
+  it("Creators can verify themselves", async function () {
+    const creator = Keypair.generate();
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: null,
+      uses: null,
+      creators: [{ address: creator.publicKey, share: 100, verified: false }],
+    };
+
+    console.log(" - Minting to tree");
+
+    const mintIx = createMintV1Instruction(
+      {
+        mintAuthority: treeAuthority,
+        mintAuthorityRequest: mintRequest,
+        authority: treeAuthority,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      { message: metadata }
+    );
+    await execute(Bubblegum.provider, [mintIx], [], true);
+
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    const leafNonce = nonceCount.sub(new BN(1));
+
+    console.log(" - Verifying creator");
+
+    let onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const verifyCreatorIx = await Bubblegum.methods
+      .verifyCreator(bufferToArray(onChainRoot), leafNonce, 0, {
+        ...metadata,
+        // Anchor encodes enums as objects
+        tokenProgramVersion: { original: {} },
+      })
+      .accounts({
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        creator: creator.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      })
+      .instruction();
+    await execute(Bubblegum.provider, [verifyCreatorIx], [creator], true);
+
+    console.log(" - Transferring with the verified creator hash");
+
+    const verifiedMetadata: MetadataArgs = {
+      ...metadata,
+      creators: [{ address: creator.publicKey, share: 100, verified: true }],
+    };
+    const [verifiedMetadataArgs] = metadataArgsBeet.serialize(verifiedMetadata);
+    const dataHash = computeDataHash(
+      verifiedMetadata.sellerFeeBasisPoints,
+      undefined,
+      bufferToArray(Buffer.from(keccak_256.digest(verifiedMetadataArgs)))
+    );
+    const creatorHash = computeCreatorHash(verifiedMetadata.creators);
+
+    onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const transferIx = createTransferInstruction(
+      {
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        newOwner: destination.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      {
+        root: bufferToArray(onChainRoot),
+        dataHash,
+        creatorHash,
+        nonce: leafNonce,
+        index: 0,
+      }
+    );
+    await execute(Bubblegum.provider, [transferIx], [payer], true);
+  });
+  it("Tree creator can update mutable metadata", async function () {
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: true,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: null,
+      uses: null,
+      creators: [],
+    };
+
+    console.log(" - Minting to tree");
+
+    const mintIx = createMintV1Instruction(
+      {
+        mintAuthority: treeAuthority,
+        mintAuthorityRequest: mintRequest,
+        authority: treeAuthority,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      { message: metadata }
+    );
+    await execute(Bubblegum.provider, [mintIx], [], true);
+
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    const leafNonce = nonceCount.sub(new BN(1));
+
+    console.log(" - Updating metadata");
+
+    let onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const updateMetadataIx = await Bubblegum.methods
+      .updateMetadata(
+        bufferToArray(onChainRoot),
+        leafNonce,
+        0,
+        {
+          ...metadata,
+          // Anchor encodes enums as objects
+          tokenProgramVersion: { original: {} },
+        },
+        {
+          name: "updated",
+          symbol: null,
+          uri: "www.solana.com/updated",
+          creators: null,
+          sellerFeeBasisPoints: null,
+          primarySaleHappened: null,
+          isMutable: false,
+        }
+      )
+      .accounts({
+        authority: treeAuthority,
+        updateAuthority: payer.publicKey,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      })
+      .instruction();
+    await execute(Bubblegum.provider, [updateMetadataIx], [payer], true);
+
+    console.log(" - Transferring with the updated data hash");
+
+    const updatedMetadata: MetadataArgs = {
+      ...metadata,
+      name: "updated",
+      uri: "www.solana.com/updated",
+      isMutable: false,
+    };
+    const [updatedMetadataArgs] = metadataArgsBeet.serialize(updatedMetadata);
+    const dataHash = computeDataHash(
+      updatedMetadata.sellerFeeBasisPoints,
+      undefined,
+      bufferToArray(Buffer.from(keccak_256.digest(updatedMetadataArgs)))
+    );
+    const creatorHash = computeCreatorHash(updatedMetadata.creators);
+
+    onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const transferIx = createTransferInstruction(
+      {
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        newOwner: destination.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      {
+        root: bufferToArray(onChainRoot),
+        dataHash,
+        creatorHash,
+        nonce: leafNonce,
+        index: 0,
+      }
+    );
+    await execute(Bubblegum.provider, [transferIx], [payer], true);
+  });
+  it("Collection update authority can mint to, unverify and verify its collection", async function () {
+    const collectionMint = await createCollection(payer);
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: { key: collectionMint, verified: false },
+      uses: null,
+      creators: [],
+    };
+    const verifiedMetadata: MetadataArgs = {
+      ...metadata,
+      collection: { key: collectionMint, verified: true },
+    };
+
+    console.log(" - Minting to collection");
+
+    const mintIx = await getMintToCollectionIx(
+      metadata,
+      collectionMint,
+      payer.publicKey,
+      Bubblegum.programId
+    );
+    await execute(Bubblegum.provider, [mintIx], [payer]);
+
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    const leafNonce = nonceCount.sub(new BN(1));
+
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getCollectionVerificationIx(
+            true,
+            verifiedMetadata,
+            leafNonce,
+            collectionMint,
+            payer.publicKey,
+            Bubblegum.programId
+          ),
+        ],
+        [payer]
+      ),
+      "AlreadyVerified"
+    );
+
+    console.log(" - Unverifying collection");
+
+    const unverifyIx = await getCollectionVerificationIx(
+      false,
+      verifiedMetadata,
+      leafNonce,
+      collectionMint,
+      payer.publicKey,
+      Bubblegum.programId
+    );
+    await execute(Bubblegum.provider, [unverifyIx], [payer]);
+
+    console.log(" - Verifying collection");
+
+    const verifyIx = await getCollectionVerificationIx(
+      true,
+      metadata,
+      leafNonce,
+      collectionMint,
+      payer.publicKey,
+      Bubblegum.programId
+    );
+    await execute(Bubblegum.provider, [verifyIx], [payer]);
+
+    console.log(" - Transferring with the verified data hash");
+
+    const [verifiedMetadataArgs] = metadataArgsBeet.serialize(verifiedMetadata);
+    const dataHash = computeDataHash(
+      verifiedMetadata.sellerFeeBasisPoints,
+      undefined,
+      bufferToArray(Buffer.from(keccak_256.digest(verifiedMetadataArgs)))
+    );
+    const onChainRoot = await getRootOfOnChainMerkleRoot(
+      connection,
+      merkleRollKeypair.publicKey
+    );
+    const transferIx = createTransferInstruction(
+      {
+        authority: treeAuthority,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        newOwner: destination.publicKey,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      {
+        root: bufferToArray(onChainRoot),
+        dataHash,
+        creatorHash: computeCreatorHash(verifiedMetadata.creators),
+        nonce: leafNonce,
+        index: 0,
+      }
+    );
+    await execute(Bubblegum.provider, [transferIx], [payer], true);
+  });
+  it("Delegated collection authority can mint to a collection until revoked", async function () {
+    const collectionMint = await createCollection(payer);
+    const collectionMetadata = await getMetadata(collectionMint);
+    const collectionAuthorityRecord = await getCollectionAuthorityRecord(
+      collectionMint,
+      delegateKey.publicKey
+    );
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: { key: collectionMint, verified: false },
+      uses: null,
+      creators: [],
+    };
+
+    console.log(" - Approving collection authority");
+
+    const approveIx = createApproveCollectionAuthorityInstruction({
+      collectionAuthorityRecord,
+      newCollectionAuthority: delegateKey.publicKey,
+      updateAuthority: payer.publicKey,
+      payer: payer.publicKey,
+      metadata: collectionMetadata,
+      mint: collectionMint,
+    });
+    await execute(Bubblegum.provider, [approveIx], [payer]);
+
+    console.log(" - Minting to collection with the delegated authority");
+
+    const mintIx = await getMintToCollectionIx(
+      metadata,
+      collectionMint,
+      delegateKey.publicKey,
+      collectionAuthorityRecord
+    );
+    await execute(Bubblegum.provider, [mintIx], [delegateKey]);
+
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    const leafNonce = nonceCount.sub(new BN(1));
+
+    console.log(" - Unverifying collection with the delegated authority");
+
+    const unverifyIx = await getCollectionVerificationIx(
+      false,
+      { ...metadata, collection: { key: collectionMint, verified: true } },
+      leafNonce,
+      collectionMint,
+      delegateKey.publicKey,
+      collectionAuthorityRecord
+    );
+    await execute(Bubblegum.provider, [unverifyIx], [delegateKey]);
+
+    console.log(" - Revoking collection authority");
+
+    const revokeIx = createRevokeCollectionAuthorityInstruction({
+      collectionAuthorityRecord,
+      delegateAuthority: delegateKey.publicKey,
+      revokeAuthority: payer.publicKey,
+      metadata: collectionMetadata,
+      mint: collectionMint,
+    });
+    await execute(Bubblegum.provider, [revokeIx], [payer]);
+
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getMintToCollectionIx(
+            metadata,
+            collectionMint,
+            delegateKey.publicKey,
+            collectionAuthorityRecord
+          ),
+        ],
+        [delegateKey]
+      ),
+      "InvalidCollectionAuthority"
+    );
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getCollectionVerificationIx(
+            true,
+            metadata,
+            leafNonce,
+            collectionMint,
+            delegateKey.publicKey,
+            collectionAuthorityRecord
+          ),
+        ],
+        [delegateKey]
+      ),
+      "InvalidCollectionAuthority"
+    );
+  });
+  it("Rejects collection authorities that are neither the update authority nor approved", async function () {
+    const collectionMint = await createCollection(payer);
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: { key: collectionMint, verified: false },
+      uses: null,
+      creators: [],
+    };
+
+    console.log(" - Minting to collection with a wrong authority");
+
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getMintToCollectionIx(
+            metadata,
+            collectionMint,
+            destination.publicKey,
+            Bubblegum.programId
+          ),
+        ],
+        [destination]
+      ),
+      "InvalidCollectionAuthority"
+    );
+
+    console.log(" - Minting to collection without a collection authority record");
+
+    const missingRecord = await getCollectionAuthorityRecord(
+      collectionMint,
+      destination.publicKey
+    );
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getMintToCollectionIx(
+            metadata,
+            collectionMint,
+            destination.publicKey,
+            missingRecord
+          ),
+        ],
+        [destination]
+      ),
+      "InvalidCollectionAuthority"
+    );
+
+    console.log(" - Unverifying collection with a wrong authority");
+
+    const mintIx = await getMintToCollectionIx(
+      metadata,
+      collectionMint,
+      payer.publicKey,
+      Bubblegum.programId
+    );
+    await execute(Bubblegum.provider, [mintIx], [payer]);
+    const nonceCount = await getNonceCount(
+      Bubblegum.provider.connection,
+      merkleRollKeypair.publicKey
+    );
+    await assertFailsWith(
+      execute(
+        Bubblegum.provider,
+        [
+          await getCollectionVerificationIx(
+            false,
+            { ...metadata, collection: { key: collectionMint, verified: true } },
+            nonceCount.sub(new BN(1)),
+            collectionMint,
+            destination.publicKey,
+            Bubblegum.programId
+          ),
+        ],
+        [destination]
+      ),
+      "InvalidCollectionAuthority"
+    );
+  });
+  it("Rejects verified collections in mint_v1", async function () {
+    const collectionMint = await createCollection(payer);
+    const metadata: MetadataArgs = {
+      name: "test",
+      symbol: "test",
+      uri: "www.solana.com",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      collection: { key: collectionMint, verified: true },
+      uses: null,
+      creators: [],
+    };
+
+    const mintIx = createMintV1Instruction(
+      {
+        mintAuthority: treeAuthority,
+        mintAuthorityRequest: mintRequest,
+        authority: treeAuthority,
+        candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        gummyrollProgram: GummyrollProgramId,
+        owner: payer.publicKey,
+        delegate: payer.publicKey,
+        merkleSlab: merkleRollKeypair.publicKey,
+      },
+      { message: metadata }
+    );
+    await assertFailsWith(
+      execute(Bubblegum.provider, [mintIx], [payer]),
+      "CollectionCannotBeVerifiedInThisInstruction"
+    );
+  });
   it("Mint to tree with delegate", async () => {
     console.log(" - Set tree delegate");
     const randomDelegate = Keypair.generate();
@@ -15,6 +15,8 @@ import * as crypto from "crypto";
 import {
   buildTree,
   hash,
+  getMultiProof,
+  getNodeByIndex,
   getProofOfLeaf,
   updateTree,
   Tree,
@@ -138,6 +140,25 @@ describe("gummyroll", () => {
     return [merkleRollKeypair, tree];
   }
 
+  async function getOnChainMerkleRoll(merkleRoll: PublicKey) {
+    return decodeMerkleRoll(
+      (await Gummyroll.provider.connection.getAccountInfo(merkleRoll)).data
+    );
+  }
+
+  async function assertFailsWith(tx: Promise<any>, errorName: string) {
+    try {
+      await tx;
+    } catch (e) {
+      assert(
+        e.logs?.some((log: string) => log.includes(`Error Code: ${errorName}`)),
+        `Expected the transaction to fail with ${errorName}`
+      );
+      return;
+    }
+    assert(false, `Transaction should have failed with ${errorName}`);
+  }
+
   beforeEach(async () => {
     payer = Keypair.generate();
     connection = new web3Connection("http://localhost:8899", {
@@ -733,4 +754,452 @@ describe("gummyroll", () => {
       }
     });
   });
+  describe(`Append batch`, () => {
+    const DEPTH = 5;
+    const BUFFER_SIZE = 8;
+    beforeEach(async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        0,
+        DEPTH,
+        BUFFER_SIZE
+      );
+    });
+    it("Appends every leaf of the batch", async () => {
+      const leaves = [];
+      for (let i = 0; i < 4; i++) {
+        leaves.push(crypto.randomBytes(32));
+      }
+      const appendBatchIx = await Gummyroll.methods
+        .appendBatch(leaves.map((leaf) => Array.from(leaf)))
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+          candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        })
+        .instruction();
+      await execute(Gummyroll.provider, [appendBatchIx], [payer]);
+      leaves.forEach((leaf, index) => updateTree(offChainTree, leaf, index));
+
+      const merkleRoll = await getOnChainMerkleRoll(merkleRollKeypair.publicKey);
+      const onChainRoot =
+        merkleRoll.roll.changeLogs[merkleRoll.roll.activeIndex].root.toBuffer();
+      assert(
+        onChainRoot.equals(offChainTree.root),
+        "Updated on chain root does not match root of updated off chain tree"
+      );
+      assert(
+        merkleRoll.roll.sequenceNumber.toNumber() === leaves.length,
+        "One change log should be written per appended leaf"
+      );
+      assert(
+        merkleRoll.roll.rightMostPath.index === leaves.length,
+        "Rightmost path should point after the last appended leaf"
+      );
+    });
+    it("Rejects a batch larger than the buffer", async () => {
+      const leaves = [];
+      for (let i = 0; i < BUFFER_SIZE + 1; i++) {
+        leaves.push(Array.from(crypto.randomBytes(32)));
+      }
+      const appendBatchIx = await Gummyroll.methods
+        .appendBatch(leaves)
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+          candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        })
+        .instruction();
+      await assertFailsWith(
+        execute(Gummyroll.provider, [appendBatchIx], [payer]),
+        "BatchSizeExceedsBufferSize"
+      );
+    });
+  });
+  describe(`Replace leaves`, () => {
+    const DEPTH = 3;
+    beforeEach(async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        2 ** DEPTH,
+        DEPTH,
+        8
+      );
+    });
+    function createReplaceLeavesIx(indices: number[], newLeaves: Buffer[]) {
+      const proof = getMultiProof(offChainTree, indices, DEPTH).map((node) => {
+        return {
+          pubkey: new PublicKey(node),
+          isSigner: false,
+          isWritable: false,
+        };
+      });
+      return Gummyroll.methods
+        .replaceLeaves(
+          Array.from(offChainTree.root),
+          indices.map((index, i) => {
+            return {
+              previousLeaf: Array.from(offChainTree.leaves[index].node),
+              newLeaf: Array.from(newLeaves[i]),
+              index,
+            };
+          })
+        )
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+          candyWrapper: CANDY_WRAPPER_PROGRAM_ID,
+        })
+        .remainingAccounts(proof)
+        .instruction();
+    }
+    it("Replaces several leaves with a multiproof", async () => {
+      const indices = [1, 2, 6];
+      const newLeaves = indices.map(() => crypto.randomBytes(32));
+      const replaceLeavesIx = await createReplaceLeavesIx(indices, newLeaves);
+      await execute(Gummyroll.provider, [replaceLeavesIx], [payer]);
+      indices.forEach((index, i) => updateTree(offChainTree, newLeaves[i], index));
+
+      const merkleRoll = await getOnChainMerkleRoll(merkleRollKeypair.publicKey);
+      const onChainRoot =
+        merkleRoll.roll.changeLogs[merkleRoll.roll.activeIndex].root.toBuffer();
+      assert(
+        onChainRoot.equals(offChainTree.root),
+        "Updated on chain root does not match root of updated off chain tree"
+      );
+      const changedLeaves = merkleRoll.getChangeLogsWithNodeIndex()
+        .slice(0, indices.length)
+        .map((path) => path[0].node.toBuffer())
+        .reverse();
+      assert(
+        changedLeaves.every((leaf, i) => leaf.equals(newLeaves[i])),
+        "One change log should be written per replaced leaf, in index order"
+      );
+    });
+    it("Rejects leaves that are not sorted by index", async () => {
+      const indices = [6, 2];
+      const newLeaves = indices.map(() => crypto.randomBytes(32));
+      const replaceLeavesIx = await createReplaceLeavesIx(indices, newLeaves);
+      await assertFailsWith(
+        execute(Gummyroll.provider, [replaceLeavesIx], [payer]),
+        "InvalidMultiProof"
+      );
+    });
+  });
+  describe(`Close empty tree`, () => {
+    const DEPTH = 3;
+    async function createCloseTreeIx(recipient: PublicKey) {
+      return Gummyroll.methods
+        .closeEmptyTree()
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+          recipient,
+        })
+        .instruction();
+    }
+    it("Closes a tree that was never appended to", async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        0,
+        DEPTH,
+        8
+      );
+      const recipient = Keypair.generate().publicKey;
+      const treeLamports = await Gummyroll.provider.connection.getBalance(
+        merkleRollKeypair.publicKey
+      );
+      await execute(
+        Gummyroll.provider,
+        [await createCloseTreeIx(recipient)],
+        [payer]
+      );
+
+      assert(
+        (await Gummyroll.provider.connection.getAccountInfo(
+          merkleRollKeypair.publicKey
+        )) === null,
+        "Closed tree account should no longer exist"
+      );
+      assert(
+        (await Gummyroll.provider.connection.getBalance(recipient)) ===
+          treeLamports,
+        "Recipient should receive the lamports of the tree"
+      );
+    });
+    it("Only closes a tree once every leaf was emptied", async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        1,
+        DEPTH,
+        8
+      );
+      const recipient = Keypair.generate().publicKey;
+      await assertFailsWith(
+        execute(Gummyroll.provider, [await createCloseTreeIx(recipient)], [payer]),
+        "TreeNotEmpty"
+      );
+
+      const emptyLeaf = Buffer.alloc(32);
+      const replaceIx = createReplaceIx(
+        Gummyroll,
+        payer,
+        merkleRollKeypair.publicKey,
+        offChainTree.root,
+        offChainTree.leaves[0].node,
+        emptyLeaf,
+        0,
+        getProofOfLeaf(offChainTree, 0).map((treeNode) => {
+          return treeNode.node;
+        })
+      );
+      await execute(
+        Gummyroll.provider,
+        [replaceIx, await createCloseTreeIx(recipient)],
+        [payer]
+      );
+      assert(
+        (await Gummyroll.provider.connection.getAccountInfo(
+          merkleRollKeypair.publicKey
+        )) === null,
+        "Closed tree account should no longer exist"
+      );
+    });
+  });
+  describe(`Grow canopy`, () => {
+    const DEPTH = 5;
+    const CANOPY_DEPTH = 2;
+    beforeEach(async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        2 ** DEPTH,
+        DEPTH,
+        8
+      );
+    });
+    it("Replaces a leaf with a shortened proof once the canopy is grown", async () => {
+      const canopyNodes = [];
+      for (let i = 0; i < 2 ** CANOPY_DEPTH; i++) {
+        canopyNodes.push({
+          pubkey: new PublicKey(
+            getNodeByIndex(offChainTree, 2 ** CANOPY_DEPTH + i).node
+          ),
+          isSigner: false,
+          isWritable: false,
+        });
+      }
+      const growCanopyIx = await Gummyroll.methods
+        .growCanopy(CANOPY_DEPTH)
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(canopyNodes)
+        .instruction();
+      await execute(Gummyroll.provider, [growCanopyIx], [payer]);
+
+      const merkleRollAccount =
+        await Gummyroll.provider.connection.getAccountInfo(
+          merkleRollKeypair.publicKey
+        );
+      assert(
+        merkleRollAccount.data.length ===
+          getMerkleRollAccountSize(DEPTH, 8, CANOPY_DEPTH),
+        "Tree account should be reallocated to hold the canopy"
+      );
+
+      const index = 13;
+      const newLeaf = crypto.randomBytes(32);
+      const proof = getProofOfLeaf(offChainTree, index)
+        .slice(0, DEPTH - CANOPY_DEPTH)
+        .map((treeNode) => {
+          return treeNode.node;
+        });
+      const replaceIx = createReplaceIx(
+        Gummyroll,
+        payer,
+        merkleRollKeypair.publicKey,
+        offChainTree.root,
+        offChainTree.leaves[index].node,
+        newLeaf,
+        index,
+        proof
+      );
+      await execute(Gummyroll.provider, [replaceIx], [payer]);
+      updateTree(offChainTree, newLeaf, index);
+
+      const merkleRoll = await getOnChainMerkleRoll(merkleRollKeypair.publicKey);
+      const onChainRoot =
+        merkleRoll.roll.changeLogs[merkleRoll.roll.activeIndex].root.toBuffer();
+      assert(
+        onChainRoot.equals(offChainTree.root),
+        "Updated on chain root does not match root of updated off chain tree"
+      );
+    });
+  });
+  describe(`Prefill canopy`, () => {
+    const DEPTH = 5;
+    const CANOPY_DEPTH = 2;
+    beforeEach(async () => {
+      [merkleRollKeypair, offChainTree] = await createTreeOnChain(
+        payer,
+        2 ** DEPTH,
+        DEPTH,
+        8,
+        CANOPY_DEPTH
+      );
+    });
+    function canopyNode(index: number): Buffer {
+      return getNodeByIndex(offChainTree, 2 ** CANOPY_DEPTH + index).node;
+    }
+    function createPrefillCanopyIx(startIndex: number, nodes: Buffer[]) {
+      const indices = nodes.map((_, i) => startIndex + i);
+      const proof = getMultiProof(offChainTree, indices, CANOPY_DEPTH).map(
+        (node) => {
+          return {
+            pubkey: new PublicKey(node),
+            isSigner: false,
+            isWritable: false,
+          };
+        }
+      );
+      return Gummyroll.methods
+        .prefillCanopy(
+          startIndex,
+          nodes.map((node) => Array.from(node))
+        )
+        .accounts({
+          merkleRoll: merkleRollKeypair.publicKey,
+          authority: payer.publicKey,
+        })
+        .remainingAccounts(proof)
+        .instruction();
+    }
+    function createShortProofReplaceIx(index: number, newLeaf: Buffer) {
+      return createReplaceIx(
+        Gummyroll,
+        payer,
+        merkleRollKeypair.publicKey,
+        offChainTree.root,
+        offChainTree.leaves[index].node,
+        newLeaf,
+        index,
+        getProofOfLeaf(offChainTree, index)
+          .slice(0, DEPTH - CANOPY_DEPTH)
+          .map((treeNode) => {
+            return treeNode.node;
+          })
+      );
+    }
+    it("Rejects shortened proofs until the canopy is prefilled", async () => {
+      await assertFailsWith(
+        execute(
+          Gummyroll.provider,
+          [createShortProofReplaceIx(2, crypto.randomBytes(32))],
+          [payer]
+        ),
+        "CanopyNotComplete"
+      );
+    });
+    it("Prefills the canopy in several chunks", async () => {
+      for (const startIndex of [0, 2]) {
+        const nodes = [canopyNode(startIndex), canopyNode(startIndex + 1)];
+        await execute(
+          Gummyroll.provider,
+          [await createPrefillCanopyIx(startIndex, nodes)],
+          [payer]
+        );
+        const merkleRoll = await getOnChainMerkleRoll(
+          merkleRollKeypair.publicKey
+        );
+        assert(
+          merkleRoll.header.pendingCanopyNodes ===
+            2 ** CANOPY_DEPTH - startIndex - nodes.length,
+          "Every uploaded node should no longer be pending"
+        );
+      }
+
+      const index = 2;
+      const newLeaf = crypto.randomBytes(32);
+      await execute(
+        Gummyroll.provider,
+        [createShortProofReplaceIx(index, newLeaf)],
+        [payer]
+      );
+      updateTree(offChainTree, newLeaf, index);
+      const merkleRoll = await getOnChainMerkleRoll(merkleRollKeypair.publicKey);
+      const onChainRoot =
+        merkleRoll.roll.changeLogs[merkleRoll.roll.activeIndex].root.toBuffer();
+      assert(
+        onChainRoot.equals(offChainTree.root),
+        "Updated on chain root does not match root of updated off chain tree"
+      );
+    });
+    it("Rejects a chunk that does not start at the first pending node", async () => {
+      await assertFailsWith(
+        execute(
+          Gummyroll.provider,
+          [await createPrefillCanopyIx(2, [canopyNode(2), canopyNode(3)])],
+          [payer]
+        ),
+        "InvalidCanopyChunk"
+      );
+    });
+    it("Rejects a chunk holding a wrong node", async () => {
+      await assertFailsWith(
+        execute(
+          Gummyroll.provider,
+          [await createPrefillCanopyIx(0, [canopyNode(0), crypto.randomBytes(32)])],
+          [payer]
+        ),
+        "InvalidProof"
+      );
+      const merkleRoll = await getOnChainMerkleRoll(merkleRollKeypair.publicKey);
+      assert(
+        merkleRoll.header.pendingCanopyNodes === 2 ** CANOPY_DEPTH,
+        "No canopy node should be uploaded from a rejected chunk"
+      );
+    });
+  });
+  describe(`Verify leaf account owner`, () => {
+    // Both accounts are loaded from `tests/fixtures` (see Anchor.toml) and hold the same bytes:
+    // a depth 3 tree whose leaf 0 is [1; 32]. Only the look-alike is owned by another program.
+    const MERKLE_ROLL = new PublicKey(
+      "ETUF5nZ2VQcfndZ7GuShTqNyhcVihueX346mXBE6bKJt"
+    );
+    const LOOK_ALIKE_MERKLE_ROLL = new PublicKey(
+      "UQYkCfcQR6KpDsFQQUvi829Xd5TxCYWkZVTkHLhHRd5"
+    );
+    const leaves = [Buffer.alloc(32, 1), ...Array(7).fill(Buffer.alloc(32))];
+    const tree = buildTree(leaves);
+    const proof = getProofOfLeaf(tree, 0).map((treeNode) => treeNode.node);
+
+    it("Verifies a leaf of a tree owned by gummyroll", async () => {
+      const verifyLeafIx = createVerifyLeafIx(
+        Gummyroll,
+        MERKLE_ROLL,
+        tree.root,
+        leaves[0],
+        0,
+        proof
+      );
+      await execute(Gummyroll.provider, [verifyLeafIx], [payer]);
+    });
+    it("Rejects the same tree owned by another program", async () => {
+      const verifyLeafIx = createVerifyLeafIx(
+        Gummyroll,
+        LOOK_ALIKE_MERKLE_ROLL,
+        tree.root,
+        leaves[0],
+        0,
+        proof
+      );
+      await assertFailsWith(
+        execute(Gummyroll.provider, [verifyLeafIx], [payer]),
+        "ConstraintOwner"
+      );
+    });
+  });
 });
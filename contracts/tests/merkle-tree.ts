@@ -183,6 +183,43 @@ export function getProofOfLeaf(tree: Tree, idx: number, minimizeProofHeight: boo
     return proof;
 }
 
+/**
+ * Returns the node at `nodeIndex`, where the root has index 1 and the children of node `i` are `2i` and `2i + 1`
+ */
+export function getNodeByIndex(tree: Tree, nodeIndex: number): TreeNode {
+    let node = getRoot(tree);
+    for (let level = Math.floor(Math.log2(nodeIndex)) - 1; level >= 0; level--) {
+        node = (nodeIndex >> level) & 1 ? node.right : node.left;
+    }
+    return node;
+}
+
+/**
+ * Returns the multiproof of the nodes at positions `indices` (strictly increasing), `height` levels below the root.
+ * Nodes are ordered level by level from the bottom up, and by increasing node index within a level,
+ * like `MultiProof` in `concurrent-merkle-tree`.
+ */
+export function getMultiProof(tree: Tree, indices: number[], height: number): Buffer[] {
+    let proof: Buffer[] = [];
+    let level = indices.map((index) => (1 << height) + index);
+    for (let i = 0; i < height; i++) {
+        let parents: number[] = [];
+        let k = 0;
+        while (k < level.length) {
+            const nodeIndex = level[k];
+            if (nodeIndex % 2 == 0 && level[k + 1] === nodeIndex + 1) {
+                k += 2;
+            } else {
+                proof.push(getNodeByIndex(tree, nodeIndex ^ 1).node);
+                k += 1;
+            }
+            parents.push(nodeIndex >> 1);
+        }
+        level = parents;
+    }
+    return proof;
+}
+
 export function updateTree(tree: Tree, newNode: Buffer, index: number, verbose = false) {
     let leaf = tree.leaves[index];
     leaf.node = newNode;
@@ -223,6 +223,7 @@ describe("sugar-shack", () => {
         index: 0,
         root: bufferToArray(onChainRoot),
         creatorShares: bufferOfCreatorShares,
+        creatorVerified: compressedNFTMetadata.creators.map((c) => c.verified),
         sellerFeeBasisPoints: compressedNFTMetadata.sellerFeeBasisPoints,
       }
     );
@@ -35,4 +35,56 @@ pub enum CMTError {
         "Valid proof was passed to a leaf, but it's value has changed since the proof was issued"
     )]
     LeafContentsModified,
+
+    /// Max depth is 0 or larger than 30, or max buffer size is not a power of 2
+    #[error("Unsupported max depth or max buffer size")]
+    UnsupportedMerkleRollConstants,
+
+    /// Byte slice length does not match the size of a merkle roll with the given dimensions
+    #[error("Invalid number of bytes passed for merkle roll")]
+    InvalidMerkleRollByteLength,
+
+    /// Compressed proof has more nodes than the tree depth or marks levels outside of the proof as empty
+    #[error("Invalid compressed proof")]
+    InvalidCompressedProof,
+
+    /// Multiproof leaf indices are not strictly increasing, or the multiproof does not hold
+    /// exactly the nodes needed to prove them
+    #[error("Invalid multiproof")]
+    InvalidMultiProof,
+}
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    /// Changelogs between the last applied changelog and the received one are missing
+    #[error("Sequence gap: expected {expected}, received {received}")]
+    SequenceGap { expected: u64, received: u64 },
+
+    /// Changelog was already applied
+    #[error("Stale sequence number: expected {expected}, received {received}")]
+    StaleSequenceNumber { expected: u64, received: u64 },
+
+    /// Changelog path has the wrong length or node indices
+    #[error("Invalid changelog path")]
+    InvalidChangeLog,
+
+    /// Changelog path does not hash to its root given the replayed tree
+    #[error("Changelog is inconsistent with the replayed tree")]
+    InconsistentChangeLog,
+
+    /// Max depth is larger than 30 or does not match the merkle roll
+    #[error("Unsupported max depth")]
+    UnsupportedDepth,
+
+    /// The merkle roll no longer stores the root for the replayed sequence number
+    #[error("Root for the replayed sequence number is not in the changelog buffer")]
+    RootNotInBuffer,
+
+    /// The replayed root differs from the merkle roll root at the same sequence number
+    #[error("Replayed root does not match merkle roll root")]
+    RootMismatch,
+
+    /// Leaf index is not smaller than `1 << max_depth`
+    #[error("Leaf index out of bounds")]
+    LeafIndexOutOfBounds,
 }
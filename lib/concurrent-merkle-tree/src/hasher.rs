@@ -0,0 +1,169 @@
+//! Hash functions used to build the tree.
+//!
+//! Tree operations are generic over a [Hasher], which defaults to [Keccak].
+//! [Sha256] and [Poseidon] are available behind the `sha256` and `poseidon` features.
+//!
+//! Any hasher can be wrapped in [DomainSeparated] so that leaves and internal nodes are
+//! hashed with distinct prefixes, which keeps an internal node from being passed off as a leaf.
+use crate::state::{Node, EMPTY};
+use solana_program::keccak;
+use std::marker::PhantomData;
+
+/// Prefix of the data hashed into a leaf by [DomainSeparated::hash_leaf]
+pub const LEAF_PREFIX: u8 = 0;
+
+/// Prefix of the children hashed into an internal node by [DomainSeparated]
+pub const NODE_PREFIX: u8 = 1;
+
+/// Hash function used to compute parent nodes from their children.
+///
+/// Hashers are stateless marker types, so the tree layout does not depend on the hasher.
+pub trait Hasher: Copy + 'static {
+    /// Hashes the concatenation of `vals`
+    fn hashv(vals: &[&[u8]]) -> Node;
+
+    /// Hashes leaf data into a leaf node
+    #[inline(always)]
+    fn hash_leaf(vals: &[&[u8]]) -> Node {
+        Self::hashv(vals)
+    }
+
+    /// Hashes two children into their parent node
+    #[inline(always)]
+    fn hash_nodes(left: &Node, right: &Node) -> Node {
+        Self::hashv(&[left, right])
+    }
+
+    /// Computes the parent node of `node` and `sibling` and copies the result into `node`
+    #[inline(always)]
+    fn hash_to_parent(node: &mut Node, sibling: &Node, is_left: bool) {
+        let parent = if is_left {
+            Self::hash_nodes(node, sibling)
+        } else {
+            Self::hash_nodes(sibling, node)
+        };
+        node.copy_from_slice(&parent)
+    }
+
+    /// Recomputes root of the Merkle tree from Node & proof
+    fn recompute(leaf: Node, proof: &[Node], index: u32) -> Node {
+        let mut current_node = leaf;
+        for (depth, sibling) in proof.iter().enumerate() {
+            Self::hash_to_parent(&mut current_node, sibling, index >> depth & 1 == 0);
+        }
+        current_node
+    }
+
+    /// Calculates hash of empty nodes up to level i
+    fn empty_node(level: u32) -> Node {
+        Self::empty_node_cached::<0>(level, &mut Box::new([]))
+    }
+
+    /// Calculates hash of empty nodes up to level i
+    fn empty_node_cached<const N: usize>(level: u32, cache: &mut Box<[Node; N]>) -> Node {
+        let mut data = EMPTY;
+        if level != 0 {
+            let target = (level - 1) as usize;
+            let lower_empty = if target < cache.len() && cache[target] != EMPTY {
+                cache[target]
+            } else {
+                Self::empty_node(target as u32)
+            };
+            data = Self::hash_nodes(&lower_empty, &lower_empty);
+        }
+        data
+    }
+
+    /// Returns the empty node hashes for levels `0..num_levels`
+    fn empty_nodes(num_levels: usize) -> Vec<Node> {
+        let mut empty_nodes = Vec::with_capacity(num_levels);
+        let mut empty_node = EMPTY;
+        for _ in 0..num_levels {
+            empty_nodes.push(empty_node);
+            let sibling = empty_node;
+            Self::hash_to_parent(&mut empty_node, &sibling, true);
+        }
+        empty_nodes
+    }
+}
+
+/// Keccak-256, the default hasher
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Keccak;
+
+impl Hasher for Keccak {
+    #[inline(always)]
+    fn hashv(vals: &[&[u8]]) -> Node {
+        keccak::hashv(vals).to_bytes()
+    }
+}
+
+/// SHA-256
+#[cfg(feature = "sha256")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Sha256;
+
+#[cfg(feature = "sha256")]
+impl Hasher for Sha256 {
+    #[inline(always)]
+    fn hashv(vals: &[&[u8]]) -> Node {
+        solana_program::hash::hashv(vals).to_bytes()
+    }
+}
+
+/// Poseidon over the BN254 scalar field with the circom parameters.
+///
+/// Every input is reduced modulo the field order so that any 32 byte node can be hashed,
+/// and the output is the big endian encoding of the resulting field element.
+/// At most 12 inputs can be hashed at once.
+#[cfg(feature = "poseidon")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Poseidon;
+
+#[cfg(feature = "poseidon")]
+impl Hasher for Poseidon {
+    fn hashv(vals: &[&[u8]]) -> Node {
+        use ark_bn254::Fr;
+        use ark_ff::{BigInteger, PrimeField};
+        use light_poseidon::PoseidonHasher;
+
+        let inputs: Vec<Fr> = vals
+            .iter()
+            .map(|val| Fr::from_be_bytes_mod_order(val))
+            .collect();
+        let mut poseidon = light_poseidon::Poseidon::<Fr>::new_circom(inputs.len())
+            .expect("Poseidon supports up to 12 inputs");
+        let hash = poseidon
+            .hash(&inputs)
+            .expect("Inputs are valid field elements");
+        let mut node = EMPTY;
+        node.copy_from_slice(&hash.into_bigint().to_bytes_be());
+        node
+    }
+}
+
+/// Wraps `H` so that leaf data is hashed after [LEAF_PREFIX] and children after [NODE_PREFIX].
+///
+/// Only internal nodes are computed by the tree, so leaves must be built with
+/// [Hasher::hash_leaf] by the caller for the separation to hold. Empty leaves are still [EMPTY].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DomainSeparated<H: Hasher>(PhantomData<H>);
+
+impl<H: Hasher> Hasher for DomainSeparated<H> {
+    #[inline(always)]
+    fn hashv(vals: &[&[u8]]) -> Node {
+        H::hashv(vals)
+    }
+
+    fn hash_leaf(vals: &[&[u8]]) -> Node {
+        let mut prefixed = Vec::with_capacity(vals.len() + 1);
+        prefixed.push([LEAF_PREFIX].as_ref());
+        prefixed.extend_from_slice(vals);
+        H::hashv(&prefixed)
+    }
+
+    #[inline(always)]
+    fn hash_nodes(left: &Node, right: &Node) -> Node {
+        H::hashv(&[&[NODE_PREFIX], left, right])
+    }
+}
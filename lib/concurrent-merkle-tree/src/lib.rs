@@ -1,6 +1,10 @@
 pub mod error;
+pub mod hasher;
 #[macro_use]
 pub mod log;
 pub mod merkle_roll;
+pub mod merkle_roll_view;
+pub mod multiproof;
+pub mod replay;
 pub mod state;
 pub mod utils;
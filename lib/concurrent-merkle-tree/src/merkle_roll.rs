@@ -1,27 +1,27 @@
 use crate::{
     error::CMTError,
-    state::{ChangeLog, Node, Path, EMPTY},
-    utils::{empty_node, empty_node_cached, fill_in_proof, recompute, hash_to_parent},
+    hasher::{Hasher, Keccak},
+    merkle_roll_view::{MerkleRollMut, MerkleRollRef},
+    multiproof::MultiProof,
+    state::{ChangeLog, Node, Path},
 };
-use bytemuck::{Pod, Zeroable};
+use bytemuck::{bytes_of, bytes_of_mut, Pod, Zeroable};
 pub(crate) use log_compute;
 pub(crate) use solana_logging;
+use std::marker::PhantomData;
 
-#[cfg(feature = "sol-log")]
-use solana_program::{log::sol_log_compute_units, msg};
-
-#[inline(always)]
-fn check_bounds(max_depth: usize, max_buffer_size: usize) {
-    assert!(max_depth < 31);
-    // This will return true if MAX_BUFFER_SIZE is a power of 2 or if it is 0
-    assert!(max_buffer_size & (max_buffer_size - 1) == 0);
-}
 /// Tracks updates to off-chain Merkle tree
 ///
 /// Allows for concurrent writes to same merkle tree so long as proof
 /// was generated at most MAX_SIZE updates since the tx was submitted
+///
+/// The layout is `repr(C)` so that [MerkleRollRef] and [MerkleRollMut] can operate
+/// on the same bytes when the dimensions are only known at runtime.
+/// All tree operations are implemented by those views.
+/// Nodes are hashed with `H`, which does not change the layout.
 #[derive(Copy, Clone)]
-pub struct MerkleRoll<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> {
+#[repr(C)]
+pub struct MerkleRoll<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize, H: Hasher = Keccak> {
     pub sequence_number: u64,
     /// Index of most recent root & changes
     pub active_index: u64,
@@ -30,18 +30,21 @@ pub struct MerkleRoll<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> {
     /// Proof for respective root
     pub change_logs: [ChangeLog<MAX_DEPTH>; MAX_BUFFER_SIZE],
     pub rightmost_proof: Path<MAX_DEPTH>,
+    _hasher: PhantomData<H>,
 }
 
-unsafe impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> Zeroable
-    for MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE>
+unsafe impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize, H: Hasher> Zeroable
+    for MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE, H>
 {
 }
-unsafe impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> Pod
-    for MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE>
+unsafe impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize, H: Hasher> Pod
+    for MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE, H>
 {
 }
 
-impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE> {
+impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize, H: Hasher>
+    MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE, H>
+{
     pub fn new() -> Self {
         Self {
             sequence_number: 0,
@@ -49,27 +52,22 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> MerkleRoll<MAX_DEPTH,
             buffer_size: 0,
             change_logs: [ChangeLog::<MAX_DEPTH>::default(); MAX_BUFFER_SIZE],
             rightmost_proof: Path::<MAX_DEPTH>::default(),
+            _hasher: PhantomData,
         }
     }
 
+    /// Read-only runtime-sized view over this merkle roll
+    pub fn as_view(&self) -> Result<MerkleRollRef<'_, H>, CMTError> {
+        MerkleRollRef::with_hasher(bytes_of(self), MAX_DEPTH, MAX_BUFFER_SIZE)
+    }
+
+    /// Mutable runtime-sized view over this merkle roll
+    pub fn as_view_mut(&mut self) -> Result<MerkleRollMut<'_, H>, CMTError> {
+        MerkleRollMut::with_hasher(bytes_of_mut(self), MAX_DEPTH, MAX_BUFFER_SIZE)
+    }
+
     pub fn initialize(&mut self) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        let mut rightmost_proof = Path::default();
-        let mut empty_node_cache = Box::new([Node::default(); MAX_DEPTH]);
-        for (i, node) in rightmost_proof.proof.iter_mut().enumerate() {
-            *node = empty_node_cached::<MAX_DEPTH>(i as u32, &mut empty_node_cache);
-        }
-        let mut path = [Node::default(); MAX_DEPTH];
-        for (i, node) in path.iter_mut().enumerate() {
-            *node = empty_node_cached::<MAX_DEPTH>(i as u32, &mut empty_node_cache);
-        }
-        self.change_logs[0].root = empty_node(MAX_DEPTH as u32);
-        self.change_logs[0].path = path;
-        self.sequence_number = 0;
-        self.active_index = 0;
-        self.buffer_size = 1;
-        self.rightmost_proof = rightmost_proof;
-        Ok(self.change_logs[0].root)
+        self.as_view_mut()?.initialize()
     }
 
     pub fn initialize_with_root(
@@ -79,121 +77,84 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> MerkleRoll<MAX_DEPTH,
         proof_vec: &Vec<Node>,
         index: u32,
     ) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        let mut proof: [Node; MAX_DEPTH] = [Node::default(); MAX_DEPTH];
-        proof.copy_from_slice(&proof_vec[..]);
-        let rightmost_proof = Path {
-            proof,
-            index: index + 1,
-            leaf: rightmost_leaf,
-            _padding: 0,
-        };
-        self.change_logs[0].root = root;
-        self.sequence_number = 1;
-        self.active_index = 0;
-        self.buffer_size = 1;
-        self.rightmost_proof = rightmost_proof;
-        assert_eq!(root, recompute(rightmost_leaf, &proof, index,));
-        Ok(root)
+        self.as_view_mut()?
+            .initialize_with_root(root, rightmost_leaf, proof_vec, index)
     }
 
     pub fn get_change_log(&self) -> Box<ChangeLog<MAX_DEPTH>> {
         Box::new(self.change_logs[self.active_index as usize])
     }
 
+    /// Returns the change log written `offset` operations before the most recent one.
+    /// Only the last `buffer_size` change logs are retained.
+    pub fn get_prior_change_log(&self, offset: u64) -> Box<ChangeLog<MAX_DEPTH>> {
+        let mask: usize = MAX_BUFFER_SIZE - 1;
+        let index = self.active_index.wrapping_sub(offset) & mask as u64;
+        Box::new(self.change_logs[index as usize])
+    }
+
+    /// See [MerkleRollRef::changes_for_leaf]
+    pub fn changes_for_leaf(&self, leaf_index: u32) -> impl Iterator<Item = (u64, Node)> + '_ {
+        let mask: usize = MAX_BUFFER_SIZE - 1;
+        (0..self.buffer_size).rev().filter_map(move |offset| {
+            let index = self.active_index.wrapping_sub(offset) & mask as u64;
+            let change_log = &self.change_logs[index as usize];
+            if change_log.index == leaf_index && self.sequence_number > offset {
+                Some((self.sequence_number - offset, change_log.get_leaf()))
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn prove_leaf(
-        &mut self,
+        &self,
         current_root: Node,
         leaf: Node,
         proof_vec: &Vec<Node>,
         leaf_index: u32,
     ) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        if leaf_index > self.rightmost_proof.index {
-            solana_logging!(
-                "Received an index larger than the rightmost index {} > {}",
-                leaf_index,
-                self.rightmost_proof.index
-            );
-            return Err(CMTError::LeafIndexOutOfBounds);
-        } else {
-            let mut proof: [Node; MAX_DEPTH] = [Node::default(); MAX_DEPTH];
-            fill_in_proof::<MAX_DEPTH>(proof_vec, &mut proof);
-            let valid_root =
-                self.check_valid_leaf(current_root, leaf, &mut proof, leaf_index, true)?;
-            if !valid_root {
-                solana_logging!("Proof failed to verify");
-                return Err(CMTError::InvalidProof);
-            }
-            Ok(Node::default())
-        }
+        self.as_view()?
+            .prove_leaf(current_root, leaf, proof_vec, leaf_index)
     }
 
-    /// Only used to initialize right most path for a completely empty tree
-    #[inline(always)]
-    fn initialize_tree_from_append(
-        &mut self,
+    /// See [MerkleRollRef::fast_forward]
+    pub fn fast_forward(
+        &self,
+        current_root: Node,
         leaf: Node,
-        mut proof: [Node; MAX_DEPTH],
+        proof_vec: &[Node],
+        leaf_index: u32,
+    ) -> Result<(Node, Vec<Node>), CMTError> {
+        self.as_view()?
+            .fast_forward(current_root, leaf, proof_vec, leaf_index)
+    }
+
+    /// Batch version of `prove_leaf`, see [MerkleRollRef::prove_leaves]
+    pub fn prove_leaves(
+        &self,
+        current_root: Node,
+        leaves: &[Node],
+        multiproof: &MultiProof,
     ) -> Result<Node, CMTError> {
-        let old_root = recompute(EMPTY, &proof, 0);
-        if old_root == empty_node(MAX_DEPTH as u32) {
-            self.try_apply_proof(old_root, EMPTY, leaf, &mut proof, 0, false)
-        } else {
-            return Err(CMTError::TreeAlreadyInitialized);
-        }
+        self.as_view()?
+            .prove_leaves(current_root, leaves, multiproof)
     }
 
     /// Basic operation that always succeeds
-    pub fn append(&mut self, mut node: Node) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        if node == EMPTY {
-            return Err(CMTError::CannotAppendEmptyNode);
-        }
-        if self.rightmost_proof.index >= 1 << MAX_DEPTH {
-            return Err(CMTError::TreeFull);
-        }
-        if self.rightmost_proof.index == 0 {
-            return self.initialize_tree_from_append(node, self.rightmost_proof.proof);
-        }
-        let leaf = node.clone();
-        let intersection = self.rightmost_proof.index.trailing_zeros() as usize;
-        let mut change_list = [EMPTY; MAX_DEPTH];
-        let mut intersection_node = self.rightmost_proof.leaf;
-        let mut empty_node_cache = Box::new([Node::default(); MAX_DEPTH]);
-
-        for i in 0..MAX_DEPTH {
-            change_list[i] = node;
-            if i < intersection {
-                // Compute proof to the appended node from empty nodes
-                let sibling = empty_node_cached::<MAX_DEPTH>(i as u32, &mut empty_node_cache);
-                hash_to_parent(
-                    &mut intersection_node,
-                    &self.rightmost_proof.proof[i],
-                    ((self.rightmost_proof.index - 1) >> i) & 1 == 0,
-                );
-                hash_to_parent(&mut node, &sibling, true);
-                self.rightmost_proof.proof[i] = sibling;
-            } else if i == intersection {
-                // Compute the where the new node intersects the main tree
-                hash_to_parent(&mut node, &intersection_node, false);
-                self.rightmost_proof.proof[intersection] = intersection_node;
-            } else {
-                // Update the change list path up to the root
-                hash_to_parent(
-                    &mut node,
-                    &self.rightmost_proof.proof[i],
-                    ((self.rightmost_proof.index - 1) >> i) & 1 == 0,
-                );
-            }
-        }
+    pub fn append(&mut self, node: Node) -> Result<Node, CMTError> {
+        self.as_view_mut()?.append(node)
+    }
 
-        self.update_internal_counters();
-        self.change_logs[self.active_index as usize] =
-            ChangeLog::<MAX_DEPTH>::new(node, change_list, self.rightmost_proof.index);
-        self.rightmost_proof.index = self.rightmost_proof.index + 1;
-        self.rightmost_proof.leaf = leaf;
-        Ok(node)
+    /// Appends `leaves` in order, writing one change log per leaf.
+    ///
+    /// The resulting state is identical to calling `append` on each leaf. Every change log
+    /// holds the full path and root after its leaf, so each leaf still costs at least `max_depth`
+    /// hashes: the batch only saves the per call overhead, e.g. the empty node hashes are
+    /// computed once. The batch is validated up front, so either all leaves are appended
+    /// or the tree is left unchanged.
+    pub fn append_batch(&mut self, leaves: &[Node]) -> Result<Node, CMTError> {
+        self.as_view_mut()?.append_batch(leaves)
     }
 
     /// Convenience function for `set_leaf`
@@ -206,19 +167,8 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> MerkleRoll<MAX_DEPTH,
         proof_vec: &Vec<Node>,
         index: u32,
     ) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        let mut proof: [Node; MAX_DEPTH] = [Node::default(); MAX_DEPTH];
-        fill_in_proof::<MAX_DEPTH>(&proof_vec, &mut proof);
-        log_compute!();
-        let root = match self.try_apply_proof(current_root, EMPTY, leaf, &mut proof, index, false) {
-            Ok(new_root) => Ok(new_root),
-            Err(error) => match error {
-                CMTError::LeafContentsModified => self.append(leaf),
-                _ => Err(error),
-            },
-        };
-        log_compute!();
-        root
+        self.as_view_mut()?
+            .fill_empty_or_append(current_root, leaf, proof_vec, index)
     }
 
     /// On write conflict:
@@ -231,177 +181,18 @@ impl<const MAX_DEPTH: usize, const MAX_BUFFER_SIZE: usize> MerkleRoll<MAX_DEPTH,
         proof_vec: &Vec<Node>,
         index: u32,
     ) -> Result<Node, CMTError> {
-        check_bounds(MAX_DEPTH, MAX_BUFFER_SIZE);
-        if index > self.rightmost_proof.index {
-            return Err(CMTError::LeafIndexOutOfBounds);
-        } else {
-            let mut proof: [Node; MAX_DEPTH] = [Node::default(); MAX_DEPTH];
-            fill_in_proof::<MAX_DEPTH>(&proof_vec, &mut proof);
-            log_compute!();
-            let root = self.try_apply_proof(
-                current_root,
-                previous_leaf,
-                new_leaf,
-                &mut proof,
-                index,
-                true,
-            );
-            log_compute!();
-            root
-        }
-    }
-
-    /// Modifies the `proof` for leaf at `leaf_index`
-    /// in place by fast-forwarding the given `proof` through the
-    /// `changelog`s, starting at index `changelog_buffer_index`
-    /// Returns false if the leaf was updated in the change log
-    #[inline(always)]
-    fn fast_forward_proof(
-        &self,
-        leaf: &mut Node,
-        proof: &mut [Node; MAX_DEPTH],
-        leaf_index: u32,
-        mut changelog_buffer_index: u64,
-        use_full_buffer: bool,
-    ) -> bool {
-        solana_logging!(
-            "Fast-forwarding proof, starting index {}",
-            changelog_buffer_index
-        );
-        let mask: usize = MAX_BUFFER_SIZE - 1;
-
-        let mut updated_leaf = *leaf;
-        log_compute!();
-        // Modifies proof by iterating through the change log
-        loop {
-            // If use_full_buffer is false, this loop will terminate if the initial value of changelog_buffer_index is the active index
-            if !use_full_buffer && changelog_buffer_index == self.active_index {
-                break;
-            }
-            changelog_buffer_index = (changelog_buffer_index + 1) & mask as u64;
-            self.change_logs[changelog_buffer_index as usize].update_proof_or_leaf(
-                leaf_index,
-                proof,
-                &mut updated_leaf,
-            );
-            // If use_full_buffer is true, this loop will do 1 full pass of the change logs
-            if use_full_buffer && changelog_buffer_index == self.active_index {
-                break;
-            }
-        }
-        log_compute!();
-        let proof_leaf_unchanged = updated_leaf == *leaf;
-        *leaf = updated_leaf;
-        proof_leaf_unchanged
-    }
-
-    #[inline(always)]
-    fn find_root_in_changelog(&self, current_root: Node) -> Option<u64> {
-        let mask: usize = MAX_BUFFER_SIZE - 1;
-        for i in 0..self.buffer_size {
-            let j = self.active_index.wrapping_sub(i) & mask as u64;
-            if self.change_logs[j as usize].root == current_root {
-                return Some(j);
-            }
-        }
-        None
-    }
-
-    #[inline(always)]
-    fn check_valid_leaf(
-        &self,
-        current_root: Node,
-        leaf: Node,
-        proof: &mut [Node; MAX_DEPTH],
-        leaf_index: u32,
-        allow_inferred_proof: bool,
-    ) -> Result<bool, CMTError> {
-        let mask: usize = MAX_BUFFER_SIZE - 1;
-        let (changelog_index, use_full_buffer) = match self.find_root_in_changelog(current_root) {
-            Some(matching_changelog_index) => (matching_changelog_index, false),
-            None => {
-                if allow_inferred_proof {
-                    solana_logging!("Failed to find root in change log -> replaying full buffer");
-                    (
-                        self.active_index.wrapping_sub(self.buffer_size - 1) & mask as u64,
-                        true,
-                    )
-                } else {
-                    return Err(CMTError::RootNotFound);
-                }
-            }
-        };
-        let mut updatable_leaf_node = leaf;
-        let proof_leaf_unchanged = self.fast_forward_proof(
-            &mut updatable_leaf_node,
-            proof,
-            leaf_index,
-            changelog_index,
-            use_full_buffer,
-        );
-        if !proof_leaf_unchanged {
-            return Err(CMTError::LeafContentsModified);
-        }
-        Ok(recompute(updatable_leaf_node, proof, leaf_index) == self.get_change_log().root)
+        self.as_view_mut()?
+            .set_leaf(current_root, previous_leaf, new_leaf, proof_vec, index)
     }
 
-    /// Note: Enabling `allow_inferred_proof` will fast forward the given proof
-    /// from the beginning of the buffer in the case that the supplied root is not in the buffer.
-    #[inline(always)]
-    fn try_apply_proof(
+    /// Replaces several leaves using a single multiproof, see [MerkleRollMut::set_leaves]
+    pub fn set_leaves(
         &mut self,
         current_root: Node,
-        leaf: Node,
-        new_leaf: Node,
-        proof: &mut [Node; MAX_DEPTH],
-        leaf_index: u32,
-        allow_inferred_proof: bool,
+        leaves: &[(Node, Node, u32)],
+        proof_vec: &[Node],
     ) -> Result<Node, CMTError> {
-        solana_logging!("Active Index: {}", self.active_index);
-        solana_logging!("Rightmost Index: {}", self.rightmost_proof.index);
-        solana_logging!("Buffer Size: {}", self.buffer_size);
-        solana_logging!("Leaf Index: {}", leaf_index);
-        let valid_root =
-            self.check_valid_leaf(current_root, leaf, proof, leaf_index, allow_inferred_proof)?;
-        if !valid_root {
-            return Err(CMTError::InvalidProof);
-        }
-        self.update_internal_counters();
-        Ok(self.update_buffers_from_proof(new_leaf, proof, leaf_index))
-    }
-
-    /// Implements circular addition for changelog buffer index
-    fn update_internal_counters(&mut self) {
-        let mask: usize = MAX_BUFFER_SIZE - 1;
-        self.active_index += 1;
-        self.active_index &= mask as u64;
-        if self.buffer_size < MAX_BUFFER_SIZE as u64 {
-            self.buffer_size += 1;
-        }
-        self.sequence_number = self.sequence_number.saturating_add(1);
-    }
-
-    /// Creates a new root from a proof that is valid for the root at `self.active_index`
-    fn update_buffers_from_proof(&mut self, start: Node, proof: &[Node], index: u32) -> Node {
-        let change_log = &mut self.change_logs[self.active_index as usize];
-        // Also updates change_log's current root
-        let root = change_log.replace_and_recompute_path(index, start, proof);
-        // Update rightmost path if possible
-        if self.rightmost_proof.index < (1 << MAX_DEPTH) {
-            if index < self.rightmost_proof.index as u32 {
-                change_log.update_proof_or_leaf(
-                    self.rightmost_proof.index - 1,
-                    &mut self.rightmost_proof.proof,
-                    &mut self.rightmost_proof.leaf,
-                );
-            } else {
-                assert!(index == self.rightmost_proof.index);
-                solana_logging!("Appending rightmost leaf");
-                self.rightmost_proof.proof.copy_from_slice(&proof);
-                self.rightmost_proof.index = index + 1;
-                self.rightmost_proof.leaf = change_log.get_leaf();
-            }
-        }
-        root
+        self.as_view_mut()?
+            .set_leaves(current_root, leaves, proof_vec)
     }
 }
@@ -0,0 +1,916 @@
+//! Runtime-sized views over the bytes of a [MerkleRoll](crate::merkle_roll::MerkleRoll).
+//!
+//! `MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE>` fixes the tree dimensions at compile time, so a program
+//! that supports several tree sizes needs one copy of the tree logic per size. The views in this
+//! module read `max_depth` and `max_buffer_size` at runtime and operate directly on a byte slice
+//! with the same layout as `MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE>`.
+use crate::{
+    error::CMTError,
+    hasher::{Hasher, Keccak},
+    multiproof::{expand_multiproof, MultiProof},
+    state::{Node, EMPTY},
+    utils::fill_in_proof,
+};
+use bytemuck::{cast_slice, cast_slice_mut, from_bytes};
+use std::convert::TryInto;
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+#[cfg(feature = "sol-log")]
+use solana_program::{log::sol_log_compute_units, msg};
+
+/// Largest tree depth supported by the concurrent merkle tree
+pub const MAX_SUPPORTED_DEPTH: usize = 30;
+
+/// `sequence_number`, `active_index` and `buffer_size`
+const COUNTERS_SIZE: usize = 3 * size_of::<u64>();
+
+/// Byte length of a `ChangeLog` (root, path, index & padding)
+#[inline(always)]
+fn change_log_size(max_depth: usize) -> usize {
+    size_of::<Node>() * (max_depth + 1) + 2 * size_of::<u32>()
+}
+
+/// Byte length of a `Path` (proof, leaf, index & padding)
+#[inline(always)]
+fn path_size(max_depth: usize) -> usize {
+    size_of::<Node>() * (max_depth + 1) + 2 * size_of::<u32>()
+}
+
+/// Returns the number of bytes occupied by a merkle roll with the given dimensions.
+/// This is equal to `size_of::<MerkleRoll<max_depth, max_buffer_size>>()`.
+pub fn merkle_roll_size(max_depth: usize, max_buffer_size: usize) -> Result<usize, CMTError> {
+    // is_power_of_two returns false for 0
+    if max_depth == 0 || max_depth > MAX_SUPPORTED_DEPTH || !max_buffer_size.is_power_of_two() {
+        solana_logging!(
+            "Unsupported max depth {} or max buffer size {}",
+            max_depth,
+            max_buffer_size
+        );
+        return Err(CMTError::UnsupportedMerkleRollConstants);
+    }
+    Ok(COUNTERS_SIZE + max_buffer_size * change_log_size(max_depth) + path_size(max_depth))
+}
+
+#[inline(always)]
+fn check_dimensions(
+    data_len: usize,
+    max_depth: usize,
+    max_buffer_size: usize,
+) -> Result<(), CMTError> {
+    let expected_len = merkle_roll_size(max_depth, max_buffer_size)?;
+    if data_len != expected_len {
+        solana_logging!(
+            "Merkle roll byte length {} does not match expected length {}",
+            data_len,
+            expected_len
+        );
+        return Err(CMTError::InvalidMerkleRollByteLength);
+    }
+    Ok(())
+}
+
+#[inline(always)]
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[inline(always)]
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[inline(always)]
+fn write_u64(data: &mut [u8], offset: usize, value: u64) {
+    data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[inline(always)]
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Borrowed counterpart of [ChangeLog](crate::state::ChangeLog)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChangeLogRef<'a> {
+    /// Root of the tree after the change was applied
+    pub root: &'a Node,
+    /// Nodes from the modified leaf up to (but not including) the root
+    pub path: &'a [Node],
+    /// Index of the modified leaf
+    pub index: u32,
+}
+
+impl<'a> ChangeLogRef<'a> {
+    pub fn get_leaf(&self) -> Node {
+        self.path[0]
+    }
+
+    /// Updates `proof` (or `leaf`) for the leaf at `leaf_index` so that it reflects this change.
+    /// Equivalent to [ChangeLog::update_proof_or_leaf](crate::state::ChangeLog::update_proof_or_leaf).
+    pub fn update_proof_or_leaf(&self, leaf_index: u32, proof: &mut [Node], leaf: &mut Node) {
+        let max_depth = self.path.len();
+        let padding: usize = 32 - max_depth;
+        if leaf_index != self.index {
+            // This bit math is used to identify which node in the proof
+            // we need to swap for a corresponding node in a saved change log
+            let common_path_len = ((leaf_index ^ self.index) << padding).leading_zeros() as usize;
+            let critbit_index = (max_depth - 1) - common_path_len;
+            proof[critbit_index] = self.path[critbit_index];
+        } else {
+            *leaf = self.get_leaf();
+        }
+    }
+}
+
+/// Borrowed counterpart of [Path](crate::state::Path)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathRef<'a> {
+    pub proof: &'a [Node],
+    pub leaf: &'a Node,
+    pub index: u32,
+}
+
+/// Read-only view of a merkle roll whose dimensions are only known at runtime
+#[derive(Copy, Clone)]
+pub struct MerkleRollRef<'a, H: Hasher = Keccak> {
+    max_depth: usize,
+    max_buffer_size: usize,
+    data: &'a [u8],
+    _hasher: PhantomData<H>,
+}
+
+impl<'a> MerkleRollRef<'a> {
+    /// Fails if the dimensions are unsupported or if `data` is not exactly
+    /// `merkle_roll_size(max_depth, max_buffer_size)` bytes long
+    pub fn new(data: &'a [u8], max_depth: usize, max_buffer_size: usize) -> Result<Self, CMTError> {
+        Self::with_hasher(data, max_depth, max_buffer_size)
+    }
+}
+
+impl<'a, H: Hasher> MerkleRollRef<'a, H> {
+    /// Same as `new`, for a tree built with the hasher `H`
+    pub fn with_hasher(
+        data: &'a [u8],
+        max_depth: usize,
+        max_buffer_size: usize,
+    ) -> Result<Self, CMTError> {
+        check_dimensions(data.len(), max_depth, max_buffer_size)?;
+        Ok(Self {
+            max_depth,
+            max_buffer_size,
+            data,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Underlying merkle roll bytes
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn max_buffer_size(&self) -> usize {
+        self.max_buffer_size
+    }
+
+    pub fn sequence_number(&self) -> u64 {
+        read_u64(self.data, 0)
+    }
+
+    /// Index of most recent root & changes
+    pub fn active_index(&self) -> u64 {
+        read_u64(self.data, 8)
+    }
+
+    /// Number of active changes we are tracking
+    pub fn buffer_size(&self) -> u64 {
+        read_u64(self.data, 16)
+    }
+
+    /// Returns the change log stored at `buffer_index` in the changelog buffer
+    pub fn change_log(&self, buffer_index: usize) -> ChangeLogRef<'a> {
+        let offset = COUNTERS_SIZE + buffer_index * change_log_size(self.max_depth);
+        let change_log_bytes = &self.data[offset..offset + change_log_size(self.max_depth)];
+        let (root, rest) = change_log_bytes.split_at(size_of::<Node>());
+        let (path, rest) = rest.split_at(size_of::<Node>() * self.max_depth);
+        ChangeLogRef {
+            root: from_bytes(root),
+            path: cast_slice(path),
+            index: read_u32(rest, 0),
+        }
+    }
+
+    pub fn get_change_log(&self) -> ChangeLogRef<'a> {
+        self.change_log(self.active_index() as usize)
+    }
+
+    /// Returns the change log written `offset` operations before the most recent one.
+    /// Only the last `buffer_size` change logs are retained.
+    pub fn get_prior_change_log(&self, offset: u64) -> ChangeLogRef<'a> {
+        let mask: usize = self.max_buffer_size - 1;
+        let index = self.active_index().wrapping_sub(offset) & mask as u64;
+        self.change_log(index as usize)
+    }
+
+    /// Returns the sequence number and new value of every change to the leaf at `leaf_index`
+    /// that is still in the changelog buffer, from oldest to most recent. The change log
+    /// written when the tree was initialized (sequence number 0) is not a change.
+    ///
+    /// A proof of the leaf that is older than one of these changes fails with
+    /// `LeafContentsModified`.
+    pub fn changes_for_leaf(&self, leaf_index: u32) -> impl Iterator<Item = (u64, Node)> + 'a
+    where
+        H: 'a,
+    {
+        let merkle_roll = *self;
+        let sequence_number = self.sequence_number();
+        (0..self.buffer_size()).rev().filter_map(move |offset| {
+            let change_log = merkle_roll.get_prior_change_log(offset);
+            if change_log.index == leaf_index && sequence_number > offset {
+                Some((sequence_number - offset, change_log.get_leaf()))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn rightmost_proof(&self) -> PathRef<'a> {
+        let offset = COUNTERS_SIZE + self.max_buffer_size * change_log_size(self.max_depth);
+        let path_bytes = &self.data[offset..offset + path_size(self.max_depth)];
+        let (proof, rest) = path_bytes.split_at(size_of::<Node>() * self.max_depth);
+        let (leaf, rest) = rest.split_at(size_of::<Node>());
+        PathRef {
+            proof: cast_slice(proof),
+            leaf: from_bytes(leaf),
+            index: read_u32(rest, 0),
+        }
+    }
+
+    pub fn prove_leaf(
+        &self,
+        current_root: Node,
+        leaf: Node,
+        proof_vec: &[Node],
+        leaf_index: u32,
+    ) -> Result<Node, CMTError> {
+        if leaf_index > self.rightmost_proof().index {
+            solana_logging!(
+                "Received an index larger than the rightmost index {} > {}",
+                leaf_index,
+                self.rightmost_proof().index
+            );
+            Err(CMTError::LeafIndexOutOfBounds)
+        } else {
+            let mut proof = vec![Node::default(); self.max_depth];
+            fill_in_proof::<H>(proof_vec, &mut proof);
+            let valid_root =
+                self.check_valid_leaf(current_root, leaf, &mut proof, leaf_index, true)?;
+            if !valid_root {
+                solana_logging!("Proof failed to verify");
+                return Err(CMTError::InvalidProof);
+            }
+            Ok(Node::default())
+        }
+    }
+
+    /// Fast-forwards a possibly stale `proof` of `leaf` against `current_root` through the
+    /// changelog buffer, as the program does before modifying the tree.
+    ///
+    /// Returns the most recent root and the proof of `leaf` against it, so clients can compute
+    /// an up to date proof from a snapshot of the account instead of replaying it on-chain.
+    /// Fails like `prove_leaf` if the leaf was modified or the proof does not verify.
+    pub fn fast_forward(
+        &self,
+        current_root: Node,
+        leaf: Node,
+        proof_vec: &[Node],
+        leaf_index: u32,
+    ) -> Result<(Node, Vec<Node>), CMTError> {
+        if leaf_index > self.rightmost_proof().index {
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        let mut proof = vec![Node::default(); self.max_depth];
+        fill_in_proof::<H>(proof_vec, &mut proof);
+        if !self.check_valid_leaf(current_root, leaf, &mut proof, leaf_index, true)? {
+            return Err(CMTError::InvalidProof);
+        }
+        Ok((*self.get_change_log().root, proof))
+    }
+
+    /// Batch version of `prove_leaf`: checks that `leaves`, given in the same order as
+    /// `multiproof.leaf_indices`, were in the tree with root `current_root` and have not
+    /// been modified since. Proofs are fast-forwarded through the change log buffer.
+    pub fn prove_leaves(
+        &self,
+        current_root: Node,
+        leaves: &[Node],
+        multiproof: &MultiProof,
+    ) -> Result<Node, CMTError> {
+        let rightmost_index = self.rightmost_proof().index;
+        if multiproof
+            .leaf_indices
+            .iter()
+            .any(|leaf_index| *leaf_index > rightmost_index)
+        {
+            solana_logging!("Received an index larger than the rightmost index");
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        let mut proofs = multiproof.expand::<H>(leaves, self.max_depth)?;
+        for ((leaf, leaf_index), proof) in leaves
+            .iter()
+            .zip(multiproof.leaf_indices.iter())
+            .zip(proofs.iter_mut())
+        {
+            let valid_root =
+                self.check_valid_leaf(current_root, *leaf, proof, *leaf_index, true)?;
+            if !valid_root {
+                solana_logging!("Proof failed to verify");
+                return Err(CMTError::InvalidProof);
+            }
+        }
+        Ok(Node::default())
+    }
+
+    /// Modifies the `proof` for leaf at `leaf_index`
+    /// in place by fast-forwarding the given `proof` through the
+    /// `changelog`s, starting at index `changelog_buffer_index`
+    /// Returns false if the leaf was updated in the change log
+    #[inline(always)]
+    fn fast_forward_proof(
+        &self,
+        leaf: &mut Node,
+        proof: &mut [Node],
+        leaf_index: u32,
+        mut changelog_buffer_index: u64,
+        use_full_buffer: bool,
+    ) -> bool {
+        solana_logging!(
+            "Fast-forwarding proof, starting index {}",
+            changelog_buffer_index
+        );
+        let mask: usize = self.max_buffer_size - 1;
+        let active_index = self.active_index();
+
+        let mut updated_leaf = *leaf;
+        log_compute!();
+        // Modifies proof by iterating through the change log
+        loop {
+            // If use_full_buffer is false, this loop will terminate if the initial value of changelog_buffer_index is the active index
+            if !use_full_buffer && changelog_buffer_index == active_index {
+                break;
+            }
+            changelog_buffer_index = (changelog_buffer_index + 1) & mask as u64;
+            self.change_log(changelog_buffer_index as usize)
+                .update_proof_or_leaf(leaf_index, proof, &mut updated_leaf);
+            // If use_full_buffer is true, this loop will do 1 full pass of the change logs
+            if use_full_buffer && changelog_buffer_index == active_index {
+                break;
+            }
+        }
+        log_compute!();
+        let proof_leaf_unchanged = updated_leaf == *leaf;
+        *leaf = updated_leaf;
+        proof_leaf_unchanged
+    }
+
+    #[inline(always)]
+    fn find_root_in_changelog(&self, current_root: Node) -> Option<u64> {
+        let mask: usize = self.max_buffer_size - 1;
+        for i in 0..self.buffer_size() {
+            let j = self.active_index().wrapping_sub(i) & mask as u64;
+            if *self.change_log(j as usize).root == current_root {
+                return Some(j);
+            }
+        }
+        None
+    }
+
+    #[inline(always)]
+    fn check_valid_leaf(
+        &self,
+        current_root: Node,
+        leaf: Node,
+        proof: &mut [Node],
+        leaf_index: u32,
+        allow_inferred_proof: bool,
+    ) -> Result<bool, CMTError> {
+        let mask: usize = self.max_buffer_size - 1;
+        let (changelog_index, use_full_buffer) = match self.find_root_in_changelog(current_root) {
+            Some(matching_changelog_index) => (matching_changelog_index, false),
+            None => {
+                if allow_inferred_proof {
+                    solana_logging!("Failed to find root in change log -> replaying full buffer");
+                    (
+                        self.active_index().wrapping_sub(self.buffer_size() - 1) & mask as u64,
+                        true,
+                    )
+                } else {
+                    return Err(CMTError::RootNotFound);
+                }
+            }
+        };
+        let mut updatable_leaf_node = leaf;
+        let proof_leaf_unchanged = self.fast_forward_proof(
+            &mut updatable_leaf_node,
+            proof,
+            leaf_index,
+            changelog_index,
+            use_full_buffer,
+        );
+        if !proof_leaf_unchanged {
+            return Err(CMTError::LeafContentsModified);
+        }
+        Ok(H::recompute(updatable_leaf_node, proof, leaf_index) == *self.get_change_log().root)
+    }
+}
+
+/// Mutable view of a merkle roll whose dimensions are only known at runtime.
+///
+/// Produces the same bytes as the equivalent `MerkleRoll<MAX_DEPTH, MAX_BUFFER_SIZE>` operations.
+pub struct MerkleRollMut<'a, H: Hasher = Keccak> {
+    max_depth: usize,
+    max_buffer_size: usize,
+    data: &'a mut [u8],
+    _hasher: PhantomData<H>,
+}
+
+impl<'a> MerkleRollMut<'a> {
+    /// Fails if the dimensions are unsupported or if `data` is not exactly
+    /// `merkle_roll_size(max_depth, max_buffer_size)` bytes long
+    pub fn new(
+        data: &'a mut [u8],
+        max_depth: usize,
+        max_buffer_size: usize,
+    ) -> Result<Self, CMTError> {
+        Self::with_hasher(data, max_depth, max_buffer_size)
+    }
+}
+
+impl<'a, H: Hasher> MerkleRollMut<'a, H> {
+    /// Same as `new`, for a tree built with the hasher `H`
+    pub fn with_hasher(
+        data: &'a mut [u8],
+        max_depth: usize,
+        max_buffer_size: usize,
+    ) -> Result<Self, CMTError> {
+        check_dimensions(data.len(), max_depth, max_buffer_size)?;
+        Ok(Self {
+            max_depth,
+            max_buffer_size,
+            data,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Reborrows this view as a read-only view
+    pub fn as_ref(&self) -> MerkleRollRef<'_, H> {
+        MerkleRollRef {
+            max_depth: self.max_depth,
+            max_buffer_size: self.max_buffer_size,
+            data: self.data,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn max_buffer_size(&self) -> usize {
+        self.max_buffer_size
+    }
+
+    pub fn sequence_number(&self) -> u64 {
+        self.as_ref().sequence_number()
+    }
+
+    pub fn active_index(&self) -> u64 {
+        self.as_ref().active_index()
+    }
+
+    pub fn buffer_size(&self) -> u64 {
+        self.as_ref().buffer_size()
+    }
+
+    pub fn get_change_log(&self) -> ChangeLogRef<'_> {
+        self.as_ref().get_change_log()
+    }
+
+    pub fn get_prior_change_log(&self, offset: u64) -> ChangeLogRef<'_> {
+        self.as_ref().get_prior_change_log(offset)
+    }
+
+    /// See [MerkleRollRef::changes_for_leaf]
+    pub fn changes_for_leaf(&self, leaf_index: u32) -> impl Iterator<Item = (u64, Node)> + '_ {
+        self.as_ref().changes_for_leaf(leaf_index)
+    }
+
+    /// See [MerkleRollRef::fast_forward]
+    pub fn fast_forward(
+        &self,
+        current_root: Node,
+        leaf: Node,
+        proof_vec: &[Node],
+        leaf_index: u32,
+    ) -> Result<(Node, Vec<Node>), CMTError> {
+        self.as_ref()
+            .fast_forward(current_root, leaf, proof_vec, leaf_index)
+    }
+
+    pub fn rightmost_proof(&self) -> PathRef<'_> {
+        self.as_ref().rightmost_proof()
+    }
+
+    fn set_sequence_number(&mut self, sequence_number: u64) {
+        write_u64(self.data, 0, sequence_number);
+    }
+
+    fn set_active_index(&mut self, active_index: u64) {
+        write_u64(self.data, 8, active_index);
+    }
+
+    fn set_buffer_size(&mut self, buffer_size: u64) {
+        write_u64(self.data, 16, buffer_size);
+    }
+
+    /// Overwrites the root, path and index of the change log at `buffer_index`
+    fn set_change_log(&mut self, buffer_index: usize, root: &Node, path: &[Node], index: u32) {
+        let offset = COUNTERS_SIZE + buffer_index * change_log_size(self.max_depth);
+        let change_log_bytes = &mut self.data[offset..offset + change_log_size(self.max_depth)];
+        let (root_bytes, rest) = change_log_bytes.split_at_mut(size_of::<Node>());
+        let (path_bytes, rest) = rest.split_at_mut(size_of::<Node>() * self.max_depth);
+        root_bytes.copy_from_slice(root);
+        cast_slice_mut::<u8, Node>(path_bytes).copy_from_slice(path);
+        write_u32(rest, 0, index);
+    }
+
+    fn set_rightmost_proof(&mut self, proof: &[Node], leaf: &Node, index: u32) {
+        let offset = COUNTERS_SIZE + self.max_buffer_size * change_log_size(self.max_depth);
+        let path_bytes = &mut self.data[offset..offset + path_size(self.max_depth)];
+        let (proof_bytes, rest) = path_bytes.split_at_mut(size_of::<Node>() * self.max_depth);
+        let (leaf_bytes, rest) = rest.split_at_mut(size_of::<Node>());
+        cast_slice_mut::<u8, Node>(proof_bytes).copy_from_slice(proof);
+        leaf_bytes.copy_from_slice(leaf);
+        write_u32(rest, 0, index);
+    }
+
+    pub fn initialize(&mut self) -> Result<Node, CMTError> {
+        let mut path = vec![Node::default(); self.max_depth];
+        let mut empty_node = EMPTY;
+        for node in path.iter_mut() {
+            *node = empty_node;
+            let sibling = empty_node;
+            H::hash_to_parent(&mut empty_node, &sibling, true);
+        }
+        let root = empty_node;
+        let path_index = self.as_ref().change_log(0).index;
+        self.set_change_log(0, &root, &path, path_index);
+        self.set_sequence_number(0);
+        self.set_active_index(0);
+        self.set_buffer_size(1);
+        self.set_rightmost_proof(&path, &EMPTY, 0);
+        Ok(root)
+    }
+
+    pub fn initialize_with_root(
+        &mut self,
+        root: Node,
+        rightmost_leaf: Node,
+        proof_vec: &[Node],
+        index: u32,
+    ) -> Result<Node, CMTError> {
+        if index >> self.max_depth != 0 {
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        if proof_vec.len() != self.max_depth
+            || root != H::recompute(rightmost_leaf, proof_vec, index)
+        {
+            return Err(CMTError::InvalidProof);
+        }
+        let change_log = self.as_ref().change_log(0);
+        let (path, path_index) = (change_log.path.to_vec(), change_log.index);
+        self.set_change_log(0, &root, &path, path_index);
+        self.set_sequence_number(1);
+        self.set_active_index(0);
+        self.set_buffer_size(1);
+        self.set_rightmost_proof(proof_vec, &rightmost_leaf, index + 1);
+        Ok(root)
+    }
+
+    pub fn prove_leaf(
+        &self,
+        current_root: Node,
+        leaf: Node,
+        proof_vec: &[Node],
+        leaf_index: u32,
+    ) -> Result<Node, CMTError> {
+        self.as_ref()
+            .prove_leaf(current_root, leaf, proof_vec, leaf_index)
+    }
+
+    pub fn prove_leaves(
+        &self,
+        current_root: Node,
+        leaves: &[Node],
+        multiproof: &MultiProof,
+    ) -> Result<Node, CMTError> {
+        self.as_ref().prove_leaves(current_root, leaves, multiproof)
+    }
+
+    /// Only used to initialize right most path for a completely empty tree
+    #[inline(always)]
+    fn initialize_tree_from_append(
+        &mut self,
+        leaf: Node,
+        mut proof: Vec<Node>,
+    ) -> Result<Node, CMTError> {
+        let old_root = H::recompute(EMPTY, &proof, 0);
+        if old_root == H::empty_node(self.max_depth as u32) {
+            self.try_apply_proof(old_root, EMPTY, leaf, &mut proof, 0, false)
+        } else {
+            Err(CMTError::TreeAlreadyInitialized)
+        }
+    }
+
+    /// Basic operation that always succeeds
+    pub fn append(&mut self, node: Node) -> Result<Node, CMTError> {
+        if node == EMPTY {
+            return Err(CMTError::CannotAppendEmptyNode);
+        }
+        let rightmost_index = self.rightmost_proof().index;
+        if rightmost_index >= 1 << self.max_depth {
+            return Err(CMTError::TreeFull);
+        }
+        if rightmost_index == 0 {
+            let proof = self.rightmost_proof().proof.to_vec();
+            return self.initialize_tree_from_append(node, proof);
+        }
+        let intersection = rightmost_index.trailing_zeros() as usize;
+        let empty_nodes = H::empty_nodes(intersection);
+        Ok(self.append_to_rightmost_path(node, &empty_nodes))
+    }
+
+    /// Appends `leaves` in order, writing one change log per leaf.
+    ///
+    /// The resulting state is identical to calling `append` on each leaf. Every change log
+    /// holds the full path and root after its leaf, so each leaf still costs at least `max_depth`
+    /// hashes: the batch only saves the per call overhead, e.g. the empty node hashes are
+    /// computed once. The batch is validated up front, so either all leaves are appended
+    /// or the tree is left unchanged.
+    pub fn append_batch(&mut self, leaves: &[Node]) -> Result<Node, CMTError> {
+        if leaves.contains(&EMPTY) {
+            return Err(CMTError::CannotAppendEmptyNode);
+        }
+        if self.rightmost_proof().index as u64 + leaves.len() as u64 > 1 << self.max_depth {
+            return Err(CMTError::TreeFull);
+        }
+        let mut root = *self.get_change_log().root;
+        let mut remaining_leaves = leaves;
+        if self.rightmost_proof().index == 0 {
+            if let Some((first_leaf, rest)) = leaves.split_first() {
+                let proof = self.rightmost_proof().proof.to_vec();
+                root = self.initialize_tree_from_append(*first_leaf, proof)?;
+                remaining_leaves = rest;
+            }
+        }
+        let empty_nodes = H::empty_nodes(self.max_depth);
+        for leaf in remaining_leaves {
+            root = self.append_to_rightmost_path(*leaf, &empty_nodes);
+        }
+        Ok(root)
+    }
+
+    /// Appends `node` to the right of the rightmost leaf and returns the new root.
+    /// Expects the tree to be non-empty and not full, and `empty_nodes` to hold
+    /// the empty node for every level below the intersection with the rightmost path.
+    #[inline(always)]
+    fn append_to_rightmost_path(&mut self, mut node: Node, empty_nodes: &[Node]) -> Node {
+        let leaf = node;
+        let rightmost_proof = self.rightmost_proof();
+        let rightmost_index = rightmost_proof.index;
+        let mut proof = rightmost_proof.proof.to_vec();
+        let mut intersection_node = *rightmost_proof.leaf;
+        let intersection = rightmost_index.trailing_zeros() as usize;
+        let mut change_list = vec![EMPTY; self.max_depth];
+
+        for (i, change) in change_list.iter_mut().enumerate() {
+            *change = node;
+            if i < intersection {
+                // Compute proof to the appended node from empty nodes
+                let sibling = empty_nodes[i];
+                H::hash_to_parent(
+                    &mut intersection_node,
+                    &proof[i],
+                    ((rightmost_index - 1) >> i) & 1 == 0,
+                );
+                H::hash_to_parent(&mut node, &sibling, true);
+                proof[i] = sibling;
+            } else if i == intersection {
+                // Compute the where the new node intersects the main tree
+                H::hash_to_parent(&mut node, &intersection_node, false);
+                proof[intersection] = intersection_node;
+            } else {
+                // Update the change list path up to the root
+                H::hash_to_parent(&mut node, &proof[i], ((rightmost_index - 1) >> i) & 1 == 0);
+            }
+        }
+
+        self.update_internal_counters();
+        let active_index = self.active_index() as usize;
+        self.set_change_log(active_index, &node, &change_list, rightmost_index);
+        self.set_rightmost_proof(&proof, &leaf, rightmost_index + 1);
+        node
+    }
+
+    /// Convenience function for `set_leaf`
+    /// On write conflict:
+    /// Will append
+    pub fn fill_empty_or_append(
+        &mut self,
+        current_root: Node,
+        leaf: Node,
+        proof_vec: &[Node],
+        index: u32,
+    ) -> Result<Node, CMTError> {
+        let mut proof = vec![Node::default(); self.max_depth];
+        fill_in_proof::<H>(proof_vec, &mut proof);
+        log_compute!();
+        let root = match self.try_apply_proof(current_root, EMPTY, leaf, &mut proof, index, false) {
+            Ok(new_root) => Ok(new_root),
+            Err(error) => match error {
+                CMTError::LeafContentsModified => self.append(leaf),
+                _ => Err(error),
+            },
+        };
+        log_compute!();
+        root
+    }
+
+    /// On write conflict:
+    /// Will fail by returning None
+    pub fn set_leaf(
+        &mut self,
+        current_root: Node,
+        previous_leaf: Node,
+        new_leaf: Node,
+        proof_vec: &[Node],
+        index: u32,
+    ) -> Result<Node, CMTError> {
+        if index > self.rightmost_proof().index {
+            Err(CMTError::LeafIndexOutOfBounds)
+        } else {
+            let mut proof = vec![Node::default(); self.max_depth];
+            fill_in_proof::<H>(proof_vec, &mut proof);
+            log_compute!();
+            let root = self.try_apply_proof(
+                current_root,
+                previous_leaf,
+                new_leaf,
+                &mut proof,
+                index,
+                true,
+            );
+            log_compute!();
+            root
+        }
+    }
+
+    /// Replaces several leaves using a single multiproof, writing one change log per leaf.
+    ///
+    /// `leaves` holds `(previous_leaf, new_leaf, index)` tuples sorted by strictly increasing
+    /// index, and `proof_vec` is the multiproof of the previous leaves against `current_root`
+    /// (see [multiproof](crate::multiproof)). The result is identical to calling `set_leaf`
+    /// once per leaf with an up-to-date proof. Every leaf is validated before any change log
+    /// is written, so either all leaves are replaced or the tree is left unchanged.
+    pub fn set_leaves(
+        &mut self,
+        current_root: Node,
+        leaves: &[(Node, Node, u32)],
+        proof_vec: &[Node],
+    ) -> Result<Node, CMTError> {
+        let rightmost_index = self.rightmost_proof().index;
+        if leaves.iter().any(|(_, _, index)| *index > rightmost_index) {
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        let previous_leaves: Vec<(u32, Node)> = leaves
+            .iter()
+            .map(|(previous_leaf, _, index)| (*index, *previous_leaf))
+            .collect();
+        let mut proofs = expand_multiproof::<H>(&previous_leaves, proof_vec, self.max_depth)?;
+        log_compute!();
+        for ((previous_leaf, _, index), proof) in leaves.iter().zip(proofs.iter_mut()) {
+            // Fast-forwards every proof to the current root
+            let valid_root = self.as_ref().check_valid_leaf(
+                current_root,
+                *previous_leaf,
+                proof,
+                *index,
+                true,
+            )?;
+            if !valid_root {
+                return Err(CMTError::InvalidProof);
+            }
+        }
+        let mut root = *self.get_change_log().root;
+        for (i, (_, new_leaf, index)) in leaves.iter().enumerate() {
+            self.update_internal_counters();
+            root = self.update_buffers_from_proof(*new_leaf, &proofs[i], *index);
+            // Brings the proofs of the remaining leaves up to date with the new change log
+            let change_log = self.as_ref().get_change_log();
+            for ((previous_leaf, _, index), proof) in
+                leaves.iter().zip(proofs.iter_mut()).skip(i + 1)
+            {
+                let mut leaf = *previous_leaf;
+                change_log.update_proof_or_leaf(*index, proof, &mut leaf);
+            }
+        }
+        log_compute!();
+        Ok(root)
+    }
+
+    /// Note: Enabling `allow_inferred_proof` will fast forward the given proof
+    /// from the beginning of the buffer in the case that the supplied root is not in the buffer.
+    #[inline(always)]
+    fn try_apply_proof(
+        &mut self,
+        current_root: Node,
+        leaf: Node,
+        new_leaf: Node,
+        proof: &mut [Node],
+        leaf_index: u32,
+        allow_inferred_proof: bool,
+    ) -> Result<Node, CMTError> {
+        solana_logging!("Active Index: {}", self.active_index());
+        solana_logging!("Rightmost Index: {}", self.rightmost_proof().index);
+        solana_logging!("Buffer Size: {}", self.buffer_size());
+        solana_logging!("Leaf Index: {}", leaf_index);
+        // Leaves past the rightmost leaf can only be written by appending
+        if leaf_index > self.rightmost_proof().index {
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        let valid_root = self.as_ref().check_valid_leaf(
+            current_root,
+            leaf,
+            proof,
+            leaf_index,
+            allow_inferred_proof,
+        )?;
+        if !valid_root {
+            return Err(CMTError::InvalidProof);
+        }
+        self.update_internal_counters();
+        Ok(self.update_buffers_from_proof(new_leaf, proof, leaf_index))
+    }
+
+    /// Implements circular addition for changelog buffer index
+    fn update_internal_counters(&mut self) {
+        let mask: usize = self.max_buffer_size - 1;
+        let active_index = (self.active_index() + 1) & mask as u64;
+        self.set_active_index(active_index);
+        if self.buffer_size() < self.max_buffer_size as u64 {
+            let buffer_size = self.buffer_size() + 1;
+            self.set_buffer_size(buffer_size);
+        }
+        let sequence_number = self.sequence_number().saturating_add(1);
+        self.set_sequence_number(sequence_number);
+    }
+
+    /// Creates a new root from a proof that is valid for the root at `self.active_index`
+    fn update_buffers_from_proof(&mut self, start: Node, proof: &[Node], index: u32) -> Node {
+        let active_index = self.active_index() as usize;
+        // Recompute the path from the new leaf up to the root
+        let mut path = vec![EMPTY; self.max_depth];
+        let mut node = start;
+        for (i, sibling) in proof.iter().enumerate() {
+            path[i] = node;
+            H::hash_to_parent(&mut node, sibling, index >> i & 1 == 0);
+        }
+        let root = node;
+        self.set_change_log(active_index, &root, &path, index);
+
+        // Update rightmost path if possible
+        let rightmost_proof = self.rightmost_proof();
+        if rightmost_proof.index < (1 << self.max_depth) {
+            if index < rightmost_proof.index {
+                let mut rightmost_leaf = *rightmost_proof.leaf;
+                let mut rightmost_path = rightmost_proof.proof.to_vec();
+                let rightmost_index = rightmost_proof.index;
+                self.as_ref().change_log(active_index).update_proof_or_leaf(
+                    rightmost_index - 1,
+                    &mut rightmost_path,
+                    &mut rightmost_leaf,
+                );
+                self.set_rightmost_proof(&rightmost_path, &rightmost_leaf, rightmost_index);
+            } else {
+                // Callers reject indices past the rightmost leaf, so `index` is the next leaf
+                solana_logging!("Appending rightmost leaf");
+                self.set_rightmost_proof(proof, &path[0], index + 1);
+            }
+        }
+        root
+    }
+}
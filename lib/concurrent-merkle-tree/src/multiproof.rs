@@ -0,0 +1,195 @@
+//! Proofs for several leaves of the same tree that share their interior nodes.
+//!
+//! A multiproof for a set of leaves holds every sibling node needed to recompute the root
+//! that cannot be derived from the leaves themselves. Nodes are ordered level by level from
+//! the leaves up, and by increasing node index within a level. Node indices follow
+//! `PathNode::index`: the root has index 1 and the children of node `i` are `2i` and `2i + 1`.
+use crate::{error::CMTError, hasher::Hasher, merkle_roll_view::MAX_SUPPORTED_DEPTH, state::Node};
+
+fn check_max_depth(max_depth: usize) -> Result<(), CMTError> {
+    if max_depth > MAX_SUPPORTED_DEPTH {
+        return Err(CMTError::UnsupportedMerkleRollConstants);
+    }
+    Ok(())
+}
+
+/// Checks that `leaf_indices` are strictly increasing and fit in a tree of `max_depth`
+fn check_leaf_indices(leaf_indices: &[u32], max_depth: usize) -> Result<(), CMTError> {
+    check_max_depth(max_depth)?;
+    for (i, leaf_index) in leaf_indices.iter().enumerate() {
+        if (*leaf_index as u64) >> max_depth != 0 {
+            return Err(CMTError::LeafIndexOutOfBounds);
+        }
+        if i > 0 && leaf_indices[i - 1] >= *leaf_index {
+            return Err(CMTError::InvalidMultiProof);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the node indices of the multiproof nodes for `leaf_indices`, in multiproof order
+pub fn multiproof_node_indices(
+    leaf_indices: &[u32],
+    max_depth: usize,
+) -> Result<Vec<u32>, CMTError> {
+    check_leaf_indices(leaf_indices, max_depth)?;
+    let mut node_indices = vec![];
+    let mut level: Vec<u32> = leaf_indices
+        .iter()
+        .map(|leaf_index| (1 << max_depth) + leaf_index)
+        .collect();
+    for _ in 0..max_depth {
+        let mut parents = Vec::with_capacity(level.len());
+        let mut k = 0;
+        while k < level.len() {
+            let node_index = level[k];
+            if node_index & 1 == 0 && level.get(k + 1) == Some(&(node_index + 1)) {
+                k += 2;
+            } else {
+                node_indices.push(node_index ^ 1);
+                k += 1;
+            }
+            parents.push(node_index >> 1);
+        }
+        level = parents;
+    }
+    Ok(node_indices)
+}
+
+/// Proof for several leaves of the same tree, see the [module docs](self) for the node order
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiProof {
+    /// Indices of the proven leaves, strictly increasing
+    pub leaf_indices: Vec<u32>,
+    /// Siblings that cannot be computed from the proven leaves
+    pub nodes: Vec<Node>,
+}
+
+impl MultiProof {
+    pub fn new(leaf_indices: Vec<u32>, nodes: Vec<Node>) -> Self {
+        Self {
+            leaf_indices,
+            nodes,
+        }
+    }
+
+    /// Recomputes the root of a tree of `max_depth` built with `H` from `leaves`,
+    /// given in the same order as `leaf_indices`
+    pub fn compute_root<H: Hasher>(
+        &self,
+        leaves: &[Node],
+        max_depth: usize,
+    ) -> Result<Node, CMTError> {
+        let leaves = self.zip_leaves(leaves)?;
+        hash_multiproof::<H>(&leaves, &self.nodes, max_depth, None)?
+            .ok_or(CMTError::InvalidMultiProof)
+    }
+
+    /// Checks that `leaves` are in the tree with root `root` at `leaf_indices`
+    pub fn verify<H: Hasher>(
+        &self,
+        root: Node,
+        leaves: &[Node],
+        max_depth: usize,
+    ) -> Result<(), CMTError> {
+        if self.compute_root::<H>(leaves, max_depth)? != root {
+            return Err(CMTError::InvalidProof);
+        }
+        Ok(())
+    }
+
+    /// Expands the multiproof into one proof per leaf, see [expand_multiproof]
+    pub fn expand<H: Hasher>(
+        &self,
+        leaves: &[Node],
+        max_depth: usize,
+    ) -> Result<Vec<Vec<Node>>, CMTError> {
+        expand_multiproof::<H>(&self.zip_leaves(leaves)?, &self.nodes, max_depth)
+    }
+
+    fn zip_leaves(&self, leaves: &[Node]) -> Result<Vec<(u32, Node)>, CMTError> {
+        if leaves.len() != self.leaf_indices.len() {
+            return Err(CMTError::InvalidMultiProof);
+        }
+        Ok(self
+            .leaf_indices
+            .iter()
+            .copied()
+            .zip(leaves.iter().copied())
+            .collect())
+    }
+}
+
+/// Expands the multiproof `nodes` for `leaves`, given as `(leaf_index, leaf)` pairs sorted by
+/// strictly increasing index, into one proof per leaf ordered from the leaf up.
+///
+/// Fails if `nodes` does not hold exactly the nodes needed for a tree of `max_depth`.
+pub fn expand_multiproof<H: Hasher>(
+    leaves: &[(u32, Node)],
+    nodes: &[Node],
+    max_depth: usize,
+) -> Result<Vec<Vec<Node>>, CMTError> {
+    check_max_depth(max_depth)?;
+    let mut proofs = vec![Vec::with_capacity(max_depth); leaves.len()];
+    hash_multiproof::<H>(leaves, nodes, max_depth, Some(&mut proofs))?;
+    Ok(proofs)
+}
+
+/// Hashes `leaves` up to the root with the multiproof `nodes`, pushing the sibling at every
+/// level to the proof of each leaf if `proofs` is set. Returns `None` if there are no leaves.
+fn hash_multiproof<H: Hasher>(
+    leaves: &[(u32, Node)],
+    nodes: &[Node],
+    max_depth: usize,
+    mut proofs: Option<&mut Vec<Vec<Node>>>,
+) -> Result<Option<Node>, CMTError> {
+    let leaf_indices: Vec<u32> = leaves.iter().map(|(leaf_index, _)| *leaf_index).collect();
+    check_leaf_indices(&leaf_indices, max_depth)?;
+    let mut nodes = nodes.iter();
+    // Known nodes of the current level as (node index, node, range of leaves below it)
+    let mut level: Vec<(u32, Node, usize, usize)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, (leaf_index, leaf))| ((1 << max_depth) + leaf_index, *leaf, i, i + 1))
+        .collect();
+    for _ in 0..max_depth {
+        let mut parents = Vec::with_capacity(level.len());
+        let mut k = 0;
+        while k < level.len() {
+            let (node_index, mut node, start, mut end) = level[k];
+            match level.get(k + 1) {
+                Some((right_index, right, right_start, right_end))
+                    if node_index & 1 == 0 && *right_index == node_index + 1 =>
+                {
+                    if let Some(proofs) = proofs.as_deref_mut() {
+                        for proof in proofs[start..end].iter_mut() {
+                            proof.push(*right);
+                        }
+                        for proof in proofs[*right_start..*right_end].iter_mut() {
+                            proof.push(node);
+                        }
+                    }
+                    H::hash_to_parent(&mut node, right, true);
+                    end = *right_end;
+                    k += 2;
+                }
+                _ => {
+                    let sibling = nodes.next().ok_or(CMTError::InvalidMultiProof)?;
+                    if let Some(proofs) = proofs.as_deref_mut() {
+                        for proof in proofs[start..end].iter_mut() {
+                            proof.push(*sibling);
+                        }
+                    }
+                    H::hash_to_parent(&mut node, sibling, node_index & 1 == 0);
+                    k += 1;
+                }
+            }
+            parents.push((node_index >> 1, node, start, end));
+        }
+        level = parents;
+    }
+    if nodes.next().is_some() {
+        return Err(CMTError::InvalidMultiProof);
+    }
+    Ok(level.first().map(|(_, root, _, _)| *root))
+}
@@ -0,0 +1,222 @@
+//! Off-chain replay of the changelogs emitted by a merkle roll.
+//!
+//! Every modification to a merkle roll emits the new path from the modified leaf
+//! to the root along with the roll's sequence number. Applying those changelogs in
+//! order to a [TreeReplayer] rebuilds the full tree, which can then serve proofs for
+//! any leaf and be checked against the on-chain [MerkleRollRef].
+use crate::{
+    error::ReplayError,
+    hasher::{Hasher, Keccak},
+    merkle_roll_view::{MerkleRollRef, MAX_SUPPORTED_DEPTH},
+    state::Node,
+};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Node of a changelog path, keyed by its position in the tree.
+/// The root has index 1 and the children of node `i` have indices `2i` and `2i + 1`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathNode {
+    pub node: Node,
+    pub index: u32,
+}
+
+/// Changelog emitted after a single modification of a merkle roll.
+/// Mirrors the `ChangeLogEvent` emitted by gummyroll, without the tree id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeLogRecord {
+    /// Nodes from the modified leaf up to and including the root
+    pub path: Vec<PathNode>,
+    /// Sequence number of the merkle roll after the modification
+    pub seq: u64,
+    /// Index of the modified leaf
+    pub index: u32,
+}
+
+impl ChangeLogRecord {
+    /// Builds a record from a changelog's root and path (leaf first, root excluded)
+    pub fn new(root: Node, changelog_path: &[Node], index: u32, seq: u64) -> Self {
+        let path_len = changelog_path.len() as u32;
+        let mut path: Vec<PathNode> = changelog_path
+            .iter()
+            .enumerate()
+            .map(|(lvl, n)| PathNode {
+                node: *n,
+                index: (1 << (path_len - lvl as u32)) + (index >> lvl),
+            })
+            .collect();
+        path.push(PathNode {
+            node: root,
+            index: 1,
+        });
+        Self { path, seq, index }
+    }
+}
+
+/// In-memory tree rebuilt from an ordered stream of changelogs.
+///
+/// Nodes that were never written are implied to be empty, so the memory used is
+/// proportional to the number of changelogs applied rather than the tree capacity.
+pub struct TreeReplayer<H: Hasher = Keccak> {
+    max_depth: usize,
+    /// Sequence number of the last applied changelog
+    sequence_number: Option<u64>,
+    nodes: HashMap<u32, Node>,
+    /// Empty node for every level, from the leaves up to the root
+    empty_nodes: Vec<Node>,
+    _hasher: PhantomData<H>,
+}
+
+impl TreeReplayer {
+    pub fn new(max_depth: usize) -> Result<Self, ReplayError> {
+        Self::with_hasher(max_depth)
+    }
+}
+
+impl<H: Hasher> TreeReplayer<H> {
+    /// Same as `new`, for a tree built with the hasher `H`
+    pub fn with_hasher(max_depth: usize) -> Result<Self, ReplayError> {
+        if max_depth > MAX_SUPPORTED_DEPTH {
+            return Err(ReplayError::UnsupportedDepth);
+        }
+        Ok(Self {
+            max_depth,
+            sequence_number: None,
+            nodes: HashMap::new(),
+            empty_nodes: H::empty_nodes(max_depth + 1),
+            _hasher: PhantomData,
+        })
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sequence number of the last applied changelog, if any
+    pub fn sequence_number(&self) -> Option<u64> {
+        self.sequence_number
+    }
+
+    /// Sequence number of the next changelog to apply
+    pub fn next_sequence_number(&self) -> u64 {
+        self.sequence_number.map_or(0, |seq| seq + 1)
+    }
+
+    pub fn get_root(&self) -> Node {
+        self.get_node(1)
+    }
+
+    pub fn get_leaf(&self, leaf_index: u32) -> Result<Node, ReplayError> {
+        Ok(self.get_node(self.leaf_node_index(leaf_index)?))
+    }
+
+    /// Returns the proof for `leaf_index` against the current root, ordered from the leaf up
+    pub fn get_proof(&self, leaf_index: u32) -> Result<Vec<Node>, ReplayError> {
+        let mut node_index = self.leaf_node_index(leaf_index)?;
+        let mut proof = Vec::with_capacity(self.max_depth);
+        while node_index > 1 {
+            proof.push(self.get_node(node_index ^ 1));
+            node_index >>= 1;
+        }
+        Ok(proof)
+    }
+
+    fn leaf_node_index(&self, leaf_index: u32) -> Result<u32, ReplayError> {
+        if leaf_index as u64 >= 1 << self.max_depth {
+            return Err(ReplayError::LeafIndexOutOfBounds);
+        }
+        Ok((1 << self.max_depth) + leaf_index)
+    }
+
+    fn get_node(&self, node_index: u32) -> Node {
+        match self.nodes.get(&node_index) {
+            Some(node) => *node,
+            None => {
+                let level = self.max_depth - (31 - node_index.leading_zeros()) as usize;
+                self.empty_nodes[level]
+            }
+        }
+    }
+
+    /// Applies the next changelog in the stream.
+    ///
+    /// Fails without modifying the tree if `record.seq` is not the next expected sequence
+    /// number, or if the path does not hash up to its root given the current tree.
+    /// The latter means that a changelog was dropped or that the record is corrupt.
+    pub fn apply(&mut self, record: &ChangeLogRecord) -> Result<Node, ReplayError> {
+        validate_change_log::<H, _>(
+            record,
+            self.max_depth,
+            self.next_sequence_number(),
+            |index| Ok::<_, ReplayError>(self.get_node(index)),
+        )?;
+        for path_node in record.path.iter() {
+            self.nodes.insert(path_node.index, path_node.node);
+        }
+        self.sequence_number = Some(record.seq);
+        Ok(self.get_root())
+    }
+
+    /// Checks that the rebuilt root matches the root that `merkle_roll` recorded
+    /// at the same sequence number. That root must still be in the changelog buffer.
+    pub fn verify_root(&self, merkle_roll: &MerkleRollRef<H>) -> Result<(), ReplayError> {
+        if merkle_roll.max_depth() != self.max_depth {
+            return Err(ReplayError::UnsupportedDepth);
+        }
+        let sequence_number = self.sequence_number.unwrap_or(0);
+        let offset = match merkle_roll.sequence_number().checked_sub(sequence_number) {
+            Some(offset) if offset < merkle_roll.buffer_size() => offset,
+            _ => return Err(ReplayError::RootNotInBuffer),
+        };
+        if *merkle_roll.get_prior_change_log(offset).root != self.get_root() {
+            return Err(ReplayError::RootMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `record` is the changelog with sequence number `next_seq` for a tree of
+/// `max_depth` built with `H`, and that its path hashes up to its root given the siblings
+/// returned by `get_node`.
+///
+/// `get_node` is called with the node index (see [PathNode]) of every sibling along the path.
+pub fn validate_change_log<H: Hasher, E: From<ReplayError>>(
+    record: &ChangeLogRecord,
+    max_depth: usize,
+    next_seq: u64,
+    mut get_node: impl FnMut(u32) -> Result<Node, E>,
+) -> Result<(), E> {
+    if record.seq > next_seq {
+        return Err(ReplayError::SequenceGap {
+            expected: next_seq,
+            received: record.seq,
+        }
+        .into());
+    }
+    if record.seq < next_seq {
+        return Err(ReplayError::StaleSequenceNumber {
+            expected: next_seq,
+            received: record.seq,
+        }
+        .into());
+    }
+    if record.path.len() != max_depth + 1 || record.index >> max_depth != 0 {
+        return Err(ReplayError::InvalidChangeLog.into());
+    }
+    let mut node_index = (1 << max_depth) + record.index;
+    for (lvl, path_node) in record.path.iter().enumerate() {
+        if path_node.index != node_index {
+            return Err(ReplayError::InvalidChangeLog.into());
+        }
+        if lvl < max_depth {
+            let mut parent = path_node.node;
+            let sibling = get_node(node_index ^ 1)?;
+            H::hash_to_parent(&mut parent, &sibling, node_index & 1 == 0);
+            if parent != record.path[lvl + 1].node {
+                return Err(ReplayError::InconsistentChangeLog.into());
+            }
+        }
+        node_index >>= 1;
+    }
+    Ok(())
+}
@@ -1,57 +1,104 @@
-use crate::state::{Node, EMPTY};
-use solana_program::{keccak::hashv, msg};
+use crate::{
+    error::CMTError,
+    hasher::{Hasher, Keccak},
+    merkle_roll_view::MAX_SUPPORTED_DEPTH,
+    state::{Node, EMPTY},
+};
+use solana_program::msg;
 
-/// Calculates hash of empty nodes up to level i
+/// Calculates hash of empty nodes up to level i, using [Keccak]
 pub fn empty_node(level: u32) -> Node {
-    empty_node_cached::<0>(level, &mut Box::new([]))
+    Keccak::empty_node(level)
 }
 
-/// Calculates hash of empty nodes up to level i
+/// Calculates hash of empty nodes up to level i, using [Keccak]
 pub fn empty_node_cached<const N: usize>(level: u32, cache: &mut Box<[Node; N]>) -> Node {
-    let mut data = EMPTY;
-    if level != 0 {
-        let target = (level - 1) as usize;
-        let lower_empty = if target < cache.len() && cache[target] != EMPTY {
-            cache[target]
-        } else {
-            empty_node(target as u32)
-        };
-        let hash = hashv(&[lower_empty.as_ref(), lower_empty.as_ref()]);
-        data.copy_from_slice(hash.as_ref());
-    }
-    data
+    Keccak::empty_node_cached(level, cache)
 }
 
-/// Recomputes root of the Merkle tree from Node & proof
+/// Returns the empty node hashes for levels `0..num_levels`, using [Keccak]
+pub fn empty_nodes(num_levels: usize) -> Vec<Node> {
+    Keccak::empty_nodes(num_levels)
+}
+
+/// Recomputes root of the Merkle tree from Node & proof, using [Keccak]
 pub fn recompute(leaf: Node, proof: &[Node], index: u32) -> Node {
-    let mut current_node = leaf;
-    for (depth, sibling) in proof.iter().enumerate() {
-        hash_to_parent(&mut current_node, sibling, index >> depth & 1 == 0);
-    }
-    current_node
+    Keccak::recompute(leaf, proof, index)
 }
 
-/// Computes the parent node of `node` and `sibling` and copies the result into `node`
+/// Computes the parent node of `node` and `sibling` and copies the result into `node`, using [Keccak]
 #[inline(always)]
 pub fn hash_to_parent(node: &mut Node, sibling: &Node, is_left: bool) {
-    let parent = if is_left {
-        hashv(&[node, sibling])
-    } else {
-        hashv(&[sibling, node])
-    };
-    node.copy_from_slice(parent.as_ref())
-}
-
-pub fn fill_in_proof<const MAX_DEPTH: usize>(
-    proof_vec: &Vec<Node>,
-    full_proof: &mut [Node; MAX_DEPTH],
-) {
+    Keccak::hash_to_parent(node, sibling, is_left)
+}
+
+pub fn fill_in_proof<H: Hasher>(proof_vec: &[Node], full_proof: &mut [Node]) {
     solana_logging!("Attempting to fill in proof");
     if proof_vec.len() > 0 {
-        full_proof[..proof_vec.len()].copy_from_slice(&proof_vec);
+        full_proof[..proof_vec.len()].copy_from_slice(proof_vec);
+    }
+
+    for i in proof_vec.len()..full_proof.len() {
+        full_proof[i] = H::empty_node(i as u32);
     }
+}
+
+/// Compresses `proof` by replacing every sibling that is an empty node with a bit in the returned bitmap.
+/// Bit `i` of the bitmap is set if the sibling at level `i` is `empty_node(i)`.
+/// The non-empty siblings are returned in order, from the leaf up.
+pub fn compress_proof<H: Hasher>(proof: &[Node]) -> (u32, Vec<Node>) {
+    let empty_nodes = H::empty_nodes(proof.len());
+    let mut empty_levels = 0;
+    let mut nodes = vec![];
+    for (level, node) in proof.iter().enumerate() {
+        if *node == empty_nodes[level] {
+            empty_levels |= 1 << level;
+        } else {
+            nodes.push(*node);
+        }
+    }
+    (empty_levels, nodes)
+}
 
-    for i in proof_vec.len()..MAX_DEPTH {
-        full_proof[i] = empty_node(i as u32);
+/// Expands a proof compressed with `compress_proof`.
+///
+/// Levels are filled from the leaf up until `nodes` runs out, so the returned proof may be
+/// shorter than `max_depth`; the missing top levels are then expected to come from the canopy
+/// or from `fill_in_proof`. A bitmap of 0 returns `nodes` unchanged.
+pub fn decompress_proof<H: Hasher>(
+    empty_levels: u32,
+    nodes: &[Node],
+    max_depth: usize,
+) -> Result<Vec<Node>, CMTError> {
+    if max_depth > MAX_SUPPORTED_DEPTH {
+        return Err(CMTError::UnsupportedMerkleRollConstants);
+    }
+    if nodes.len() > max_depth || empty_levels >> max_depth != 0 {
+        solana_logging!("Compressed proof is larger than max depth {}", max_depth);
+        return Err(CMTError::InvalidCompressedProof);
+    }
+    let mut proof = Vec::with_capacity(max_depth);
+    let mut remaining_nodes = nodes.iter();
+    let mut empty_node = EMPTY;
+    for level in 0..max_depth {
+        if empty_levels >> level & 1 == 1 {
+            proof.push(empty_node);
+        } else if let Some(node) = remaining_nodes.next() {
+            proof.push(*node);
+        } else if empty_levels >> level != 0 {
+            solana_logging!("Compressed proof marks empty levels above level {}", level);
+            return Err(CMTError::InvalidCompressedProof);
+        } else {
+            break;
+        }
+        // Empty nodes are only needed up to the highest empty level
+        if empty_levels >> (level + 1) != 0 {
+            let sibling = empty_node;
+            H::hash_to_parent(&mut empty_node, &sibling, true);
+        }
+    }
+    if remaining_nodes.next().is_some() {
+        return Err(CMTError::InvalidCompressedProof);
     }
+    Ok(proof)
 }
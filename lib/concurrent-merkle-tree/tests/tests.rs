@@ -1,6 +1,11 @@
-use concurrent_merkle_tree::error::CMTError;
+use concurrent_merkle_tree::error::{CMTError, ReplayError};
+use concurrent_merkle_tree::hasher::{DomainSeparated, Hasher, Keccak};
 use concurrent_merkle_tree::merkle_roll::MerkleRoll;
+use concurrent_merkle_tree::merkle_roll_view::{merkle_roll_size, MerkleRollMut, MerkleRollRef};
+use concurrent_merkle_tree::multiproof::{multiproof_node_indices, MultiProof};
+use concurrent_merkle_tree::replay::{ChangeLogRecord, TreeReplayer};
 use concurrent_merkle_tree::state::{Node, EMPTY};
+use concurrent_merkle_tree::utils::{compress_proof, decompress_proof, empty_node};
 use merkle_tree_reference::MerkleTree;
 use rand::thread_rng;
 use rand::{self, Rng};
@@ -90,7 +95,7 @@ async fn test_prove_leaf() {
     let num_leaves_to_try = 10;
     for _ in 0..num_leaves_to_try {
         let leaf_idx = rng.gen_range(0, 1 << DEPTH);
-        let last_leaf_idx = off_chain_tree.leaf_nodes.len() - 1;
+        let last_leaf_idx = off_chain_tree.num_leaves() - 1;
         let root = off_chain_tree.get_root();
         let leaf = off_chain_tree.get_leaf(leaf_idx);
         let old_proof = off_chain_tree.get_proof_of_leaf(leaf_idx);
@@ -131,7 +136,7 @@ async fn test_initialize_with_root() {
         tree.add_leaf(rng.gen::<[u8; 32]>(), i);
     }
 
-    let last_leaf_idx = tree.leaf_nodes.len() - 1;
+    let last_leaf_idx = tree.num_leaves() - 1;
     merkle_roll
         .initialize_with_root(
             tree.get_root(),
@@ -148,6 +153,63 @@ async fn test_initialize_with_root() {
     );
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn test_initialize_with_root_invalid_proof() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+
+    for i in 0..(1 << DEPTH) {
+        tree.add_leaf(rng.gen::<[u8; 32]>(), i);
+    }
+
+    let last_leaf_idx = tree.num_leaves() - 1;
+    let last_leaf = tree.get_leaf(last_leaf_idx);
+    let proof = tree.get_proof_of_leaf(last_leaf_idx);
+
+    // Root that does not match the proof
+    let res = merkle_roll.initialize_with_root(
+        rng.gen::<Node>(),
+        last_leaf,
+        &proof,
+        last_leaf_idx as u32,
+    );
+    assert!(matches!(res, Err(CMTError::InvalidProof)));
+
+    // Truncated proof
+    let res = merkle_roll.initialize_with_root(
+        tree.get_root(),
+        last_leaf,
+        &proof[..DEPTH - 1].to_vec(),
+        last_leaf_idx as u32,
+    );
+    assert!(matches!(res, Err(CMTError::InvalidProof)));
+
+    // Index outside of the tree
+    let res = merkle_roll.initialize_with_root(tree.get_root(), last_leaf, &proof, 1 << DEPTH);
+    assert!(matches!(res, Err(CMTError::LeafIndexOutOfBounds)));
+
+    assert_eq!(merkle_roll.sequence_number, 0);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_fill_empty_past_rightmost_leaf() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    let leaf = rng.gen::<[u8; 32]>();
+    tree.add_leaf(leaf, 0);
+    merkle_roll.append(leaf).unwrap();
+
+    // Leaf 2 is empty, but leaf 1 has not been appended yet
+    let root = tree.get_root();
+    let res =
+        merkle_roll.fill_empty_or_append(root, rng.gen::<Node>(), &tree.get_proof_of_leaf(2), 2);
+    assert!(matches!(res, Err(CMTError::LeafIndexOutOfBounds)));
+    assert_eq!(merkle_roll.get_change_log().root, root);
+    assert_eq!(merkle_roll.rightmost_proof.index, 1);
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn test_leaf_contents_modified() {
     let (mut merkle_roll, mut tree) = setup();
@@ -190,6 +252,117 @@ async fn test_leaf_contents_modified() {
     }
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn test_changes_for_leaf() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    // The change log written by `initialize` points at leaf 0 but is not a change
+    assert_eq!(merkle_roll.changes_for_leaf(0).count(), 0);
+    assert_eq!(
+        merkle_roll.as_view().unwrap().changes_for_leaf(0).count(),
+        0
+    );
+
+    let leaves: Vec<Node> = (0..4).map(|_| rng.gen::<Node>()).collect();
+    for (i, leaf) in leaves.iter().enumerate() {
+        merkle_roll.append(*leaf).unwrap();
+        tree.add_leaf(*leaf, i);
+    }
+
+    // Replace leaf 2 twice
+    let mut expected_changes = vec![(3, leaves[2])];
+    let mut previous_leaf = leaves[2];
+    for _ in 0..2 {
+        let new_leaf = rng.gen::<Node>();
+        merkle_roll
+            .set_leaf(
+                tree.get_root(),
+                previous_leaf,
+                new_leaf,
+                &tree.get_proof_of_leaf(2),
+                2,
+            )
+            .unwrap();
+        tree.add_leaf(new_leaf, 2);
+        expected_changes.push((merkle_roll.sequence_number, new_leaf));
+        previous_leaf = new_leaf;
+    }
+
+    let changes: Vec<(u64, Node)> = merkle_roll.changes_for_leaf(2).collect();
+    assert_eq!(changes, expected_changes);
+    let view_changes: Vec<(u64, Node)> =
+        merkle_roll.as_view().unwrap().changes_for_leaf(2).collect();
+    assert_eq!(view_changes, expected_changes);
+
+    let changes: Vec<(u64, Node)> = merkle_roll.changes_for_leaf(0).collect();
+    assert_eq!(changes, vec![(1, leaves[0])]);
+    assert_eq!(merkle_roll.changes_for_leaf(5).count(), 0);
+
+    // Changes drop out of the window once the buffer wraps around
+    for _ in 0..BUFFER_SIZE {
+        merkle_roll.append(rng.gen::<Node>()).unwrap();
+    }
+    assert_eq!(merkle_roll.changes_for_leaf(2).count(), 0);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_fast_forward() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    for i in 0..8 {
+        let leaf = rng.gen::<Node>();
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+
+    // Save a proof of leaf 3, then modify the other leaves
+    let stale_root = tree.get_root();
+    let stale_proof = tree.get_proof_of_leaf(3);
+    for i in [0, 2, 5, 7] {
+        let new_leaf = rng.gen::<Node>();
+        merkle_roll
+            .set_leaf(
+                tree.get_root(),
+                tree.get_leaf(i),
+                new_leaf,
+                &tree.get_proof_of_leaf(i),
+                i as u32,
+            )
+            .unwrap();
+        tree.add_leaf(new_leaf, i);
+    }
+
+    let (root, proof) = merkle_roll
+        .fast_forward(stale_root, tree.get_leaf(3), &stale_proof, 3)
+        .unwrap();
+    assert_eq!(root, tree.get_root());
+    assert_eq!(proof, tree.get_proof_of_leaf(3));
+    assert_eq!(
+        merkle_roll
+            .as_view()
+            .unwrap()
+            .fast_forward(stale_root, tree.get_leaf(3), &stale_proof, 3)
+            .unwrap(),
+        (root, proof.clone())
+    );
+
+    // The fast-forwarded proof is valid against the current root
+    let leaf = tree.get_leaf(3);
+    let new_leaf = rng.gen::<Node>();
+    merkle_roll
+        .set_leaf(root, leaf, new_leaf, &proof, 3)
+        .unwrap();
+    tree.add_leaf(new_leaf, 3);
+
+    // Leaf 3 has been modified since the stale root
+    let res = merkle_roll.fast_forward(stale_root, leaf, &stale_proof, 3);
+    assert!(matches!(res, Err(CMTError::LeafContentsModified)));
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn test_replaces() {
     let (mut merkle_roll, mut tree) = setup();
@@ -397,3 +570,907 @@ async fn test_append_bug_repro_2() {
     last_rmp = merkle_roll.rightmost_proof;
     assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
 }
+
+#[tokio::test(threaded_scheduler)]
+/// Batched appends should leave the tree in the same state as sequential appends
+async fn test_append_batch() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut sequential_merkle_roll = MerkleRoll::<DEPTH, BUFFER_SIZE>::new();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+    sequential_merkle_roll.initialize().unwrap();
+
+    let tree_capacity: usize = 1 << DEPTH;
+    let mut tree_size = 0;
+    while tree_size < tree_capacity {
+        let batch_size = rng
+            .gen_range(1, BUFFER_SIZE + 1)
+            .min(tree_capacity - tree_size);
+        let leaves: Vec<Node> = (0..batch_size).map(|_| rng.gen::<Node>()).collect();
+
+        let root = merkle_roll.append_batch(&leaves).unwrap();
+        for (i, leaf) in leaves.iter().enumerate() {
+            sequential_merkle_roll.append(*leaf).unwrap();
+            tree.add_leaf(*leaf, tree_size + i);
+        }
+        tree_size += batch_size;
+
+        assert_eq!(root, tree.get_root());
+        assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+        assert_eq!(
+            merkle_roll.sequence_number,
+            sequential_merkle_roll.sequence_number
+        );
+        assert_eq!(
+            merkle_roll.active_index,
+            sequential_merkle_roll.active_index
+        );
+        assert_eq!(merkle_roll.buffer_size, sequential_merkle_roll.buffer_size);
+        assert_eq!(
+            merkle_roll.rightmost_proof,
+            sequential_merkle_roll.rightmost_proof
+        );
+        assert_eq!(
+            merkle_roll.change_logs, sequential_merkle_roll.change_logs,
+            "Batch append should write one change log per leaf"
+        );
+    }
+
+    assert!(matches!(
+        merkle_roll.append_batch(&[rng.gen::<Node>()]),
+        Err(CMTError::TreeFull)
+    ));
+}
+
+#[tokio::test(threaded_scheduler)]
+/// Proofs issued before a batch append should still be fast-forwarded
+async fn test_append_batch_fast_forward() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    let tree_size = 10;
+    for i in 0..tree_size {
+        let leaf = rng.gen::<Node>();
+        tree.add_leaf(leaf, i);
+        merkle_roll.append(leaf).unwrap();
+    }
+
+    let leaf_idx = rng.gen_range(0, tree_size);
+    let root = tree.get_root();
+    let leaf = tree.get_leaf(leaf_idx);
+    let old_proof = tree.get_proof_of_leaf(leaf_idx);
+
+    let leaves: Vec<Node> = (0..BUFFER_SIZE / 2).map(|_| rng.gen::<Node>()).collect();
+    merkle_roll.append_batch(&leaves).unwrap();
+    for (i, batch_leaf) in leaves.iter().enumerate() {
+        tree.add_leaf(*batch_leaf, tree_size + i);
+    }
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+
+    let new_leaf = rng.gen::<Node>();
+    merkle_roll
+        .set_leaf(root, leaf, new_leaf, &old_proof, leaf_idx as u32)
+        .unwrap();
+    tree.add_leaf(new_leaf, leaf_idx);
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+}
+
+#[tokio::test(threaded_scheduler)]
+/// Invalid batches should not modify the tree
+async fn test_append_batch_is_atomic() {
+    let (mut merkle_roll, tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    let mut leaves: Vec<Node> = (0..4).map(|_| rng.gen::<Node>()).collect();
+    leaves.push(EMPTY);
+    assert!(matches!(
+        merkle_roll.append_batch(&leaves),
+        Err(CMTError::CannotAppendEmptyNode)
+    ));
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+    assert_eq!(merkle_roll.rightmost_proof.index, 0);
+    assert_eq!(merkle_roll.sequence_number, 0);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_view_layout_matches_merkle_roll() {
+    assert_eq!(
+        merkle_roll_size(DEPTH, BUFFER_SIZE).unwrap(),
+        std::mem::size_of::<MerkleRoll<DEPTH, BUFFER_SIZE>>()
+    );
+    assert_eq!(
+        merkle_roll_size(3, 8).unwrap(),
+        std::mem::size_of::<MerkleRoll<3, 8>>()
+    );
+    assert_eq!(
+        merkle_roll_size(30, 2048).unwrap(),
+        std::mem::size_of::<MerkleRoll<30, 2048>>()
+    );
+
+    let (mut merkle_roll, _) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+    for _ in 0..(BUFFER_SIZE + 3) {
+        merkle_roll.append(rng.gen::<Node>()).unwrap();
+    }
+    let view = MerkleRollRef::new(bytemuck::bytes_of(&merkle_roll), DEPTH, BUFFER_SIZE).unwrap();
+    assert_eq!(view.sequence_number(), merkle_roll.sequence_number);
+    assert_eq!(view.active_index(), merkle_roll.active_index);
+    assert_eq!(view.buffer_size(), merkle_roll.buffer_size);
+    for (i, change_log) in merkle_roll.change_logs.iter().enumerate() {
+        let change_log_ref = view.change_log(i);
+        assert_eq!(*change_log_ref.root, change_log.root);
+        assert_eq!(change_log_ref.path, &change_log.path[..]);
+        assert_eq!(change_log_ref.index, change_log.index);
+    }
+    let rightmost_proof = view.rightmost_proof();
+    assert_eq!(
+        rightmost_proof.proof,
+        &merkle_roll.rightmost_proof.proof[..]
+    );
+    assert_eq!(*rightmost_proof.leaf, merkle_roll.rightmost_proof.leaf);
+    assert_eq!(rightmost_proof.index, merkle_roll.rightmost_proof.index);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_view_matches_merkle_roll() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    let mut bytes = vec![0_u8; merkle_roll_size(DEPTH, BUFFER_SIZE).unwrap()];
+    let mut view = MerkleRollMut::new(&mut bytes, DEPTH, BUFFER_SIZE).unwrap();
+    merkle_roll.initialize().unwrap();
+    view.initialize().unwrap();
+    assert_eq!(bytemuck::bytes_of(&merkle_roll), view.as_ref().data());
+
+    let mut tree_size = 0;
+    for _ in 0..(4 * BUFFER_SIZE) {
+        let leaf = rng.gen::<Node>();
+        let random_num: u32 = rng.gen_range(0, 10);
+        if tree_size == 0 || random_num < 3 {
+            merkle_roll.append(leaf).unwrap();
+            view.append(leaf).unwrap();
+            tree.add_leaf(leaf, tree_size);
+            tree_size += 1;
+        } else if random_num < 5 {
+            let leaves: Vec<Node> = (0..rng.gen_range(1, 8))
+                .map(|_| rng.gen::<Node>())
+                .collect();
+            merkle_roll.append_batch(&leaves).unwrap();
+            view.append_batch(&leaves).unwrap();
+            for leaf in leaves {
+                tree.add_leaf(leaf, tree_size);
+                tree_size += 1;
+            }
+        } else {
+            let index = rng.gen_range(0, tree_size);
+            let proof = tree.get_proof_of_leaf(index);
+            merkle_roll
+                .set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &proof,
+                    index as u32,
+                )
+                .unwrap();
+            view.set_leaf(
+                tree.get_root(),
+                tree.get_leaf(index),
+                leaf,
+                &proof,
+                index as u32,
+            )
+            .unwrap();
+            tree.add_leaf(leaf, index);
+        }
+        assert_eq!(*view.get_change_log().root, tree.get_root());
+        assert_eq!(bytemuck::bytes_of(&merkle_roll), view.as_ref().data());
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_view_runtime_dimensions() {
+    let mut rng = thread_rng();
+    // Dimensions that are not instantiated as a MerkleRoll anywhere
+    for (max_depth, max_buffer_size) in [(5, 4), (9, 2), (11, 32)] {
+        let mut tree = MerkleTree::new(vec![EMPTY; 1 << max_depth]);
+        let mut bytes = vec![0_u8; merkle_roll_size(max_depth, max_buffer_size).unwrap()];
+        let mut view = MerkleRollMut::new(&mut bytes, max_depth, max_buffer_size).unwrap();
+        assert_eq!(view.initialize().unwrap(), tree.get_root());
+
+        let capacity: usize = 1 << max_depth;
+        let mut tree_size = 0;
+        while tree_size < capacity {
+            let leaf = rng.gen::<Node>();
+            if tree_size == 0 || rng.gen_range(0, 2) == 0 {
+                view.append(leaf).unwrap();
+                tree.add_leaf(leaf, tree_size);
+                tree_size += 1;
+            } else {
+                let index = rng.gen_range(0, tree_size);
+                view.set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &tree.get_proof_of_leaf(index),
+                    index as u32,
+                )
+                .unwrap();
+                tree.add_leaf(leaf, index);
+            }
+            assert_eq!(*view.get_change_log().root, tree.get_root());
+        }
+        assert!(matches!(
+            view.append(rng.gen::<Node>()),
+            Err(CMTError::TreeFull)
+        ));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_view_rejects_invalid_dimensions() {
+    assert!(matches!(
+        merkle_roll_size(0, 64),
+        Err(CMTError::UnsupportedMerkleRollConstants)
+    ));
+    assert!(matches!(
+        merkle_roll_size(31, 64),
+        Err(CMTError::UnsupportedMerkleRollConstants)
+    ));
+    assert!(matches!(
+        merkle_roll_size(14, 0),
+        Err(CMTError::UnsupportedMerkleRollConstants)
+    ));
+    assert!(matches!(
+        merkle_roll_size(14, 48),
+        Err(CMTError::UnsupportedMerkleRollConstants)
+    ));
+
+    let mut bytes = vec![0_u8; merkle_roll_size(DEPTH, BUFFER_SIZE).unwrap() - 1];
+    assert!(matches!(
+        MerkleRollMut::new(&mut bytes, DEPTH, BUFFER_SIZE),
+        Err(CMTError::InvalidMerkleRollByteLength)
+    ));
+    assert!(matches!(
+        MerkleRollRef::new(&bytes, DEPTH, BUFFER_SIZE / 2),
+        Err(CMTError::InvalidMerkleRollByteLength)
+    ));
+}
+
+fn change_log_record(merkle_roll: &MerkleRoll<DEPTH, BUFFER_SIZE>, offset: u64) -> ChangeLogRecord {
+    let change_log = merkle_roll.get_prior_change_log(offset);
+    ChangeLogRecord::new(
+        change_log.root,
+        &change_log.path,
+        change_log.index,
+        merkle_roll.sequence_number - offset,
+    )
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_replay() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    let mut replayer = TreeReplayer::new(DEPTH).unwrap();
+    merkle_roll.initialize().unwrap();
+    replayer.apply(&change_log_record(&merkle_roll, 0)).unwrap();
+    assert_eq!(replayer.get_root(), tree.get_root());
+
+    let mut tree_size = 0;
+    for _ in 0..(4 * BUFFER_SIZE) {
+        let leaf = rng.gen::<Node>();
+        let num_logs = if tree_size == 0 || rng.gen_range(0, 10) < 3 {
+            merkle_roll.append(leaf).unwrap();
+            tree.add_leaf(leaf, tree_size);
+            tree_size += 1;
+            1
+        } else if rng.gen_range(0, 10) < 3 {
+            let leaves: Vec<Node> = (0..rng.gen_range(1, 8))
+                .map(|_| rng.gen::<Node>())
+                .collect();
+            merkle_roll.append_batch(&leaves).unwrap();
+            for leaf in leaves.iter() {
+                tree.add_leaf(*leaf, tree_size);
+                tree_size += 1;
+            }
+            leaves.len() as u64
+        } else {
+            let index = rng.gen_range(0, tree_size);
+            merkle_roll
+                .set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &tree.get_proof_of_leaf(index),
+                    index as u32,
+                )
+                .unwrap();
+            tree.add_leaf(leaf, index);
+            1
+        };
+        let view = merkle_roll.as_view().unwrap();
+        for offset in (0..num_logs).rev() {
+            replayer
+                .apply(&change_log_record(&merkle_roll, offset))
+                .unwrap();
+            replayer.verify_root(&view).unwrap();
+        }
+        assert_eq!(replayer.get_root(), tree.get_root());
+        assert_eq!(
+            replayer.sequence_number(),
+            Some(merkle_roll.sequence_number)
+        );
+    }
+
+    for i in 0..tree_size {
+        assert_eq!(replayer.get_leaf(i as u32).unwrap(), tree.get_leaf(i));
+        assert_eq!(
+            replayer.get_proof(i as u32).unwrap(),
+            tree.get_proof_of_leaf(i)
+        );
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_replay_rejects_out_of_bounds_leaves() {
+    let replayer = TreeReplayer::new(DEPTH).unwrap();
+    assert!(replayer.get_leaf((1 << DEPTH) - 1).is_ok());
+    for leaf_index in [1 << DEPTH, u32::MAX] {
+        assert!(matches!(
+            replayer.get_leaf(leaf_index),
+            Err(ReplayError::LeafIndexOutOfBounds)
+        ));
+        assert!(matches!(
+            replayer.get_proof(leaf_index),
+            Err(ReplayError::LeafIndexOutOfBounds)
+        ));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_replay_detects_gaps() {
+    let (mut merkle_roll, _) = setup();
+    let mut rng = thread_rng();
+    let mut replayer = TreeReplayer::new(DEPTH).unwrap();
+    merkle_roll.initialize().unwrap();
+    replayer.apply(&change_log_record(&merkle_roll, 0)).unwrap();
+
+    merkle_roll.append(rng.gen::<Node>()).unwrap();
+    let first = change_log_record(&merkle_roll, 0);
+    merkle_roll.append(rng.gen::<Node>()).unwrap();
+    let second = change_log_record(&merkle_roll, 0);
+
+    assert!(matches!(
+        replayer.apply(&second),
+        Err(ReplayError::SequenceGap {
+            expected: 1,
+            received: 2
+        })
+    ));
+    // Forging the sequence number still fails since the path does not hash to its root
+    let mut forged = second.clone();
+    forged.seq = 1;
+    assert!(matches!(
+        replayer.apply(&forged),
+        Err(ReplayError::InconsistentChangeLog)
+    ));
+    assert_eq!(replayer.sequence_number(), Some(0));
+
+    replayer.apply(&first).unwrap();
+    assert!(matches!(
+        replayer.apply(&first),
+        Err(ReplayError::StaleSequenceNumber {
+            expected: 2,
+            received: 1
+        })
+    ));
+    replayer.apply(&second).unwrap();
+    replayer
+        .verify_root(&merkle_roll.as_view().unwrap())
+        .unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_max_depth_tree() {
+    const MAX_DEPTH: usize = 30;
+    let mut rng = thread_rng();
+    let mut tree = MerkleTree::new_empty(MAX_DEPTH);
+    let mut bytes = vec![0_u8; merkle_roll_size(MAX_DEPTH, BUFFER_SIZE).unwrap()];
+    let mut merkle_roll = MerkleRollMut::new(&mut bytes, MAX_DEPTH, BUFFER_SIZE).unwrap();
+    assert_eq!(merkle_roll.initialize().unwrap(), tree.get_root());
+
+    let mut tree_size = 0;
+    for _ in 0..(4 * BUFFER_SIZE) {
+        let leaf = rng.gen::<Node>();
+        if tree_size == 0 || rng.gen_range(0, 2) == 0 {
+            merkle_roll.append(leaf).unwrap();
+            tree.add_leaf(leaf, tree_size);
+            tree_size += 1;
+        } else {
+            let index = rng.gen_range(0, tree_size);
+            merkle_roll
+                .set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &tree.get_proof_of_leaf(index),
+                    index as u32,
+                )
+                .unwrap();
+            tree.add_leaf(leaf, index);
+        }
+        assert_eq!(*merkle_roll.get_change_log().root, tree.get_root());
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_compressed_proofs() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    // A sparsely populated tree has mostly empty siblings
+    let num_leaves = 10;
+    for i in 0..num_leaves {
+        let leaf = rng.gen::<Node>();
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+    for i in 0..num_leaves {
+        let proof = tree.get_proof_of_leaf(i);
+        let (empty_levels, nodes) = compress_proof::<Keccak>(&proof);
+        assert!(nodes.len() <= 4);
+        assert_eq!(
+            decompress_proof::<Keccak>(empty_levels, &nodes, DEPTH).unwrap(),
+            proof
+        );
+
+        let leaf = rng.gen::<Node>();
+        let decompressed = decompress_proof::<Keccak>(empty_levels, &nodes, DEPTH).unwrap();
+        merkle_roll
+            .set_leaf(
+                tree.get_root(),
+                tree.get_leaf(i),
+                leaf,
+                &decompressed,
+                i as u32,
+            )
+            .unwrap();
+        tree.add_leaf(leaf, i);
+        assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+    }
+
+    // Truncated proofs stop at the first level without a node
+    let proof = tree.get_proof_of_leaf(0);
+    let (empty_levels, nodes) = compress_proof::<Keccak>(&proof[..4]);
+    assert_eq!(
+        decompress_proof::<Keccak>(empty_levels, &nodes, DEPTH).unwrap(),
+        proof[..4].to_vec()
+    );
+    assert_eq!(
+        decompress_proof::<Keccak>(0, &proof[..3], DEPTH).unwrap(),
+        proof[..3].to_vec()
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_invalid_compressed_proofs() {
+    let mut rng = thread_rng();
+    let nodes: Vec<Node> = (0..3).map(|_| rng.gen::<Node>()).collect();
+    // Bitmap marks a level deeper than the tree
+    assert!(matches!(
+        decompress_proof::<Keccak>(1 << DEPTH, &nodes, DEPTH),
+        Err(CMTError::InvalidCompressedProof)
+    ));
+    // Bitmap marks a level above the last node
+    assert!(matches!(
+        decompress_proof::<Keccak>(1 << 5, &nodes, DEPTH),
+        Err(CMTError::InvalidCompressedProof)
+    ));
+    // More nodes than levels left after the empty ones
+    assert!(matches!(
+        decompress_proof::<Keccak>(0b11, &nodes, 4),
+        Err(CMTError::InvalidCompressedProof)
+    ));
+    assert_eq!(
+        decompress_proof::<Keccak>(0b1010, &nodes[..2], 4).unwrap(),
+        vec![nodes[0], empty_node(1), nodes[1], empty_node(3)]
+    );
+}
+
+fn get_multiproof(tree: &MerkleTree, leaf_indices: &[u32]) -> Vec<Node> {
+    let leaf_indices: Vec<usize> = leaf_indices.iter().map(|i| *i as usize).collect();
+    tree.get_multiproof(&leaf_indices)
+}
+
+/// Returns between 1 and `max_leaves` random leaf indices below `tree_size`, sorted and deduplicated
+fn random_leaf_indices(tree_size: usize, max_leaves: usize) -> Vec<u32> {
+    let mut rng = thread_rng();
+    let mut indices: Vec<u32> = (0..rng.gen_range(1, max_leaves + 1))
+        .map(|_| rng.gen_range(0, tree_size) as u32)
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_replace_leaves() {
+    let (mut merkle_roll, mut tree) = setup();
+    let (mut sequential_roll, _) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+    sequential_roll.initialize().unwrap();
+
+    let tree_size = 256;
+    let leaves: Vec<Node> = (0..tree_size).map(|_| rng.gen::<Node>()).collect();
+    merkle_roll.append_batch(&leaves).unwrap();
+    sequential_roll.append_batch(&leaves).unwrap();
+    for (i, leaf) in leaves.into_iter().enumerate() {
+        tree.add_leaf(leaf, i);
+    }
+
+    for _ in 0..100 {
+        let indices = random_leaf_indices(tree_size, 16);
+        let proof = get_multiproof(&tree, &indices);
+        let root = tree.get_root();
+        let replacements: Vec<(Node, Node, u32)> = indices
+            .iter()
+            .map(|index| (tree.get_leaf(*index as usize), rng.gen::<Node>(), *index))
+            .collect();
+        merkle_roll.set_leaves(root, &replacements, &proof).unwrap();
+
+        // Replacing the leaves one at a time with fresh proofs writes the same change logs
+        for (previous_leaf, new_leaf, index) in replacements {
+            sequential_roll
+                .set_leaf(
+                    tree.get_root(),
+                    previous_leaf,
+                    new_leaf,
+                    &tree.get_proof_of_leaf(index as usize),
+                    index,
+                )
+                .unwrap();
+            tree.add_leaf(new_leaf, index as usize);
+        }
+        assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+        assert_eq!(
+            bytemuck::bytes_of(&merkle_roll),
+            bytemuck::bytes_of(&sequential_roll)
+        );
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+/// Multiproofs against an older root are fast-forwarded like single proofs
+async fn test_replace_leaves_fast_forward() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    let tree_size = 64;
+    for i in 0..tree_size {
+        let leaf = rng.gen::<Node>();
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+
+    for _ in 0..50 {
+        let indices = random_leaf_indices(tree_size, 8);
+        let proof = get_multiproof(&tree, &indices);
+        let root = tree.get_root();
+        let replacements: Vec<(Node, Node, u32)> = indices
+            .iter()
+            .map(|index| (tree.get_leaf(*index as usize), rng.gen::<Node>(), *index))
+            .collect();
+
+        // Modify other leaves after the multiproof was generated
+        for _ in 0..rng.gen_range(0, 8) {
+            let index = rng.gen_range(0, tree_size);
+            if indices.contains(&(index as u32)) {
+                continue;
+            }
+            let leaf = rng.gen::<Node>();
+            merkle_roll
+                .set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &tree.get_proof_of_leaf(index),
+                    index as u32,
+                )
+                .unwrap();
+            tree.add_leaf(leaf, index);
+        }
+
+        merkle_roll.set_leaves(root, &replacements, &proof).unwrap();
+        for (_, new_leaf, index) in replacements {
+            tree.add_leaf(new_leaf, index as usize);
+        }
+        assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+/// Invalid multiproofs should not modify the tree
+async fn test_replace_leaves_is_atomic() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+
+    let tree_size = 32;
+    for i in 0..tree_size {
+        let leaf = rng.gen::<Node>();
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+    let bytes = bytemuck::bytes_of(&merkle_roll).to_vec();
+
+    let indices = vec![1, 6, 7, 20];
+    let proof = get_multiproof(&tree, &indices);
+    let mut replacements: Vec<(Node, Node, u32)> = indices
+        .iter()
+        .map(|index| (tree.get_leaf(*index as usize), rng.gen::<Node>(), *index))
+        .collect();
+
+    // The last leaf does not match the tree
+    replacements[3].0 = rng.gen::<Node>();
+    assert!(matches!(
+        merkle_roll.set_leaves(tree.get_root(), &replacements, &proof),
+        Err(CMTError::InvalidProof)
+    ));
+    replacements[3].0 = tree.get_leaf(20);
+
+    // Indices must be strictly increasing
+    replacements.swap(0, 1);
+    assert!(matches!(
+        merkle_roll.set_leaves(tree.get_root(), &replacements, &proof),
+        Err(CMTError::InvalidMultiProof)
+    ));
+    replacements.swap(0, 1);
+
+    // The multiproof must hold exactly the needed nodes
+    let mut long_proof = proof.clone();
+    long_proof.push(rng.gen::<Node>());
+    assert!(matches!(
+        merkle_roll.set_leaves(tree.get_root(), &replacements, &long_proof),
+        Err(CMTError::InvalidMultiProof)
+    ));
+    assert!(matches!(
+        merkle_roll.set_leaves(tree.get_root(), &replacements, &proof[1..]),
+        Err(CMTError::InvalidMultiProof)
+    ));
+    assert_eq!(bytemuck::bytes_of(&merkle_roll), &bytes[..]);
+
+    merkle_roll
+        .set_leaves(tree.get_root(), &replacements, &proof)
+        .unwrap();
+    for (_, new_leaf, index) in replacements {
+        tree.add_leaf(new_leaf, index as usize);
+    }
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_multiproof() {
+    let (_, mut tree) = setup();
+    let mut rng = thread_rng();
+    let tree_size = 512;
+    for i in 0..tree_size {
+        tree.add_leaf(rng.gen::<Node>(), i);
+    }
+
+    for _ in 0..100 {
+        let indices = random_leaf_indices(tree_size, 32);
+        let leaves: Vec<Node> = indices.iter().map(|i| tree.get_leaf(*i as usize)).collect();
+        let multiproof = MultiProof::new(indices.clone(), get_multiproof(&tree, &indices));
+        assert_eq!(
+            multiproof.nodes.len(),
+            multiproof_node_indices(&indices, DEPTH).unwrap().len()
+        );
+        // Shared siblings are only included once
+        assert!(multiproof.nodes.len() <= indices.len() * DEPTH);
+        assert_eq!(
+            multiproof.compute_root::<Keccak>(&leaves, DEPTH).unwrap(),
+            tree.get_root()
+        );
+        multiproof
+            .verify::<Keccak>(tree.get_root(), &leaves, DEPTH)
+            .unwrap();
+        let proofs = multiproof.expand::<Keccak>(&leaves, DEPTH).unwrap();
+        for (index, proof) in indices.iter().zip(proofs.iter()) {
+            assert_eq!(*proof, tree.get_proof_of_leaf(*index as usize));
+        }
+
+        let mut wrong_leaves = leaves.clone();
+        let i = rng.gen_range(0, wrong_leaves.len());
+        wrong_leaves[i] = rng.gen::<Node>();
+        assert!(matches!(
+            multiproof.verify::<Keccak>(tree.get_root(), &wrong_leaves, DEPTH),
+            Err(CMTError::InvalidProof)
+        ));
+        assert!(matches!(
+            multiproof.verify::<Keccak>(tree.get_root(), &leaves[1..], DEPTH),
+            Err(CMTError::InvalidMultiProof)
+        ));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_multiproof_rejects_unsupported_depth() {
+    let multiproof = MultiProof::new(vec![0, 1], vec![]);
+    let leaves = [[1; 32], [2; 32]];
+    for max_depth in [31, 32, 64, usize::MAX] {
+        assert!(matches!(
+            multiproof_node_indices(&multiproof.leaf_indices, max_depth),
+            Err(CMTError::UnsupportedMerkleRollConstants)
+        ));
+        assert!(matches!(
+            multiproof.compute_root::<Keccak>(&leaves, max_depth),
+            Err(CMTError::UnsupportedMerkleRollConstants)
+        ));
+        assert!(matches!(
+            multiproof.expand::<Keccak>(&leaves, max_depth),
+            Err(CMTError::UnsupportedMerkleRollConstants)
+        ));
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_prove_leaves() {
+    let (mut merkle_roll, mut tree) = setup();
+    let mut rng = thread_rng();
+    merkle_roll.initialize().unwrap();
+    let tree_size = 128;
+    for i in 0..tree_size {
+        let leaf = rng.gen::<Node>();
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+
+    let indices = vec![3, 4, 5, 64, 100];
+    let leaves: Vec<Node> = indices.iter().map(|i| tree.get_leaf(*i as usize)).collect();
+    let multiproof = MultiProof::new(indices.clone(), get_multiproof(&tree, &indices));
+    let root = tree.get_root();
+    merkle_roll
+        .prove_leaves(root, &leaves, &multiproof)
+        .unwrap();
+
+    // Modifying other leaves does not invalidate the multiproof
+    for index in [0, 6, 65, 127] {
+        let leaf = rng.gen::<Node>();
+        merkle_roll
+            .set_leaf(
+                tree.get_root(),
+                tree.get_leaf(index),
+                leaf,
+                &tree.get_proof_of_leaf(index),
+                index as u32,
+            )
+            .unwrap();
+        tree.add_leaf(leaf, index);
+    }
+    merkle_roll
+        .prove_leaves(root, &leaves, &multiproof)
+        .unwrap();
+
+    // Modifying a proven leaf does
+    let leaf = rng.gen::<Node>();
+    merkle_roll
+        .set_leaf(
+            tree.get_root(),
+            tree.get_leaf(64),
+            leaf,
+            &tree.get_proof_of_leaf(64),
+            64,
+        )
+        .unwrap();
+    assert!(matches!(
+        merkle_roll.prove_leaves(root, &leaves, &multiproof),
+        Err(CMTError::LeafContentsModified)
+    ));
+}
+
+/// Builds every level of a full tree of `leaves` with `H`, from the leaves up to the root
+fn hash_levels<H: Hasher>(leaves: &[Node]) -> Vec<Vec<Node>> {
+    let mut levels = vec![leaves.to_vec()];
+    while levels[levels.len() - 1].len() > 1 {
+        let level = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| H::hashv(&[&pair[0], &pair[1]]))
+            .collect();
+        levels.push(level);
+    }
+    levels
+}
+
+fn check_hasher<H: Hasher>() {
+    const SMALL_DEPTH: usize = 5;
+    let mut rng = thread_rng();
+    let mut merkle_roll = MerkleRoll::<SMALL_DEPTH, 8, H>::new();
+    merkle_roll.initialize().unwrap();
+    assert_eq!(
+        merkle_roll.get_change_log().root,
+        H::empty_node(SMALL_DEPTH as u32)
+    );
+
+    let mut leaves: Vec<Node> = (0..1 << SMALL_DEPTH).map(|_| rng.gen::<Node>()).collect();
+    merkle_roll.append_batch(&leaves).unwrap();
+    assert_eq!(
+        merkle_roll.get_change_log().root,
+        hash_levels::<H>(&leaves)[SMALL_DEPTH][0]
+    );
+
+    for i in 0..leaves.len() {
+        let levels = hash_levels::<H>(&leaves);
+        let proof: Vec<Node> = (0..SMALL_DEPTH)
+            .map(|level| levels[level][(i >> level) ^ 1])
+            .collect();
+        let leaf = rng.gen::<Node>();
+        merkle_roll
+            .set_leaf(levels[SMALL_DEPTH][0], leaves[i], leaf, &proof, i as u32)
+            .unwrap();
+        leaves[i] = leaf;
+    }
+    assert_eq!(
+        merkle_roll.get_change_log().root,
+        hash_levels::<H>(&leaves)[SMALL_DEPTH][0]
+    );
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_hashers() {
+    check_hasher::<Keccak>();
+    #[cfg(feature = "sha256")]
+    check_hasher::<concurrent_merkle_tree::hasher::Sha256>();
+    #[cfg(feature = "poseidon")]
+    check_hasher::<concurrent_merkle_tree::hasher::Poseidon>();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_domain_separated() {
+    let mut rng = thread_rng();
+    let mut merkle_roll = MerkleRoll::<DEPTH, BUFFER_SIZE, DomainSeparated<Keccak>>::new();
+    let mut tree = MerkleTree::new_empty_domain_separated(DEPTH);
+    merkle_roll.initialize().unwrap();
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+    assert_ne!(tree.get_root(), empty_node(DEPTH as u32));
+
+    let num_leaves = 1 << 8;
+    for i in 0..num_leaves {
+        let leaf = DomainSeparated::<Keccak>::hash_leaf(&[&rng.gen::<Node>()]);
+        merkle_roll.append(leaf).unwrap();
+        tree.add_leaf(leaf, i);
+    }
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+
+    for _ in 0..BUFFER_SIZE {
+        let index = rng.gen_range(0, num_leaves);
+        let data = rng.gen::<Node>();
+        let leaf = DomainSeparated::<Keccak>::hash_leaf(&[&data]);
+        assert_eq!(leaf, merkle_tree_reference::hash_leaf(&[&data]));
+        merkle_roll
+            .set_leaf(
+                tree.get_root(),
+                tree.get_leaf(index),
+                leaf,
+                &tree.get_proof_of_leaf(index),
+                index as u32,
+            )
+            .unwrap();
+        tree.add_leaf(leaf, index);
+    }
+    assert_eq!(merkle_roll.get_change_log().root, tree.get_root());
+
+    // The children of an internal node do not hash to that node as leaf data
+    let left = tree.get_leaf(0);
+    let right = tree.get_leaf(1);
+    let parent = DomainSeparated::<Keccak>::hash_nodes(&left, &right);
+    assert_eq!(parent, tree.get_proof_of_leaf(2)[1]);
+    assert_ne!(
+        parent,
+        DomainSeparated::<Keccak>::hash_leaf(&[&left, &right])
+    );
+}
@@ -0,0 +1,25 @@
+use concurrent_merkle_tree::error::ReplayError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProofStoreError {
+    /// Failed to read or write the node log
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Changelog could not be applied to the stored tree
+    #[error("Replay error: {0}")]
+    Replay(#[from] ReplayError),
+
+    /// Node log or snapshot does not start with the expected header
+    #[error("Invalid node log header")]
+    InvalidHeader,
+
+    /// Node log was created for a tree of a different depth
+    #[error("Max depth mismatch: stored {stored}, requested {requested}")]
+    DepthMismatch { stored: usize, requested: usize },
+
+    /// Received an index larger than the tree capacity
+    #[error("Leaf index out of bounds")]
+    LeafIndexOutOfBounds,
+}
@@ -0,0 +1,324 @@
+//! Disk-backed sparse merkle tree for serving proofs of trees maintained by a merkle roll.
+//!
+//! Nodes are keyed by their node index, as used in `PathNode::index`: the root has index 1
+//! and the children of node `i` have indices `2i` and `2i + 1`. Nodes that were never
+//! written are implied to be empty, so trees of any supported depth can be stored.
+//!
+//! The store is an append-only node log. Every applied changelog is written as one block,
+//! and an in-memory index maps each node index to the offset of its latest value in the log.
+//! Opening an existing store rebuilds that index, dropping any block that was only partially
+//! written. Since the log grows with every changelog, it can be rewritten with [ProofStore::compact].
+//!
+//! Node log format (all integers are little endian):
+//!
+//! | field     | size                         |
+//! | --------- | ---------------------------- |
+//! | magic     | 8 bytes, `CMTNODES`          |
+//! | max_depth | u32                          |
+//! | blocks    | until the end of the file    |
+//!
+//! Each block holds the sequence number of its changelog (`u64::MAX` if none),
+//! the number of nodes as a u64, then every node as a u32 node index followed by 32 bytes.
+//! Snapshots use the same format with a single block.
+use concurrent_merkle_tree::{
+    error::ReplayError,
+    hasher::{Hasher, Keccak},
+    merkle_roll_view::MAX_SUPPORTED_DEPTH,
+    replay::{validate_change_log, ChangeLogRecord},
+    state::Node,
+};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+pub mod error;
+
+use crate::error::ProofStoreError;
+
+const MAGIC: &[u8; 8] = b"CMTNODES";
+const HEADER_SIZE: u64 = 12;
+/// Sequence number and node count
+const BLOCK_HEADER_SIZE: u64 = 16;
+/// Node index and node
+const ENTRY_SIZE: u64 = (size_of::<u32>() + size_of::<Node>()) as u64;
+/// Stored in place of the sequence number when no changelog has been applied
+const NO_SEQUENCE_NUMBER: u64 = u64::MAX;
+const NODE_LOG_FILE: &str = "nodes.log";
+const COMPACTION_FILE: &str = "nodes.log.compact";
+
+/// Proof store for a tree built with the hasher `H`
+pub struct ProofStore<H: Hasher = Keccak> {
+    dir: PathBuf,
+    max_depth: usize,
+    /// Sequence number of the last applied changelog
+    sequence_number: Option<u64>,
+    file: File,
+    /// Length of the node log, where the next block is written
+    len: u64,
+    /// Offset of the latest value of every written node
+    offsets: HashMap<u32, u64>,
+    /// Empty node for every level, from the leaves up to the root
+    empty_nodes: Vec<Node>,
+    _hasher: PhantomData<H>,
+}
+
+impl ProofStore {
+    /// Opens the store in `dir`, creating it if it does not exist
+    pub fn open(dir: impl AsRef<Path>, max_depth: usize) -> Result<Self, ProofStoreError> {
+        Self::open_with_hasher(dir, max_depth)
+    }
+
+    /// Creates a store in `dir` from a snapshot written by [ProofStore::snapshot].
+    /// Any node log already in `dir` is replaced.
+    pub fn restore(
+        snapshot_path: impl AsRef<Path>,
+        dir: impl AsRef<Path>,
+    ) -> Result<Self, ProofStoreError> {
+        Self::restore_with_hasher(snapshot_path, dir)
+    }
+}
+
+impl<H: Hasher> ProofStore<H> {
+    /// Same as `open`, for a tree built with the hasher `H`.
+    /// The hasher is not recorded in the node log, so a store must always be opened with the same hasher.
+    pub fn open_with_hasher(
+        dir: impl AsRef<Path>,
+        max_depth: usize,
+    ) -> Result<Self, ProofStoreError> {
+        if max_depth > MAX_SUPPORTED_DEPTH {
+            return Err(ReplayError::UnsupportedDepth.into());
+        }
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(NODE_LOG_FILE))?;
+        let mut len = file.metadata()?.len();
+        if len == 0 {
+            let mut header = MAGIC.to_vec();
+            header.extend_from_slice(&(max_depth as u32).to_le_bytes());
+            file.write_all_at(&header, 0)?;
+            len = HEADER_SIZE;
+        } else {
+            let stored_depth = read_header(&mut &file)?;
+            if stored_depth != max_depth {
+                return Err(ProofStoreError::DepthMismatch {
+                    stored: stored_depth,
+                    requested: max_depth,
+                });
+            }
+        }
+
+        let mut store = Self {
+            dir,
+            max_depth,
+            sequence_number: None,
+            file,
+            len,
+            offsets: HashMap::new(),
+            empty_nodes: H::empty_nodes(max_depth + 1),
+            _hasher: PhantomData,
+        };
+        store.load_index()?;
+        Ok(store)
+    }
+
+    /// Same as `restore`, for a tree built with the hasher `H`
+    pub fn restore_with_hasher(
+        snapshot_path: impl AsRef<Path>,
+        dir: impl AsRef<Path>,
+    ) -> Result<Self, ProofStoreError> {
+        let max_depth = read_header(&mut File::open(snapshot_path.as_ref())?)?;
+        fs::create_dir_all(dir.as_ref())?;
+        fs::copy(snapshot_path, dir.as_ref().join(NODE_LOG_FILE))?;
+        Self::open_with_hasher(dir, max_depth)
+    }
+
+    /// Rebuilds the node index from the log, truncating a partially written last block
+    fn load_index(&mut self) -> Result<(), ProofStoreError> {
+        let mut reader = BufReader::new(&self.file);
+        let mut position = reader.seek(SeekFrom::Start(HEADER_SIZE))?;
+        let mut block_header = [0_u8; BLOCK_HEADER_SIZE as usize];
+        let mut entry = [0_u8; ENTRY_SIZE as usize];
+        while position + BLOCK_HEADER_SIZE <= self.len {
+            reader.read_exact(&mut block_header)?;
+            let seq = u64::from_le_bytes(block_header[..8].try_into().unwrap());
+            let num_nodes = u64::from_le_bytes(block_header[8..].try_into().unwrap());
+            let block_end = num_nodes
+                .checked_mul(ENTRY_SIZE)
+                .and_then(|size| size.checked_add(position + BLOCK_HEADER_SIZE));
+            match block_end {
+                Some(block_end) if block_end <= self.len => {
+                    for i in 0..num_nodes {
+                        reader.read_exact(&mut entry)?;
+                        let node_index = u32::from_le_bytes(entry[..4].try_into().unwrap());
+                        let offset = position + BLOCK_HEADER_SIZE + i * ENTRY_SIZE + 4;
+                        self.offsets.insert(node_index, offset);
+                    }
+                    if seq != NO_SEQUENCE_NUMBER {
+                        self.sequence_number = Some(seq);
+                    }
+                    position = block_end;
+                }
+                _ => break,
+            }
+        }
+        if position < self.len {
+            self.file.set_len(position)?;
+            self.len = position;
+        }
+        Ok(())
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sequence number of the last applied changelog, if any
+    pub fn sequence_number(&self) -> Option<u64> {
+        self.sequence_number
+    }
+
+    /// Sequence number of the next changelog to apply
+    pub fn next_sequence_number(&self) -> u64 {
+        self.sequence_number.map_or(0, |seq| seq + 1)
+    }
+
+    pub fn get_root(&self) -> Result<Node, ProofStoreError> {
+        self.get_node(1)
+    }
+
+    pub fn get_leaf(&self, leaf_index: u32) -> Result<Node, ProofStoreError> {
+        self.check_leaf_index(leaf_index)?;
+        self.get_node((1 << self.max_depth) + leaf_index)
+    }
+
+    /// Returns the proof for `leaf_index` against the current root, ordered from the leaf up
+    pub fn get_proof(&self, leaf_index: u32) -> Result<Vec<Node>, ProofStoreError> {
+        self.check_leaf_index(leaf_index)?;
+        let mut node_index = (1 << self.max_depth) + leaf_index;
+        let mut proof = Vec::with_capacity(self.max_depth);
+        while node_index > 1 {
+            proof.push(self.get_node(node_index ^ 1)?);
+            node_index >>= 1;
+        }
+        Ok(proof)
+    }
+
+    #[inline(always)]
+    fn check_leaf_index(&self, leaf_index: u32) -> Result<(), ProofStoreError> {
+        if leaf_index >> self.max_depth != 0 {
+            return Err(ProofStoreError::LeafIndexOutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn get_node(&self, node_index: u32) -> Result<Node, ProofStoreError> {
+        match self.offsets.get(&node_index) {
+            Some(offset) => {
+                let mut node = Node::default();
+                self.file.read_exact_at(&mut node, *offset)?;
+                Ok(node)
+            }
+            None => {
+                let level = self.max_depth - (31 - node_index.leading_zeros()) as usize;
+                Ok(self.empty_nodes[level])
+            }
+        }
+    }
+
+    /// Applies the next changelog emitted by the merkle roll.
+    ///
+    /// Fails without modifying the store if `record.seq` is not the next expected sequence
+    /// number, or if the path does not hash up to its root given the stored tree.
+    pub fn apply_changelog(&mut self, record: &ChangeLogRecord) -> Result<Node, ProofStoreError> {
+        validate_change_log::<H, _>(
+            record,
+            self.max_depth,
+            self.next_sequence_number(),
+            |index| self.get_node(index),
+        )?;
+        let mut block = Vec::with_capacity(
+            (BLOCK_HEADER_SIZE + ENTRY_SIZE * record.path.len() as u64) as usize,
+        );
+        block.extend_from_slice(&record.seq.to_le_bytes());
+        block.extend_from_slice(&(record.path.len() as u64).to_le_bytes());
+        for path_node in record.path.iter() {
+            block.extend_from_slice(&path_node.index.to_le_bytes());
+            block.extend_from_slice(&path_node.node);
+        }
+        self.file.write_all_at(&block, self.len)?;
+
+        for (i, path_node) in record.path.iter().enumerate() {
+            let offset = self.len + BLOCK_HEADER_SIZE + i as u64 * ENTRY_SIZE + 4;
+            self.offsets.insert(path_node.index, offset);
+        }
+        self.len += block.len() as u64;
+        self.sequence_number = Some(record.seq);
+        self.get_root()
+    }
+
+    /// Flushes the node log to disk
+    pub fn sync(&self) -> Result<(), ProofStoreError> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Writes the latest value of every stored node to `path`
+    pub fn snapshot(&self, path: impl AsRef<Path>) -> Result<(), ProofStoreError> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(&file);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.max_depth as u32).to_le_bytes())?;
+        writer.write_all(
+            &self
+                .sequence_number
+                .unwrap_or(NO_SEQUENCE_NUMBER)
+                .to_le_bytes(),
+        )?;
+        writer.write_all(&(self.offsets.len() as u64).to_le_bytes())?;
+        let mut node_indices: Vec<u32> = self.offsets.keys().copied().collect();
+        node_indices.sort_unstable();
+        for node_index in node_indices {
+            writer.write_all(&node_index.to_le_bytes())?;
+            writer.write_all(&self.get_node(node_index)?)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Rewrites the node log so that it only holds the latest value of every node
+    pub fn compact(&mut self) -> Result<(), ProofStoreError> {
+        let compaction_path = self.dir.join(COMPACTION_FILE);
+        self.snapshot(&compaction_path)?;
+        fs::rename(&compaction_path, self.dir.join(NODE_LOG_FILE))?;
+        *self = Self::open_with_hasher(&self.dir, self.max_depth)?;
+        Ok(())
+    }
+}
+
+/// Reads the node log header and returns the stored max depth
+fn read_header(reader: &mut impl Read) -> Result<usize, ProofStoreError> {
+    let mut header = [0_u8; HEADER_SIZE as usize];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| ProofStoreError::InvalidHeader)?;
+    if &header[..8] != MAGIC {
+        return Err(ProofStoreError::InvalidHeader);
+    }
+    let max_depth = u32::from_le_bytes(header[8..].try_into().unwrap()) as usize;
+    if max_depth > MAX_SUPPORTED_DEPTH {
+        return Err(ProofStoreError::InvalidHeader);
+    }
+    Ok(max_depth)
+}
@@ -0,0 +1,210 @@
+use concurrent_merkle_tree::error::ReplayError;
+use concurrent_merkle_tree::merkle_roll::MerkleRoll;
+use concurrent_merkle_tree::replay::ChangeLogRecord;
+use concurrent_merkle_tree::state::{Node, EMPTY};
+use merkle_proof_store::error::ProofStoreError;
+use merkle_proof_store::ProofStore;
+use merkle_tree_reference::MerkleTree;
+use rand::thread_rng;
+use rand::{self, Rng};
+use std::fs::{self, OpenOptions};
+use std::path::PathBuf;
+
+const DEPTH: usize = 10;
+const BUFFER_SIZE: usize = 32;
+
+/// Returns an empty directory that is unique to this test
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "merkle-proof-store-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn change_log_record(merkle_roll: &MerkleRoll<DEPTH, BUFFER_SIZE>) -> ChangeLogRecord {
+    let change_log = merkle_roll.get_change_log();
+    ChangeLogRecord::new(
+        change_log.root,
+        &change_log.path,
+        change_log.index,
+        merkle_roll.sequence_number,
+    )
+}
+
+/// Applies `num_ops` random appends and replaces to the roll, the reference tree and the store
+fn apply_random_ops(
+    merkle_roll: &mut MerkleRoll<DEPTH, BUFFER_SIZE>,
+    tree: &mut MerkleTree,
+    store: &mut ProofStore,
+    tree_size: &mut usize,
+    num_ops: usize,
+) {
+    let mut rng = thread_rng();
+    for _ in 0..num_ops {
+        let leaf = rng.gen::<Node>();
+        if *tree_size == 0 || rng.gen_range(0, 2) == 0 {
+            merkle_roll.append(leaf).unwrap();
+            tree.add_leaf(leaf, *tree_size);
+            *tree_size += 1;
+        } else {
+            let index = rng.gen_range(0, *tree_size);
+            merkle_roll
+                .set_leaf(
+                    tree.get_root(),
+                    tree.get_leaf(index),
+                    leaf,
+                    &tree.get_proof_of_leaf(index),
+                    index as u32,
+                )
+                .unwrap();
+            tree.add_leaf(leaf, index);
+        }
+        let root = store
+            .apply_changelog(&change_log_record(merkle_roll))
+            .unwrap();
+        assert_eq!(root, tree.get_root());
+    }
+}
+
+fn assert_matches_tree(store: &ProofStore, tree: &MerkleTree, tree_size: usize) {
+    assert_eq!(store.get_root().unwrap(), tree.get_root());
+    for i in 0..tree_size {
+        assert_eq!(store.get_leaf(i as u32).unwrap(), tree.get_leaf(i));
+        assert_eq!(
+            store.get_proof(i as u32).unwrap(),
+            tree.get_proof_of_leaf(i)
+        );
+    }
+}
+
+fn setup(dir: &PathBuf) -> (MerkleRoll<DEPTH, BUFFER_SIZE>, MerkleTree, ProofStore) {
+    let mut merkle_roll = MerkleRoll::<DEPTH, BUFFER_SIZE>::new();
+    let tree = MerkleTree::new(vec![EMPTY; 1 << DEPTH]);
+    let mut store = ProofStore::open(dir, DEPTH).unwrap();
+    merkle_roll.initialize().unwrap();
+    store
+        .apply_changelog(&change_log_record(&merkle_roll))
+        .unwrap();
+    (merkle_roll, tree, store)
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_apply_and_reopen() {
+    let dir = test_dir("reopen");
+    let (mut merkle_roll, mut tree, mut store) = setup(&dir);
+    let mut tree_size = 0;
+    apply_random_ops(&mut merkle_roll, &mut tree, &mut store, &mut tree_size, 200);
+    assert_matches_tree(&store, &tree, tree_size);
+
+    drop(store);
+    let mut store = ProofStore::open(&dir, DEPTH).unwrap();
+    assert_eq!(store.sequence_number(), Some(merkle_roll.sequence_number));
+    assert_matches_tree(&store, &tree, tree_size);
+
+    // Keep applying changelogs after a restart
+    apply_random_ops(&mut merkle_roll, &mut tree, &mut store, &mut tree_size, 50);
+    assert_matches_tree(&store, &tree, tree_size);
+
+    assert!(matches!(
+        ProofStore::open(&dir, DEPTH + 1),
+        Err(ProofStoreError::DepthMismatch { .. })
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_torn_write_is_dropped() {
+    let dir = test_dir("torn");
+    let (mut merkle_roll, mut tree, mut store) = setup(&dir);
+    let mut tree_size = 0;
+    apply_random_ops(&mut merkle_roll, &mut tree, &mut store, &mut tree_size, 20);
+    let sequence_number = store.sequence_number();
+    let root = store.get_root().unwrap();
+
+    // Simulate a crash while writing the next block
+    let len = fs::metadata(dir.join("nodes.log")).unwrap().len();
+    merkle_roll.append(thread_rng().gen::<Node>()).unwrap();
+    store
+        .apply_changelog(&change_log_record(&merkle_roll))
+        .unwrap();
+    drop(store);
+    let file = OpenOptions::new()
+        .write(true)
+        .open(dir.join("nodes.log"))
+        .unwrap();
+    file.set_len(len + 40).unwrap();
+
+    let store = ProofStore::open(&dir, DEPTH).unwrap();
+    assert_eq!(store.sequence_number(), sequence_number);
+    assert_eq!(store.get_root().unwrap(), root);
+    assert_eq!(fs::metadata(dir.join("nodes.log")).unwrap().len(), len);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_snapshot_restore_and_compact() {
+    let dir = test_dir("snapshot");
+    let restored_dir = test_dir("restored");
+    let (mut merkle_roll, mut tree, mut store) = setup(&dir);
+    let mut tree_size = 0;
+    apply_random_ops(&mut merkle_roll, &mut tree, &mut store, &mut tree_size, 200);
+
+    let snapshot_path = dir.join("snapshot");
+    store.snapshot(&snapshot_path).unwrap();
+    let mut restored = ProofStore::restore(&snapshot_path, &restored_dir).unwrap();
+    assert_eq!(restored.sequence_number(), store.sequence_number());
+    assert_matches_tree(&restored, &tree, tree_size);
+
+    let len = fs::metadata(dir.join("nodes.log")).unwrap().len();
+    store.compact().unwrap();
+    assert!(fs::metadata(dir.join("nodes.log")).unwrap().len() < len);
+    assert_eq!(store.sequence_number(), Some(merkle_roll.sequence_number));
+    assert_matches_tree(&store, &tree, tree_size);
+
+    // Both stores keep accepting changelogs
+    let mut restored_tree = MerkleTree::new(vec![EMPTY; 1 << DEPTH]);
+    for i in 0..tree_size {
+        restored_tree.add_leaf(tree.get_leaf(i), i);
+    }
+    let mut restored_roll = merkle_roll;
+    let mut restored_size = tree_size;
+    apply_random_ops(&mut merkle_roll, &mut tree, &mut store, &mut tree_size, 20);
+    apply_random_ops(
+        &mut restored_roll,
+        &mut restored_tree,
+        &mut restored,
+        &mut restored_size,
+        20,
+    );
+    assert_matches_tree(&store, &tree, tree_size);
+    assert_matches_tree(&restored, &restored_tree, restored_size);
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&restored_dir).unwrap();
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn test_rejects_out_of_order_changelogs() {
+    let dir = test_dir("out-of-order");
+    let (mut merkle_roll, _, mut store) = setup(&dir);
+    let mut rng = thread_rng();
+    merkle_roll.append(rng.gen::<Node>()).unwrap();
+    let first = change_log_record(&merkle_roll);
+    merkle_roll.append(rng.gen::<Node>()).unwrap();
+    let second = change_log_record(&merkle_roll);
+
+    assert!(matches!(
+        store.apply_changelog(&second),
+        Err(ProofStoreError::Replay(ReplayError::SequenceGap { .. }))
+    ));
+    store.apply_changelog(&first).unwrap();
+    store.apply_changelog(&second).unwrap();
+    assert_eq!(store.get_root().unwrap(), merkle_roll.get_change_log().root);
+    assert!(matches!(
+        store.get_proof(1 << DEPTH),
+        Err(ProofStoreError::LeafIndexOutOfBounds)
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
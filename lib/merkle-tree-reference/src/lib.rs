@@ -1,8 +1,5 @@
 use solana_program::keccak::hashv;
-use std::cell::RefCell;
-use std::collections::VecDeque;
-use std::iter::FromIterator;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
 
 pub type Node = [u8; 32];
 pub const EMPTY: Node = [0; 32];
@@ -18,6 +15,28 @@ pub const PADDING: usize = 32 - MAX_DEPTH;
 /// Used for node parity when hashing
 pub const MASK: usize = MAX_SIZE - 1;
 
+/// Prefix of the data hashed into a leaf in domain separated trees
+pub const LEAF_PREFIX: u8 = 0;
+
+/// Prefix of the children hashed into an internal node in domain separated trees
+pub const NODE_PREFIX: u8 = 1;
+
+/// Hashes leaf data into a leaf of a domain separated tree
+pub fn hash_leaf(vals: &[&[u8]]) -> Node {
+    let mut prefixed = vec![[LEAF_PREFIX].as_ref()];
+    prefixed.extend_from_slice(vals);
+    hashv(&prefixed).to_bytes()
+}
+
+/// Hashes two children into their parent, prefixing them with `NODE_PREFIX` if `domain_separated`
+fn hash_nodes(left: &Node, right: &Node, domain_separated: bool) -> Node {
+    if domain_separated {
+        hashv(&[&[NODE_PREFIX], left, right]).to_bytes()
+    } else {
+        hashv(&[left, right]).to_bytes()
+    }
+}
+
 /// Recomputes root of the Merkle tree from Node & proof
 pub fn recompute(mut leaf: Node, proof: &[Node], index: u32) -> Node {
     for (i, s) in proof.iter().enumerate() {
@@ -33,174 +52,184 @@ pub fn recompute(mut leaf: Node, proof: &[Node], index: u32) -> Node {
 }
 
 // Off-chain implentation to keep track of nodes
+//
+// Only nodes of non-empty subtrees are stored; every other node is `empty_node(level)`.
 pub struct MerkleTree {
-    pub leaf_nodes: Vec<Rc<RefCell<TreeNode>>>,
+    /// Non-empty nodes of each level, keyed by their index within the level.
+    /// Level 0 holds the leaves and level `depth` holds the root.
+    levels: Vec<HashMap<usize, Node>>,
+    /// Empty node for every level, from the leaves up to the root
+    empty_nodes: Vec<Node>,
+    num_leaves: usize,
+    /// Whether internal nodes are hashed after `NODE_PREFIX`
+    domain_separated: bool,
     pub root: Node,
 }
 
 impl MerkleTree {
     /// Calculates updated root from the passed leaves
+    ///
+    /// The tree is padded with empty leaves up to the next power of 2
     pub fn new(leaves: Vec<Node>) -> Self {
-        let mut leaf_nodes = vec![];
-        for (i, node) in leaves.iter().enumerate() {
-            let mut tree_node = TreeNode::new_empty(0, i as u128);
-            tree_node.node = *node;
-            leaf_nodes.push(Rc::new(RefCell::new(tree_node)));
+        Self::from_leaves(leaves, false)
+    }
+
+    /// Same as `new`, with internal nodes hashed after `NODE_PREFIX`.
+    /// Leaves are stored as given, see `hash_leaf`.
+    pub fn new_domain_separated(leaves: Vec<Node>) -> Self {
+        Self::from_leaves(leaves, true)
+    }
+
+    fn from_leaves(leaves: Vec<Node>, domain_separated: bool) -> Self {
+        let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+        let mut tree = Self::new_sparse(depth, leaves.len(), domain_separated);
+        for (i, leaf) in leaves.iter().enumerate() {
+            if *leaf != EMPTY {
+                tree.levels[0].insert(i, *leaf);
+            }
+        }
+        tree.build_root();
+        tree
+    }
+
+    /// Creates a tree with `1 << depth` empty leaves without materializing them
+    pub fn new_empty(depth: usize) -> Self {
+        Self::new_sparse(depth, 1 << depth, false)
+    }
+
+    /// Same as `new_empty`, with internal nodes hashed after `NODE_PREFIX`
+    pub fn new_empty_domain_separated(depth: usize) -> Self {
+        Self::new_sparse(depth, 1 << depth, true)
+    }
+
+    fn new_sparse(depth: usize, num_leaves: usize, domain_separated: bool) -> Self {
+        let mut empty_nodes = vec![EMPTY];
+        for _ in 0..depth {
+            let lower_empty = empty_nodes[empty_nodes.len() - 1];
+            empty_nodes.push(hash_nodes(&lower_empty, &lower_empty, domain_separated));
+        }
+        Self {
+            levels: vec![HashMap::new(); depth + 1],
+            root: empty_nodes[depth],
+            empty_nodes,
+            num_leaves,
+            domain_separated,
         }
-        let root = MerkleTree::build_root(&leaf_nodes);
-        Self { leaf_nodes, root }
-    }
-
-    /// Builds root from stack of leaves
-    pub fn build_root(leaves: &Vec<Rc<RefCell<TreeNode>>>) -> Node {
-        let mut tree = VecDeque::from_iter(leaves.iter().map(Rc::clone));
-        let mut seq_num = leaves.len() as u128;
-        while tree.len() > 1 {
-            let left = tree.pop_front().unwrap();
-            let level = left.borrow().level;
-            let right = if level != tree[0].borrow().level {
-                let node = Rc::new(RefCell::new(TreeNode::new_empty(level, seq_num)));
-                seq_num += 1;
-                node
-            } else {
-                tree.pop_front().unwrap()
-            };
-            let mut hashed_parent = EMPTY;
-
-            hashed_parent
-                .copy_from_slice(hashv(&[&left.borrow().node, &right.borrow().node]).as_ref());
-            let parent = Rc::new(RefCell::new(TreeNode::new(
-                hashed_parent,
-                left.clone(),
-                right.clone(),
-                level + 1,
-                seq_num,
-            )));
-            left.borrow_mut().assign_parent(parent.clone());
-            right.borrow_mut().assign_parent(parent.clone());
-            tree.push_back(parent);
-            seq_num += 1;
+    }
+
+    /// Builds every non-empty internal node from the stored leaves
+    fn build_root(&mut self) {
+        for level in 0..self.depth() {
+            let parents: HashSet<usize> = self.levels[level].keys().map(|i| i >> 1).collect();
+            for parent in parents {
+                let hashed_parent = self.hash_children(level, parent);
+                self.levels[level + 1].insert(parent, hashed_parent);
+            }
         }
+        self.root = self.get(self.depth(), 0);
+    }
 
-        let root = tree[0].borrow().node;
-        root
+    fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    fn get(&self, level: usize, idx: usize) -> Node {
+        match self.levels[level].get(&idx) {
+            Some(node) => *node,
+            None => self.empty_nodes[level],
+        }
     }
 
-    /// Traverses TreeNodes upwards to root from a Leaf TreeNode
-    /// hashing along the way
+    fn set(&mut self, level: usize, idx: usize, node: Node) {
+        if node == self.empty_nodes[level] {
+            self.levels[level].remove(&idx);
+        } else {
+            self.levels[level].insert(idx, node);
+        }
+    }
+
+    /// Hashes the children of node `idx` at `level + 1`
+    fn hash_children(&self, level: usize, idx: usize) -> Node {
+        let left = self.get(level, idx << 1);
+        let right = self.get(level, (idx << 1) + 1);
+        hash_nodes(&left, &right, self.domain_separated)
+    }
+
+    /// Traverses the tree upwards to root from a leaf
+    /// collecting the sibling at every level
     pub fn get_proof_of_leaf(&self, idx: usize) -> Vec<Node> {
-        let mut proof = vec![];
-        let mut node = self.leaf_nodes[idx].clone();
-        loop {
-            let ref_node = node.clone();
-            if ref_node.borrow().parent.is_none() {
-                break;
-            }
-            let parent = ref_node.borrow().parent.as_ref().unwrap().clone();
-            if parent.borrow().left.as_ref().unwrap().borrow().id == ref_node.borrow().id {
-                proof.push(parent.borrow().right.as_ref().unwrap().borrow().node);
-            } else {
-                proof.push(parent.borrow().left.as_ref().unwrap().borrow().node);
+        assert!(idx < self.num_leaves, "Leaf index out of bounds");
+        (0..self.depth())
+            .map(|level| self.get(level, (idx >> level) ^ 1))
+            .collect()
+    }
+
+    /// Collects the siblings needed to prove all of `leaf_indices` at once, skipping
+    /// the ones that can be computed from the proven leaves.
+    /// Nodes are ordered level by level from the leaves up, and by index within a level.
+    pub fn get_multiproof(&self, leaf_indices: &[usize]) -> Vec<Node> {
+        assert!(
+            leaf_indices.windows(2).all(|pair| pair[0] < pair[1]),
+            "Leaf indices must be strictly increasing"
+        );
+        if let Some(last) = leaf_indices.last() {
+            assert!(*last < self.num_leaves, "Leaf index out of bounds");
+        }
+        let mut nodes = vec![];
+        let mut indices = leaf_indices.to_vec();
+        for level in 0..self.depth() {
+            let mut parents = Vec::with_capacity(indices.len());
+            let mut k = 0;
+            while k < indices.len() {
+                let idx = indices[k];
+                if idx & 1 == 0 && indices.get(k + 1) == Some(&(idx + 1)) {
+                    k += 2;
+                } else {
+                    nodes.push(self.get(level, idx ^ 1));
+                    k += 1;
+                }
+                parents.push(idx >> 1);
             }
-            node = parent;
+            indices = parents;
         }
-        proof
+        nodes
     }
 
     /// Updates root from an updated leaf node set at index: `idx`
     fn update_root_from_leaf(&mut self, leaf_idx: usize) {
-        let mut node = self.leaf_nodes[leaf_idx].clone();
-        loop {
-            let ref_node = node.clone();
-            if ref_node.borrow().parent.is_none() {
-                self.root = ref_node.borrow().node;
-                break;
-            }
-            let parent = ref_node.borrow().parent.as_ref().unwrap().clone();
-            let hash = if parent.borrow().left.as_ref().unwrap().borrow().id == ref_node.borrow().id
-            {
-                hashv(&[
-                    &ref_node.borrow().node,
-                    &parent.borrow().right.as_ref().unwrap().borrow().node,
-                ])
-            } else {
-                hashv(&[
-                    &parent.borrow().left.as_ref().unwrap().borrow().node,
-                    &ref_node.borrow().node,
-                ])
-            };
-            node = parent;
-            node.borrow_mut().node.copy_from_slice(hash.as_ref());
+        for level in 0..self.depth() {
+            let parent = leaf_idx >> (level + 1);
+            let hashed_parent = self.hash_children(level, parent);
+            self.set(level + 1, parent, hashed_parent);
         }
+        self.root = self.get(self.depth(), 0);
     }
 
     pub fn get_node(&self, idx: usize) -> Node {
-        self.leaf_nodes[idx].borrow().node
+        self.get_leaf(idx)
     }
 
     pub fn get_root(&self) -> Node {
         self.root
     }
 
+    pub fn num_leaves(&self) -> usize {
+        self.num_leaves
+    }
+
     pub fn add_leaf(&mut self, leaf: Node, leaf_idx: usize) {
-        self.leaf_nodes[leaf_idx].borrow_mut().node = leaf;
+        assert!(leaf_idx < self.num_leaves, "Leaf index out of bounds");
+        self.set(0, leaf_idx, leaf);
         self.update_root_from_leaf(leaf_idx)
     }
 
     pub fn remove_leaf(&mut self, leaf_idx: usize) {
-        self.leaf_nodes[leaf_idx].borrow_mut().node = EMPTY;
-        self.update_root_from_leaf(leaf_idx)
+        self.add_leaf(EMPTY, leaf_idx)
     }
 
     pub fn get_leaf(&self, leaf_idx: usize) -> Node {
-        self.leaf_nodes[leaf_idx].borrow().node.clone()
-    }
-}
-
-#[derive(Clone)]
-pub struct TreeNode {
-    pub node: Node,
-    left: Option<Rc<RefCell<TreeNode>>>,
-    right: Option<Rc<RefCell<TreeNode>>>,
-    parent: Option<Rc<RefCell<TreeNode>>>,
-    level: u32,
-    /// ID needed to figure out whether we came from left or right child node
-    /// when hashing path upwards
-    id: u128,
-}
-
-impl TreeNode {
-    pub fn new(
-        node: Node,
-        left: Rc<RefCell<TreeNode>>,
-        right: Rc<RefCell<TreeNode>>,
-        level: u32,
-        id: u128,
-    ) -> Self {
-        Self {
-            node,
-            left: Some(left),
-            right: Some(right),
-            parent: None,
-            level,
-            id,
-        }
-    }
-
-    pub fn new_empty(level: u32, id: u128) -> Self {
-        Self {
-            node: empty_node(level),
-            left: None,
-            right: None,
-            parent: None,
-            level,
-            id,
-        }
-    }
-
-    /// Allows to propagate parent assignment
-    pub fn assign_parent(&mut self, parent: Rc<RefCell<TreeNode>>) {
-        self.parent = Some(parent);
+        assert!(leaf_idx < self.num_leaves, "Leaf index out of bounds");
+        self.get(0, leaf_idx)
     }
 }
 